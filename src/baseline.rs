@@ -0,0 +1,103 @@
+//! `--baseline`: compares this run against an earlier `--json` summary, so
+//! a device that got worse since then stands out.
+
+use crate::json::{self, Value};
+use crate::summary::{DeviceSummary, SCHEMA_VERSION};
+use std::fs;
+use std::path::Path;
+
+/// Relative growth of RMS or max |offset| above which a device regressed.
+pub const REGRESSION_FRACTION: f64 = 0.1;
+
+/// Drop in lock percentage, in percentage points, above which a device
+/// regressed.
+pub const LOCK_DROP_POINTS: f64 = 1.0;
+
+/// The figures compared for one device of the earlier run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baseline {
+    pub device: String,
+    pub rms: f64,
+    pub max_offset: f64,
+    /// `None` for devices without servo states, or for summaries written
+    /// before lock percentage was reported.
+    pub locked_fraction: Option<f64>,
+}
+
+/// Reads a `--json` summary file, or the bare device array written
+/// before the output had a schema version.
+pub fn load(path: &Path) -> Result<Vec<Baseline>, String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    from_json(&text)
+}
+
+pub fn from_json(text: &str) -> Result<Vec<Baseline>, String> {
+    let root = json::parse(text)?;
+    let devices = match root.get("devices") {
+        Some(devices) => {
+            let version = root.get("schema_version").and_then(Value::as_f64).ok_or("summary has no schema_version")?;
+            if version > SCHEMA_VERSION as f64 {
+                return Err(format!("summary has schema version {}, newer than the supported {}", version, SCHEMA_VERSION));
+            }
+            devices
+        }
+        None => &root,
+    };
+    let devices = devices.as_array().ok_or("expected a JSON array of devices")?;
+    devices
+        .iter()
+        .enumerate()
+        .map(|(index, device)| {
+            let field = |path: &[&str]| path.iter().try_fold(device, |value, key| value.get(key));
+            let number = |path: &[&str]| {
+                field(path)
+                    .and_then(Value::as_f64)
+                    .ok_or_else(|| format!("device {} has no numeric {}", index, path.join(".")))
+            };
+            Ok(Baseline {
+                device: field(&["device"])
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("device {} has no name", index))?
+                    .to_string(),
+                rms: number(&["offset", "rms"])?,
+                max_offset: number(&["abs_offset", "max"])?,
+                locked_fraction: field(&["locked_fraction"]).and_then(Value::as_f64),
+            })
+        })
+        .collect()
+}
+
+/// How a device changed since its baseline; every delta is now minus then.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub rms_delta: f64,
+    pub max_offset_delta: f64,
+    /// In percentage points; `None` unless both runs have servo states.
+    pub lock_delta: Option<f64>,
+    /// What got worse by more than the regression limits.
+    pub regressions: Vec<&'static str>,
+}
+
+impl Comparison {
+    pub fn new(summary: &DeviceSummary, baseline: &Baseline) -> Comparison {
+        let rms_delta = summary.offset.rms - baseline.rms;
+        let max_offset_delta = summary.abs_offset.max - baseline.max_offset;
+        let lock_delta = summary
+            .locked_fraction
+            .zip(baseline.locked_fraction)
+            .map(|(now, then)| (now - then) * 100.0);
+
+        let mut regressions = Vec::new();
+        if rms_delta > REGRESSION_FRACTION * baseline.rms.abs() {
+            regressions.push("RMS offset");
+        }
+        if max_offset_delta > REGRESSION_FRACTION * baseline.max_offset.abs() {
+            regressions.push("max |offset|");
+        }
+        if lock_delta.is_some_and(|delta| delta < -LOCK_DROP_POINTS) {
+            regressions.push("lock percentage");
+        }
+
+        Comparison { rms_delta, max_offset_delta, lock_delta, regressions }
+    }
+}
@@ -0,0 +1,126 @@
+//! `--bundle`: one directory holding a run's JSON summary, its plots and an
+//! `index.html` linking them, to hand over as a whole.
+
+use crate::export;
+use crate::html::escape_html;
+use crate::plot::{ImageFormat, PlotOptions};
+use crate::summary::DeviceSummary;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The JSON summary's file name inside the bundle.
+pub const SUMMARY_FILE: &str = "summary.json";
+
+/// The plot directory inside `dir`, where `--bundle` points `--out-dir`.
+pub fn plot_dir(dir: &Path) -> PathBuf {
+    dir.join("plots")
+}
+
+/// Writes `document` (the `--json` summary) to `dir/summary.json` and an
+/// `index.html` with a table of `summaries` and every plot found in the
+/// plot directory, under the device whose file name prefix it has or,
+/// like the comparison plots, under "Fleet". Values have `precision`
+/// fractional digits.
+pub fn write(dir: &Path, document: &str, summaries: &[DeviceSummary], plots: &PlotOptions, precision: usize) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(SUMMARY_FILE), format!("{}\n", document))?;
+
+    let mut sections: Vec<(&str, Vec<String>)> = summaries.iter().map(|summary| (summary.device.as_str(), Vec::new())).collect();
+    let mut fleet = Vec::new();
+    for file in plot_files(&plots.out_dir, plots.format)? {
+        let owner = summaries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, summary)| device_match(plots, &summary.device, &file).map(|length| (length, i)))
+            .max();
+        match owner {
+            Some((_, i)) => sections[i].1.push(file),
+            None => fleet.push(file),
+        }
+    }
+    if !fleet.is_empty() {
+        sections.push(("Fleet", fleet));
+    }
+
+    let mut out = BufWriter::new(File::create(dir.join("index.html"))?);
+    writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>PTP Statistics</title>")?;
+    writeln!(out, "<style>\n  body {{ font-family: sans-serif; margin: 20px; }}\n  table {{ border-collapse: collapse; }}\n  th, td {{ border: 1px solid #ccc; padding: 4px 8px; }}\n  td {{ text-align: right; }}\n  td:first-child {{ text-align: left; }}\n  img {{ max-width: 100%; margin: 4px 0; }}\n</style>\n</head>\n<body>")?;
+    writeln!(out, "<h1>PTP Statistics</h1>")?;
+    writeln!(out, "<p>Generated by {} {}. Full statistics: <a href=\"{}\">{}</a>.</p>", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), SUMMARY_FILE, SUMMARY_FILE)?;
+
+    let unit = escape_html(plots.unit.label());
+    let scale = plots.unit.scale();
+    let delay = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{:.precision$}", value / scale));
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Device</th><th>Samples</th><th>Mean Offset ({unit})</th><th>Max |Offset| ({unit})</th><th>RMS Offset ({unit})</th><th>Mean Delay ({unit})</th><th>Max Delay ({unit})</th></tr>"
+    )?;
+    for summary in summaries {
+        writeln!(
+            out,
+            "<tr><td><a href=\"#{}\">{}</a></td><td>{}</td><td>{:.precision$}</td><td>{:.precision$}</td><td>{:.precision$}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&export::file_stem(&summary.device)),
+            escape_html(&summary.device),
+            summary.samples,
+            summary.offset.mean / scale,
+            summary.abs_offset.max / scale,
+            summary.offset.rms / scale,
+            delay(summary.delay.as_ref().map(|delay| delay.mean)),
+            delay(summary.delay.as_ref().map(|delay| delay.max)),
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    let plot_dir = plots.out_dir.strip_prefix(dir).unwrap_or(&plots.out_dir);
+    for (name, files) in sections.iter().filter(|(_, files)| !files.is_empty()) {
+        writeln!(out, "<h2 id=\"{}\">{}</h2>", escape_html(&export::file_stem(name)), escape_html(name))?;
+        for file in files {
+            let link = escape_html(&plot_dir.join(file).to_string_lossy().replace('\\', "/"));
+            if plots.format == ImageFormat::Html {
+                writeln!(out, "<p><a href=\"{}\">{}</a></p>", link, escape_html(file))?;
+            } else {
+                writeln!(out, "<div><img src=\"{}\" alt=\"{}\"></div>", link, escape_html(file))?;
+            }
+        }
+    }
+    writeln!(out, "</body>\n</html>")?;
+    out.flush()
+}
+
+/// The file names in `dir` with `format`'s extension, sorted; none if no
+/// plots were drawn.
+fn plot_files(dir: &Path, format: ImageFormat) -> io::Result<Vec<String>> {
+    if format == ImageFormat::Ascii || !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path.extension().is_some_and(|extension| extension == format.extension())
+            && let Some(name) = path.file_name().and_then(|name| name.to_str())
+        {
+            files.push(name.to_string());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `file` is one of `device`'s plots, and if so how much of its
+/// name says so, for the longest match to win between devices such as
+/// `gm` and `gm-2`. A device's plots start with the file name template up
+/// to `{type}`, as [`PlotOptions::path`] fills it in; its HTML page is the
+/// template with no type. A template that starts with `{type}` tells no
+/// devices apart.
+fn device_match(plots: &PlotOptions, device: &str, file: &str) -> Option<usize> {
+    if plots.path(device, "").file_name().is_some_and(|page| page == file) {
+        return Some(file.len());
+    }
+    let before_type = plots.file_template.split("{type}").next().unwrap_or_default();
+    let prefix = export::file_stem(&before_type.replace("{device}", device));
+    let prefix = prefix.trim_start_matches(['-', '_', '.']);
+    (!prefix.is_empty() && file.starts_with(prefix)).then_some(prefix.len())
+}
@@ -0,0 +1,189 @@
+use crate::json;
+use crate::summary::DeviceSummary;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Where a value sits relative to its limit, for colorized output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Good,
+    /// Within the limit but above `WARNING_FRACTION` of it.
+    Warning,
+    Bad,
+}
+
+/// Fraction of a limit above which a passing value is still a warning.
+const WARNING_FRACTION: f64 = 0.8;
+
+fn level(value: f64, limit: Option<f64>) -> Option<Level> {
+    let limit = limit?;
+    Some(if value > limit {
+        Level::Bad
+    } else if value > WARNING_FRACTION * limit {
+        Level::Warning
+    } else {
+        Level::Good
+    })
+}
+
+/// Pass/fail limits for a run. Unset limits are not checked.
+#[derive(Debug, Clone, Default)]
+pub struct Thresholds {
+    pub max_offset_ns: Option<f64>,
+    /// An acceptance mask on max |offset|, reported as the margin left
+    /// under it.
+    pub mask_ns: Option<f64>,
+    pub max_delay_ns: Option<f64>,
+    /// Highest acceptable path delay std dev (see
+    /// [`DeviceSummary::delay_jitter`]).
+    pub max_delay_jitter_ns: Option<f64>,
+    /// Lowest acceptable share of locked samples, in percent.
+    pub min_lock_pct: Option<f64>,
+}
+
+impl Thresholds {
+    /// Level of the device's max |offset|, `None` without an offset limit.
+    pub fn offset_level(&self, summary: &DeviceSummary) -> Option<Level> {
+        level(summary.abs_offset.max, self.max_offset_ns)
+    }
+
+    /// How far the device's max |offset| stayed under the mask; negative
+    /// when it broke through. `None` without a mask.
+    pub fn mask_margin(&self, summary: &DeviceSummary) -> Option<f64> {
+        Some(self.mask_ns? - summary.abs_offset.max)
+    }
+
+    /// Level of the device's max |offset| against the mask, `None` without
+    /// one.
+    pub fn mask_level(&self, summary: &DeviceSummary) -> Option<Level> {
+        level(summary.abs_offset.max, self.mask_ns)
+    }
+
+    /// Level of the device's max path delay, `None` without a delay limit
+    /// or delays.
+    pub fn delay_level(&self, summary: &DeviceSummary) -> Option<Level> {
+        level(summary.delay.as_ref()?.max, self.max_delay_ns)
+    }
+
+    /// Level of the device's delay jitter, `None` without a jitter limit
+    /// or delays.
+    pub fn jitter_level(&self, summary: &DeviceSummary) -> Option<Level> {
+        level(summary.delay_jitter()?, self.max_delay_jitter_ns)
+    }
+
+    /// Level of the device's locked share: only good or bad, since there is
+    /// no sensible warning band below 100%. `None` without a lock limit or
+    /// servo states.
+    pub fn lock_level(&self, summary: &DeviceSummary) -> Option<Level> {
+        let pct = summary.locked_fraction? * 100.0;
+        let limit = self.min_lock_pct?;
+        Some(if pct < limit { Level::Bad } else { Level::Good })
+    }
+
+    /// Returns a description of every limit the device exceeds.
+    pub fn violations(&self, summary: &DeviceSummary) -> Vec<String> {
+        self.breaches(summary).into_iter().map(|breach| breach.message).collect()
+    }
+
+    /// Every limit the device exceeds, with which one it is.
+    pub fn breaches(&self, summary: &DeviceSummary) -> Vec<Breach> {
+        let mut breaches = Vec::new();
+
+        if let Some(limit) = self.max_offset_ns
+            && summary.abs_offset.max > limit
+        {
+            breaches.push(Breach::new("max_offset_ns", format!("max |offset| {:.2} ns exceeds {:.2} ns", summary.abs_offset.max, limit)));
+        }
+
+        if let (Some(mask), Some(margin)) = (self.mask_ns, self.mask_margin(summary))
+            && margin < 0.0
+        {
+            breaches.push(Breach::new("mask_ns", format!("max |offset| {:.2} ns is {:.2} ns over the {:.2} ns mask", summary.abs_offset.max, -margin, mask)));
+        }
+
+        if let (Some(limit), Some(delay)) = (self.max_delay_ns, &summary.delay)
+            && delay.max > limit
+        {
+            breaches.push(Breach::new("max_delay_ns", format!("max delay {:.2} ns exceeds {:.2} ns", delay.max, limit)));
+        }
+
+        if let (Some(limit), Some(jitter)) = (self.max_delay_jitter_ns, summary.delay_jitter())
+            && jitter > limit
+        {
+            breaches.push(Breach::new("max_delay_jitter_ns", format!("delay jitter {:.2} ns exceeds {:.2} ns", jitter, limit)));
+        }
+
+        if let (Some(limit), Some(fraction)) = (self.min_lock_pct, summary.locked_fraction)
+            && fraction * 100.0 < limit
+        {
+            breaches.push(Breach::new("min_lock_pct", format!("locked {:.1}% of samples, below {:.1}%", fraction * 100.0, limit)));
+        }
+
+        breaches
+    }
+}
+
+/// A limit a device exceeded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breach {
+    /// The limit's name, as in `--max-offset-ns` without the dashes:
+    /// `max_offset_ns`, `mask_ns`, `max_delay_ns`, `max_delay_jitter_ns`
+    /// or `min_lock_pct`.
+    pub threshold: &'static str,
+    pub message: String,
+}
+
+impl Breach {
+    fn new(threshold: &'static str, message: String) -> Breach {
+        Breach { threshold, message }
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new().string("threshold", self.threshold).string("message", &self.message).finish()
+    }
+}
+
+/// The outcome of a run for `--status-file`: whether it succeeded, with
+/// each device's verdict, for tools deciding whether to go ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunStatus {
+    /// Devices in report order with the limits each exceeded; a device
+    /// passed when it exceeded none.
+    pub devices: Vec<(String, Vec<Breach>)>,
+    /// Logs that could not be read.
+    pub unreadable_logs: Vec<String>,
+    /// Logs with a line `--strict` rejected.
+    pub nonconforming_logs: usize,
+    /// Logs Ctrl-C stopped before they were read and reported.
+    pub unfinished_logs: Vec<String>,
+}
+
+impl RunStatus {
+    /// Whether the run exits with status 0.
+    pub fn success(&self) -> bool {
+        self.devices.iter().all(|(_, breaches)| breaches.is_empty()) && self.unreadable_logs.is_empty() && self.nonconforming_logs == 0 && self.unfinished_logs.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        let devices = self.devices.iter().map(|(device, breaches)| {
+            json::Object::new()
+                .string("device", device)
+                .raw("passed", breaches.is_empty().to_string())
+                .raw("breaches", json::array(breaches.iter().map(Breach::to_json)))
+                .finish()
+        });
+        json::Object::new()
+            .raw("success", self.success().to_string())
+            .raw("devices", json::array(devices))
+            .raw("unreadable_logs", json::array(self.unreadable_logs.iter().map(|path| json::string(path))))
+            .integer("nonconforming_logs", self.nonconforming_logs)
+            .raw("unfinished_logs", json::array(self.unfinished_logs.iter().map(|name| json::string(name))))
+            .finish()
+    }
+
+    /// Writes the status as JSON to `path`.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json() + "\n")
+    }
+}
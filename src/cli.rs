@@ -0,0 +1,1002 @@
+use chrono::{DateTime, FixedOffset};
+use crate::bundle;
+use crate::check::Thresholds;
+use crate::config;
+use crate::demo;
+use crate::detrend::Detrend;
+use crate::glob;
+use crate::parser::{InputFormat, LogFormat, TimeWindow, STDIN_PATH};
+use crate::plot::{parse_hex_color, Grid, ImageFormat, PlotColors, PlotOptions, XAxis, DEFAULT_FILE_TEMPLATE, DEFAULT_TITLE, FILE_TEMPLATE_PLACEHOLDERS};
+use crate::summary::{Metric, RankBy, METRICS};
+use crate::timing::Warmup;
+use crate::unit::Unit;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+Usage: ptp-stats [OPTIONS] [NAME=]PATH...
+
+Arguments:
+  [NAME=]PATH      Log file to analyze, or - for stdin. The device name
+                   defaults to the file stem (stdin for -) unless given
+                   as NAME=PATH. NAME=PATH,PATH... reads the parts of a
+                   rotated log as one, oldest first by their first
+                   timestamps.
+
+Options:
+  --dir <DIR>      Analyze every file in DIR
+  --config <FILE>  Analyze the [[machine]] entries (name, path and optional
+                   max_offset_ns and grandmaster) of a TOML file
+  --glob <PATTERN> Analyze every file matching PATTERN, with *, ?, [...]
+                   and ** for any depth of directories, e.g. 'logs/*.log'
+                   or 'logs/**/ptp[0-9].log'
+  --demo           Analyze made-up logs of a few devices, with noise, a
+                   drift and a spike, written to a temporary directory, to
+                   try the tool without a real log
+  --limit <N>      Analyze only the first N logs after expanding --dir,
+                   --config and --glob, for a quick trial run
+  --only <NAME>    Analyze only the device named NAME, exactly; repeatable
+  --skip <NAME>    Leave out the device named NAME, exactly; repeatable
+  --csv <OUTDIR>   Write one CSV of parsed samples per device to OUTDIR
+  --out-dir <DIR>  Directory for plot images, created if missing [default: plots]
+  --out-template <TEMPLATE>
+                   Plot file name without the extension, with {device},
+                   {type} and {date} (the run's date, YYYY-MM-DD) replaced,
+                   e.g. {date}-{device}-{type} to keep earlier runs' plots
+                   [default: {device}-{type}]
+  --no-plots       Skip writing plots; only print and export statistics
+  --format <FMT>   Plot format: png, svg, html for one interactive page per
+                   device, or ascii for an offset chart printed with each
+                   device's stats instead of any files [default: png]
+  --width <PX>     Plot image width [default: 640]
+  --height <PX>    Plot image height [default: 480]
+  --max-points <N> Most points drawn per plot series; longer series keep each
+                   bucket's min and max [default: twice the width]
+  --x-axis <AXIS>  X axis of time plots: index, time, elapsed seconds since
+                   the first sample, from ptp4l's monotonic timestamps when
+                   present (for logs whose wall clock was unreliable), or
+                   internal for those timestamps as logged, to sub-second
+                   precision [default: time]
+  --grid <GRID>    Grid lines behind each plot: full, coarse for only the
+                   lines at tick labels, or none [default: full]
+  --label-size <PX>
+                   Font size of plot tick labels and axis descriptions
+  --color <KIND=HEX>
+                   Line color for offset, delay or freq plots, e.g.
+                   delay=#1f77b4; repeatable [default: offset=#ff0000,
+                   delay=#0000ff, freq=#009600]
+  --opacity <ALPHA>
+                   Opacity of each line on the comparison plots, above 0 up
+                   to 1, so many overlapping devices show up as darker
+                   bands where they agree [default: 1]
+  --y-min <V>, --y-max <V>
+                   Fix the offset axis of the offset, combined and
+                   comparison plots, in --unit, so devices share one scale
+  --plot-title <TEMPLATE>
+                   Plot caption, with {device} and {type} replaced by the
+                   device name and plot type [default: {device} {type}]
+  --no-title       Draw plots without a caption
+  --subtitle       Add a line under each device plot's caption with its
+                   sample count, time span and RMS offset
+  --contact-sheet <COLS>
+                   Also draw every device's offset plot as a labeled tile
+                   in one contact-sheet image, COLS tiles to a row
+  --box-plot       Also draw one box and whiskers per device, of its offset
+                   quartiles and outliers, in one comparison-box-plot image
+  --diff <A>,<B>   Also plot device A's offset minus device B's, aligned on
+                   timestamps, e.g. a candidate against a known-good
+                   reference. Devices are named as in the stats output
+  --smooth <N>     Draw an N-sample moving average over the offset, delay
+                   and freq plots
+  --unit <UNIT>    Unit for offsets and delays: ns, us or ms [default: ns]
+  --input-unit <UNIT>
+                   Unit the log's offsets and delays are written in, for
+                   logs converted before logging: ns, us or ms. Limits
+                   such as --max-offset-ns stay in ns [default: ns]
+  --input-format <FMT>
+                   How log lines are read: text by the line patterns, json
+                   for one JSON object per line with offset, delay and
+                   optionally timestamp, internal_ts, freq, servo_state and
+                   source fields (as --jsonl writes them), or auto for JSON
+                   objects as json and other lines as text [default: auto]
+  --precision <N>  Digits after the decimal point in printed offsets, delays
+                   and frequencies [default: 2]
+  --metrics <LIST> Comma-separated stats to print in each block, in order:
+                   mean, rms, median, p95, p99, min, max, peak_to_peak,
+                   std_dev, skewness, kurtosis [default: all]
+  --from <TIME>    Ignore samples before this RFC3339 timestamp
+  --to <TIME>      Ignore samples after this RFC3339 timestamp
+  --tail <N>       Analyze only each log's last N samples (after --from/--to)
+  --skip-warmup <N>
+                   Drop each device's first N samples, while ptp4l is still
+                   converging, before computing stats and plotting
+  --skip-warmup-secs <SECS>
+                   Drop each device's samples from its first SECS seconds
+  --strict         Fail, naming the line, if any non-blank line of a log is
+                   neither a sample nor a ptp4l event, and fail devices
+                   with a negative path delay rather than only warning
+  --read-timeout <SECS>
+                   Give up on a log not read within SECS, e.g. on a stalled
+                   network mount, reporting it as timed out and going on
+                   with the others
+  --min-samples <N>
+                   Skip stats and plots for devices with fewer samples
+                   [default: 10]
+  --pattern <RE>   Custom line regex with named groups offset and delay, and
+                   optionally timestamp, freq, state and internal_ts
+  --log-format <PRESET>
+                   Read only one kind of sample line instead of all the
+                   builtin ones: ptp4l-syslog (ptp4l with a syslog prefix),
+                   ptp4l-bare (without one), phc2sys or summary (ptp4l's
+                   summary_interval lines)
+  --exclude <RE>   Drop lines matching RE before parsing, e.g. debug lines
+                   that look like samples; they are counted separately
+  --bins <N>       Number of offset histogram bins [default: 50]
+  --fit-normal     Draw the normal curve of the offset mean and std dev over
+                   the histogram, to judge whether the noise is Gaussian
+  --mark-shifts    Draw a line on the offset plot at each detected step in
+                   the offset's mean, as listed under Mean shifts
+  --highlight-sigma <N>
+                   Dot the samples on the offset plot more than N standard
+                   deviations from the mean offset, to spot anomalies
+  --window <N>     Samples per rolling std dev window [default: 60]
+  --tau0 <SECS>    Sampling interval used for Allan deviation, and for the
+                   offset spectrum of logs without timestamps [default: 1]
+  --sample-rate <HZ>
+                   The rate the log was written at, for logs whose
+                   timestamps are too coarse to tell. Sets --tau0 to 1/HZ
+                   and the offset spectrum's interval to match, and is the
+                   rate completeness is judged against
+  --resample       Interpolate offsets linearly onto a uniform --sample-rate
+                   grid before computing Allan deviation, MTIE and the
+                   spectrum, for logs with irregular intervals
+  --sort-by-time   Sort each daemon's samples in a log by timestamp before
+                   analysis, for merged logs with duplicate or out-of-order
+                   timestamps
+  --gap-factor <N> Report gaps longer than N median sample intervals [default: 3]
+  --delay-spikes <N>
+                   List each device's N largest path delays with the offset
+                   at the same sample
+  --drop-outliers  Also report offset stats with MAD-rule outliers removed
+  --detrend <METHOD>
+                   Also report offset stats, and plot the offset, with slow
+                   drift removed: linear subtracts a fitted line, a number
+                   N a centered N-sample moving average
+  --time-weighted  Also report the offset mean and std dev with each sample
+                   weighted by the time until the next, for logs whose
+                   interval varies
+  --hourly         Also print the offset mean and max |offset| of each clock
+                   hour, to show time-of-day patterns in long captures
+  --settle-ns <NS> Report how long after the start of the log |offset| came
+                   within NS for good, e.g. after a restart or failover
+  --trend-ns-per-hour <NS>
+                   Steepest change of |offset| over the log, in ns per
+                   hour, still reported as a stable trend rather than
+                   improving or degrading [default: 10]
+  --baseline <FILE>
+                   Compare RMS and max |offset| and lock percentage with an
+                   earlier --json summary, flagging devices that got worse
+  --top <N>        Print the N worst devices at the end of the run
+  --rank-by <KEY>  What --top ranks by: rms, max (|offset|), jitter (delay
+                   std dev) or lock (lowest lock percentage first)
+                   [default: rms]
+  --correlation    Print the pairwise correlation of device offsets, aligned
+                   on timestamps, to tell fleet-wide swings from local ones.
+                   Devices on different grandmasters, as last selected in
+                   their logs, get separate matrices, as they always get
+                   separate comparison plots
+  --max-offset-ns <NS>
+                   Fail if any device's max |offset| exceeds NS. On a
+                   terminal the max is colored green, yellow (above 80%
+                   of NS) or red; set NO_COLOR to disable
+  --mask-ns <NS>   Report each device's margin under an acceptance mask of
+                   NS on max |offset|, failing devices that break it
+  --max-delay-ns <NS>
+                   Fail if any device's max path delay exceeds NS, colored
+                   the same way
+  --max-delay-jitter-ns <NS>
+                   Fail if any device's path delay std dev exceeds NS,
+                   colored the same way
+  --min-lock-pct <PCT>
+                   Fail if a device spent less than PCT% of samples locked
+                   (servo state s2)
+  --streaming      Compute mean, RMS, min, max and std dev on the fly without
+                   keeping samples in memory. Skips plots, percentiles and
+                   sample intervals (see --reservoir); incompatible with --csv,
+                   --drop-outliers, --detrend, --time-weighted, --hourly,
+                   --settle-ns, --delay-spikes, --correlation,
+                   --sort-by-time, --skip-warmup and --diff
+  --reservoir <N>  With --streaming, also keep a uniform random sample of N
+                   samples per device, to estimate the median and
+                   percentiles from and draw the offset histogram
+  --report <FILE>  Write a Markdown report of all devices to FILE
+  --bundle <DIR>   Write the --json summary to DIR/summary.json and the plots
+                   to DIR/plots, with an index.html showing every device's
+                   stats and plots, for one folder to hand over as a whole
+  --text-out <DIR> Also write each device's stats block to DIR/{device}.txt
+  --prom <FILE>    Write per-device metrics to FILE for the Prometheus
+                   textfile collector
+  --status-file <FILE>
+                   Write the run's outcome to FILE as JSON: overall success,
+                   and each device's pass or fail with the limits exceeded
+  --json           Print per-device statistics as JSON instead of text:
+                   an object with schema_version, the tool version, run
+                   time and arguments, and a devices array. Values are
+                   always in nanoseconds
+  --follow         Keep reading a single log as it grows and redraw its stats
+                   every --interval seconds, without plots, until
+                   interrupted. Honors --tail to show only recent samples.
+                   A named pipe is kept open when its writer closes it, so
+                   a later writer's lines are read too; a regular file is
+                   re-read from the start if it shrinks
+  --interval <SECS>
+                   Seconds between --follow updates [default: 10]
+  --check          Only check that every log can be read and that its first
+                   1000 lines contain samples; writes nothing
+  --jsonl          Print each sample as a JSON object on its own line as soon
+                   as it is read, e.g. to follow a live log from stdin,
+                   instead of computing statistics and plots
+  --influx <FILE>  Like --jsonl, but write InfluxDB line protocol to FILE, or
+                   to stdout for -; samples without a timestamp are left out
+  -q, --quiet      Print only a one-line-per-device summary table, with no
+                   progress line on stderr
+  -v, --verbose    Print parsing diagnostics on stderr, such as how many
+                   lines of each log matched; -vv also prints every line
+                   that did not
+  -h, --help       Print this help";
+
+#[derive(Debug, Clone)]
+pub struct Machine {
+    pub name: String,
+    /// The log, or the parts of one rotated across several files (given as
+    /// `NAME=PATH,PATH...`) in any order.
+    pub paths: Vec<PathBuf>,
+    /// Per-machine override of `--max-offset-ns`, from a config file.
+    pub max_offset_ns: Option<f64>,
+    /// The grandmaster's clock identity from a config file, for logs that
+    /// don't record the grandmaster being selected.
+    pub grandmaster: Option<String>,
+}
+
+impl Machine {
+    /// A machine with a single log file.
+    pub fn new(name: String, path: PathBuf) -> Machine {
+        Machine { name, paths: vec![path], max_offset_ns: None, grandmaster: None }
+    }
+
+    /// The log's paths, comma-separated, for messages.
+    pub fn display_paths(&self) -> String {
+        let paths: Vec<String> = self.paths.iter().map(|path| path.display().to_string()).collect();
+        paths.join(", ")
+    }
+}
+
+/// Output path argument that writes to standard output instead of a file.
+pub const STDOUT_PATH: &str = "-";
+
+#[derive(Debug)]
+pub struct Args {
+    pub machines: Vec<Machine>,
+    /// `--demo`: `machines` include the demo devices, whose logs are
+    /// written before parsing.
+    pub demo: bool,
+    /// `--limit`: how many of `machines` were kept.
+    pub limit: Option<usize>,
+    /// `--only` and `--skip`: device names kept or dropped from
+    /// `machines` before `--limit` applies.
+    pub only: Vec<String>,
+    pub skip: Vec<String>,
+    pub csv: Option<PathBuf>,
+    pub plot_options: PlotOptions,
+    pub window: TimeWindow,
+    pub tail: Option<usize>,
+    pub warmup: Option<Warmup>,
+    pub min_samples: usize,
+    pub strict: bool,
+    /// Fractional digits of printed stats.
+    pub precision: usize,
+    pub input_unit: Unit,
+    pub input_format: InputFormat,
+    /// The lines of each stats block, in order.
+    pub metrics: Vec<&'static Metric>,
+    pub pattern: Option<Regex>,
+    pub log_format: Option<LogFormat>,
+    pub exclude: Option<Regex>,
+    pub bins: usize,
+    pub fit_normal: bool,
+    pub mark_shifts: bool,
+    pub window_size: usize,
+    pub tau0: f64,
+    /// `--sample-rate` in Hz; `tau0` is set from it.
+    pub sample_rate: Option<f64>,
+    pub resample: bool,
+    pub sort_by_time: bool,
+    pub gap_factor: f64,
+    pub drop_outliers: bool,
+    pub detrend: Option<Detrend>,
+    pub time_weighted: bool,
+    pub hourly: bool,
+    pub settle_ns: Option<f64>,
+    pub trend_ns_per_hour: f64,
+    pub delay_spikes: Option<usize>,
+    pub correlation: bool,
+    pub no_plots: bool,
+    /// Tiles per row of the `--contact-sheet` image, when requested.
+    pub contact_sheet: Option<usize>,
+    pub box_plot: bool,
+    /// `--subtitle`: device plots carry a line of run metadata.
+    pub subtitle: bool,
+    /// `--diff`: the device plotted against, and the reference.
+    pub diff: Option<(String, String)>,
+    pub top: Option<usize>,
+    pub baseline: Option<PathBuf>,
+    pub rank_by: RankBy,
+    pub thresholds: Thresholds,
+    pub streaming: bool,
+    pub reservoir: Option<usize>,
+    pub report: Option<PathBuf>,
+    pub bundle: Option<PathBuf>,
+    pub text_out: Option<PathBuf>,
+    pub prom: Option<PathBuf>,
+    pub status_file: Option<PathBuf>,
+    pub json: bool,
+    pub jsonl: bool,
+    /// `--influx` destination; [`STDOUT_PATH`] for stdout.
+    pub influx: Option<PathBuf>,
+    pub check: bool,
+    pub follow: bool,
+    /// Seconds between `--follow` updates, when given.
+    pub interval: Option<f64>,
+    pub read_timeout: Option<f64>,
+    pub quiet: bool,
+    /// `-v` count: 1 for debug diagnostics, 2 or more for trace.
+    pub verbosity: u8,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            machines: Vec::new(),
+            demo: false,
+            limit: None,
+            only: Vec::new(),
+            skip: Vec::new(),
+            csv: None,
+            plot_options: PlotOptions {
+                out_dir: PathBuf::from("plots"),
+                format: ImageFormat::Png,
+                unit: Unit::Ns,
+                width: 640,
+                height: 480,
+                max_points: None,
+                smooth: None,
+                x_axis: XAxis::Time,
+                grid: Grid::Full,
+                label_size: None,
+                colors: PlotColors::default(),
+                opacity: 1.0,
+                y_min: None,
+                y_max: None,
+                highlight_sigma: None,
+                title: Some(DEFAULT_TITLE.to_string()),
+                subtitle: None,
+                file_template: DEFAULT_FILE_TEMPLATE.to_string(),
+            },
+            window: TimeWindow::default(),
+            tail: None,
+            warmup: None,
+            min_samples: 10,
+            strict: false,
+            precision: 2,
+            input_unit: Unit::Ns,
+            input_format: InputFormat::Auto,
+            metrics: METRICS.iter().collect(),
+            pattern: None,
+            log_format: None,
+            exclude: None,
+            bins: 50,
+            fit_normal: false,
+            mark_shifts: false,
+            window_size: 60,
+            tau0: 1.0,
+            sample_rate: None,
+            resample: false,
+            sort_by_time: false,
+            gap_factor: 3.0,
+            drop_outliers: false,
+            detrend: None,
+            time_weighted: false,
+            hourly: false,
+            settle_ns: None,
+            trend_ns_per_hour: 10.0,
+            delay_spikes: None,
+            correlation: false,
+            no_plots: false,
+            contact_sheet: None,
+            box_plot: false,
+            subtitle: false,
+            diff: None,
+            top: None,
+            baseline: None,
+            rank_by: RankBy::Rms,
+            thresholds: Thresholds::default(),
+            streaming: false,
+            reservoir: None,
+            report: None,
+            bundle: None,
+            text_out: None,
+            prom: None,
+            status_file: None,
+            json: false,
+            jsonl: false,
+            influx: None,
+            check: false,
+            follow: false,
+            interval: None,
+            read_timeout: None,
+            quiet: false,
+            verbosity: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    Help,
+    Usage(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{}", USAGE),
+            CliError::Usage(msg) => write!(f, "{}\n\n{}", msg, USAGE),
+        }
+    }
+}
+
+impl Args {
+    /// Whether the per-device stats blocks are printed, as opposed to a
+    /// JSON document or the `--quiet` table.
+    pub fn detailed(&self) -> bool {
+        !self.json && !self.quiet
+    }
+
+    /// The limits for one machine: the command-line thresholds with any
+    /// per-machine override from a config file applied.
+    pub fn thresholds_for(&self, machine: &Machine) -> Thresholds {
+        Thresholds {
+            max_offset_ns: machine.max_offset_ns.or(self.thresholds.max_offset_ns),
+            ..self.thresholds.clone()
+        }
+    }
+
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, CliError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        let mut out_dir_given = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Err(CliError::Help),
+                "--dir" => {
+                    let dir = next_value(&mut args, "--dir")?;
+                    parsed.machines.extend(machines_in_dir(Path::new(&dir))?);
+                }
+                "--config" => {
+                    let path = next_value(&mut args, "--config")?;
+                    parsed.machines.extend(config::load(Path::new(&path)).map_err(CliError::Usage)?);
+                }
+                "--glob" => {
+                    let pattern = next_value(&mut args, "--glob")?;
+                    parsed.machines.extend(machines_matching(&pattern)?);
+                }
+                "--demo" => {
+                    parsed.demo = true;
+                    parsed.machines.extend(demo::machines(&demo::dir()));
+                }
+                "--limit" => parsed.limit = Some(parse_number(&mut args, "--limit")?),
+                "--only" => parsed.only.push(next_value(&mut args, "--only")?),
+                "--skip" => parsed.skip.push(next_value(&mut args, "--skip")?),
+                "--csv" => parsed.csv = Some(PathBuf::from(next_value(&mut args, "--csv")?)),
+                "--out-dir" => {
+                    parsed.plot_options.out_dir = PathBuf::from(next_value(&mut args, "--out-dir")?);
+                    out_dir_given = true;
+                }
+                "--out-template" => parsed.plot_options.file_template = parse_file_template(&next_value(&mut args, "--out-template")?)?,
+                "--format" => {
+                    parsed.plot_options.format = match next_value(&mut args, "--format")?.as_str() {
+                        "png" => ImageFormat::Png,
+                        "svg" => ImageFormat::Svg,
+                        "html" => ImageFormat::Html,
+                        "ascii" => ImageFormat::Ascii,
+                        other => return Err(CliError::Usage(format!("unknown --format '{}' (expected png, svg, html or ascii)", other))),
+                    }
+                }
+                "--width" => parsed.plot_options.width = parse_number(&mut args, "--width")?,
+                "--height" => parsed.plot_options.height = parse_number(&mut args, "--height")?,
+                "--grid" => {
+                    let value = next_value(&mut args, "--grid")?;
+                    parsed.plot_options.grid = value
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("unknown --grid '{}' (expected full, coarse or none)", value)))?;
+                }
+                "--label-size" => parsed.plot_options.label_size = Some(parse_number(&mut args, "--label-size")?),
+                "--x-axis" => {
+                    let value = next_value(&mut args, "--x-axis")?;
+                    parsed.plot_options.x_axis = value.parse().map_err(|_| {
+                        CliError::Usage(format!("unknown --x-axis '{}' (expected index, time, elapsed or internal)", value))
+                    })?;
+                }
+                "--color" => {
+                    let value = next_value(&mut args, "--color")?;
+                    let invalid = || CliError::Usage(format!("invalid --color '{}' (expected offset=, delay= or freq= followed by #rrggbb)", value));
+                    let (kind, hex) = value.split_once('=').ok_or_else(invalid)?;
+                    let color = parse_hex_color(hex).ok_or_else(invalid)?;
+                    let colors = &mut parsed.plot_options.colors;
+                    match kind {
+                        "offset" => colors.offset = color,
+                        "delay" => colors.delay = color,
+                        "freq" => colors.freq = color,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--opacity" => parsed.plot_options.opacity = parse_number(&mut args, "--opacity")?,
+                "--highlight-sigma" => parsed.plot_options.highlight_sigma = Some(parse_number(&mut args, "--highlight-sigma")?),
+                "--y-min" => parsed.plot_options.y_min = Some(parse_number(&mut args, "--y-min")?),
+                "--y-max" => parsed.plot_options.y_max = Some(parse_number(&mut args, "--y-max")?),
+                "--plot-title" => parsed.plot_options.title = Some(next_value(&mut args, "--plot-title")?),
+                "--no-title" => parsed.plot_options.title = None,
+                "--subtitle" => parsed.subtitle = true,
+                "--smooth" => parsed.plot_options.smooth = Some(parse_number(&mut args, "--smooth")?),
+                "--max-points" => parsed.plot_options.max_points = Some(parse_number(&mut args, "--max-points")?),
+                "--unit" => {
+                    let value = next_value(&mut args, "--unit")?;
+                    parsed.plot_options.unit = value
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("unknown --unit '{}' (expected ns, us or ms)", value)))?;
+                }
+                "--input-unit" => {
+                    let value = next_value(&mut args, "--input-unit")?;
+                    parsed.input_unit = value
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("unknown --input-unit '{}' (expected ns, us or ms)", value)))?;
+                }
+                "--input-format" => {
+                    let value = next_value(&mut args, "--input-format")?;
+                    parsed.input_format = value
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("unknown --input-format '{}' (expected auto, text or json)", value)))?;
+                }
+                "--log-format" => {
+                    let value = next_value(&mut args, "--log-format")?;
+                    parsed.log_format = Some(value.parse().map_err(|_| {
+                        CliError::Usage(format!("unknown --log-format '{}' (expected ptp4l-syslog, ptp4l-bare, phc2sys or summary)", value))
+                    })?);
+                }
+                "--precision" => parsed.precision = parse_number(&mut args, "--precision")?,
+                "--metrics" => parsed.metrics = parse_metrics(&next_value(&mut args, "--metrics")?)?,
+                "--from" => parsed.window.from = Some(parse_timestamp(&mut args, "--from")?),
+                "--to" => parsed.window.to = Some(parse_timestamp(&mut args, "--to")?),
+                "--tail" => parsed.tail = Some(parse_number(&mut args, "--tail")?),
+                "--skip-warmup" | "--skip-warmup-secs" if parsed.warmup.is_some() => {
+                    return Err(CliError::Usage("--skip-warmup and --skip-warmup-secs cannot be combined".to_string()));
+                }
+                "--skip-warmup" => parsed.warmup = Some(Warmup::Samples(parse_number(&mut args, "--skip-warmup")?)),
+                "--skip-warmup-secs" => parsed.warmup = Some(Warmup::Seconds(parse_number(&mut args, "--skip-warmup-secs")?)),
+                "--strict" => parsed.strict = true,
+                "--min-samples" => parsed.min_samples = parse_number(&mut args, "--min-samples")?,
+                "--pattern" => parsed.pattern = Some(parse_pattern(&next_value(&mut args, "--pattern")?)?),
+                "--exclude" => {
+                    let value = next_value(&mut args, "--exclude")?;
+                    parsed.exclude = Some(Regex::new(&value).map_err(|err| CliError::Usage(format!("invalid --exclude: {}", err)))?);
+                }
+                "--bins" => parsed.bins = parse_number(&mut args, "--bins")?,
+                "--fit-normal" => parsed.fit_normal = true,
+                "--mark-shifts" => parsed.mark_shifts = true,
+                "--window" => parsed.window_size = parse_number(&mut args, "--window")?,
+                "--tau0" => parsed.tau0 = parse_number(&mut args, "--tau0")?,
+                "--sample-rate" => parsed.sample_rate = Some(parse_number(&mut args, "--sample-rate")?),
+                "--resample" => parsed.resample = true,
+                "--sort-by-time" => parsed.sort_by_time = true,
+                "--gap-factor" => parsed.gap_factor = parse_number(&mut args, "--gap-factor")?,
+                "--drop-outliers" => parsed.drop_outliers = true,
+                "--detrend" => {
+                    let value = next_value(&mut args, "--detrend")?;
+                    parsed.detrend = Some(
+                        value
+                            .parse()
+                            .map_err(|_| CliError::Usage(format!("invalid --detrend '{}' (expected linear or a window of at least 2 samples)", value)))?,
+                    );
+                }
+                "--time-weighted" => parsed.time_weighted = true,
+                "--hourly" => parsed.hourly = true,
+                "--settle-ns" => parsed.settle_ns = Some(parse_number(&mut args, "--settle-ns")?),
+                "--trend-ns-per-hour" => parsed.trend_ns_per_hour = parse_number(&mut args, "--trend-ns-per-hour")?,
+                "--delay-spikes" => parsed.delay_spikes = Some(parse_number(&mut args, "--delay-spikes")?),
+                "--correlation" => parsed.correlation = true,
+                "--no-plots" => parsed.no_plots = true,
+                "--contact-sheet" => parsed.contact_sheet = Some(parse_number(&mut args, "--contact-sheet")?),
+                "--box-plot" => parsed.box_plot = true,
+                "--diff" => {
+                    let value = next_value(&mut args, "--diff")?;
+                    let pair = value.split_once(',').filter(|(a, b)| !a.is_empty() && !b.is_empty() && a != b);
+                    let Some((a, b)) = pair else {
+                        return Err(CliError::Usage(format!("invalid --diff '{}' (expected two device names, A,B)", value)));
+                    };
+                    parsed.diff = Some((a.to_string(), b.to_string()));
+                }
+                "--baseline" => parsed.baseline = Some(PathBuf::from(next_value(&mut args, "--baseline")?)),
+                "--top" => parsed.top = Some(parse_number(&mut args, "--top")?),
+                "--rank-by" => {
+                    let value = next_value(&mut args, "--rank-by")?;
+                    parsed.rank_by = value
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("unknown --rank-by '{}' (expected rms, max, jitter or lock)", value)))?;
+                }
+                "--max-offset-ns" => parsed.thresholds.max_offset_ns = Some(parse_number(&mut args, "--max-offset-ns")?),
+                "--mask-ns" => parsed.thresholds.mask_ns = Some(parse_number(&mut args, "--mask-ns")?),
+                "--max-delay-ns" => parsed.thresholds.max_delay_ns = Some(parse_number(&mut args, "--max-delay-ns")?),
+                "--max-delay-jitter-ns" => {
+                    parsed.thresholds.max_delay_jitter_ns = Some(parse_number(&mut args, "--max-delay-jitter-ns")?)
+                }
+                "--min-lock-pct" => parsed.thresholds.min_lock_pct = Some(parse_number(&mut args, "--min-lock-pct")?),
+                "--streaming" => parsed.streaming = true,
+                "--reservoir" => parsed.reservoir = Some(parse_number(&mut args, "--reservoir")?),
+                "--report" => parsed.report = Some(PathBuf::from(next_value(&mut args, "--report")?)),
+                "--bundle" => parsed.bundle = Some(PathBuf::from(next_value(&mut args, "--bundle")?)),
+                "--text-out" => parsed.text_out = Some(PathBuf::from(next_value(&mut args, "--text-out")?)),
+                "--prom" => parsed.prom = Some(PathBuf::from(next_value(&mut args, "--prom")?)),
+                "--status-file" => parsed.status_file = Some(PathBuf::from(next_value(&mut args, "--status-file")?)),
+                "--json" => parsed.json = true,
+                "--jsonl" => parsed.jsonl = true,
+                "--influx" => parsed.influx = Some(PathBuf::from(next_value(&mut args, "--influx")?)),
+                "--check" => parsed.check = true,
+                "--follow" => parsed.follow = true,
+                "--interval" => parsed.interval = Some(parse_number(&mut args, "--interval")?),
+                "--read-timeout" => parsed.read_timeout = Some(parse_number(&mut args, "--read-timeout")?),
+                "-q" | "--quiet" => parsed.quiet = true,
+                "-v" | "--verbose" => parsed.verbosity = parsed.verbosity.saturating_add(1),
+                "-vv" => parsed.verbosity = parsed.verbosity.saturating_add(2),
+                flag if flag.starts_with("--") => {
+                    return Err(CliError::Usage(format!("unknown option '{}'", flag)));
+                }
+                token => parsed.machines.push(parse_machine(token)),
+            }
+        }
+
+        if parsed.machines.is_empty() {
+            return Err(CliError::Usage("no log files given".to_string()));
+        }
+        filter_machines(&mut parsed.machines, &parsed.only, &parsed.skip)?;
+        if let Some(limit) = parsed.limit {
+            if limit == 0 {
+                return Err(CliError::Usage("--limit must be greater than zero".to_string()));
+            }
+            parsed.machines.truncate(limit);
+        }
+
+        check_unique_names(&parsed.machines)?;
+
+        if parsed.plot_options.label_size == Some(0) {
+            return Err(CliError::Usage("--label-size must be greater than zero".to_string()));
+        }
+
+        if parsed.plot_options.width == 0 || parsed.plot_options.height == 0 {
+            return Err(CliError::Usage("--width and --height must be greater than zero".to_string()));
+        }
+
+        if !(parsed.plot_options.opacity > 0.0 && parsed.plot_options.opacity <= 1.0) {
+            return Err(CliError::Usage("--opacity must be greater than 0 and at most 1".to_string()));
+        }
+
+        if parsed.log_format.is_some() && parsed.pattern.is_some() {
+            return Err(CliError::Usage("--log-format cannot be combined with --pattern".to_string()));
+        }
+
+        if parsed.plot_options.highlight_sigma.is_some_and(|sigma| !sigma.is_finite() || sigma <= 0.0) {
+            return Err(CliError::Usage("--highlight-sigma must be greater than zero".to_string()));
+        }
+
+        if let (Some(min), Some(max)) = (parsed.plot_options.y_min, parsed.plot_options.y_max)
+            && min >= max
+        {
+            return Err(CliError::Usage("--y-min must be less than --y-max".to_string()));
+        }
+
+        if parsed.plot_options.smooth == Some(0) {
+            return Err(CliError::Usage("--smooth must be greater than zero".to_string()));
+        }
+
+        if parsed.tail == Some(0) {
+            return Err(CliError::Usage("--tail must be greater than zero".to_string()));
+        }
+
+        if parsed.contact_sheet == Some(0) {
+            return Err(CliError::Usage("--contact-sheet must be greater than zero".to_string()));
+        }
+
+        if parsed.reservoir == Some(0) {
+            return Err(CliError::Usage("--reservoir must be greater than zero".to_string()));
+        }
+        if parsed.reservoir.is_some() && !parsed.streaming {
+            return Err(CliError::Usage("--reservoir requires --streaming".to_string()));
+        }
+
+        if parsed.top == Some(0) {
+            return Err(CliError::Usage("--top must be greater than zero".to_string()));
+        }
+
+        if parsed.follow {
+            let [Machine { paths, .. }] = &parsed.machines[..] else {
+                return Err(CliError::Usage("--follow takes exactly one log".to_string()));
+            };
+            let [path] = &paths[..] else {
+                return Err(CliError::Usage("--follow takes a single log file, not rotated parts".to_string()));
+            };
+            if path.as_os_str() == STDIN_PATH || is_compressed(path) {
+                return Err(CliError::Usage("--follow needs an uncompressed log file".to_string()));
+            }
+            if parsed.json || parsed.jsonl || parsed.influx.is_some() || parsed.streaming || parsed.check || parsed.read_timeout.is_some() {
+                return Err(CliError::Usage(
+                    "--follow cannot be combined with --json, --jsonl, --influx, --streaming, --check or --read-timeout".to_string(),
+                ));
+            }
+        } else if parsed.interval.is_some() {
+            return Err(CliError::Usage("--interval requires --follow".to_string()));
+        }
+        if parsed.interval.is_some_and(|secs| !secs.is_finite() || secs <= 0.0) {
+            return Err(CliError::Usage("--interval must be greater than zero".to_string()));
+        }
+        if !parsed.trend_ns_per_hour.is_finite() || parsed.trend_ns_per_hour < 0.0 {
+            return Err(CliError::Usage("--trend-ns-per-hour must not be negative".to_string()));
+        }
+        if parsed.read_timeout.is_some_and(|secs| !secs.is_finite() || secs <= 0.0) {
+            return Err(CliError::Usage("--read-timeout must be greater than zero".to_string()));
+        }
+        if let Some(Warmup::Seconds(secs)) = parsed.warmup
+            && (!secs.is_finite() || secs < 0.0)
+        {
+            return Err(CliError::Usage("--skip-warmup-secs must not be negative".to_string()));
+        }
+
+        if let Some(hz) = parsed.sample_rate {
+            if !hz.is_finite() || hz <= 0.0 {
+                return Err(CliError::Usage("--sample-rate must be greater than zero".to_string()));
+            }
+            parsed.tau0 = 1.0 / hz;
+        } else if parsed.resample {
+            return Err(CliError::Usage("--resample requires --sample-rate".to_string()));
+        }
+
+        if parsed.jsonl && (parsed.json || parsed.streaming) {
+            return Err(CliError::Usage("--jsonl cannot be combined with --json or --streaming".to_string()));
+        }
+
+        if parsed.influx.is_some() && (parsed.json || parsed.jsonl || parsed.streaming) {
+            return Err(CliError::Usage("--influx cannot be combined with --json, --jsonl or --streaming".to_string()));
+        }
+
+        if let Some(dir) = &parsed.bundle {
+            if out_dir_given {
+                return Err(CliError::Usage("--bundle cannot be combined with --out-dir; its plots go to DIR/plots".to_string()));
+            }
+            if parsed.follow || parsed.jsonl || parsed.influx.is_some() || parsed.check {
+                return Err(CliError::Usage("--bundle cannot be combined with --follow, --jsonl, --influx or --check".to_string()));
+            }
+            parsed.plot_options.out_dir = bundle::plot_dir(dir);
+        }
+
+        if parsed.streaming {
+            if parsed.csv.is_some() {
+                return Err(CliError::Usage("--streaming cannot be combined with --csv".to_string()));
+            }
+            if parsed.drop_outliers {
+                return Err(CliError::Usage("--streaming cannot be combined with --drop-outliers".to_string()));
+            }
+            if parsed.detrend.is_some() {
+                return Err(CliError::Usage("--streaming cannot be combined with --detrend".to_string()));
+            }
+            if parsed.time_weighted {
+                return Err(CliError::Usage("--streaming cannot be combined with --time-weighted".to_string()));
+            }
+            if parsed.hourly {
+                return Err(CliError::Usage("--streaming cannot be combined with --hourly".to_string()));
+            }
+            if parsed.sort_by_time {
+                return Err(CliError::Usage("--streaming cannot be combined with --sort-by-time".to_string()));
+            }
+            if parsed.settle_ns.is_some() {
+                return Err(CliError::Usage("--streaming cannot be combined with --settle-ns".to_string()));
+            }
+            if parsed.delay_spikes.is_some() {
+                return Err(CliError::Usage("--streaming cannot be combined with --delay-spikes".to_string()));
+            }
+            if parsed.correlation {
+                return Err(CliError::Usage("--streaming cannot be combined with --correlation".to_string()));
+            }
+            if parsed.warmup.is_some() {
+                return Err(CliError::Usage("--streaming cannot be combined with --skip-warmup".to_string()));
+            }
+            if parsed.diff.is_some() {
+                return Err(CliError::Usage("--streaming cannot be combined with --diff".to_string()));
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, CliError> {
+    args.next()
+        .ok_or_else(|| CliError::Usage(format!("{} requires a value", flag)))
+}
+
+fn parse_number<I, T>(args: &mut I, flag: &str) -> Result<T, CliError>
+where
+    I: Iterator<Item = String>,
+    T: std::str::FromStr,
+{
+    let value = next_value(args, flag)?;
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("invalid value '{}' for {}", value, flag)))
+}
+
+/// Compiles a `--pattern` regex and checks it has the groups the parser
+/// relies on.
+fn parse_pattern(pattern: &str) -> Result<Regex, CliError> {
+    let re = Regex::new(pattern)
+        .map_err(|err| CliError::Usage(format!("invalid --pattern: {}", err)))?;
+
+    let groups: Vec<&str> = re.capture_names().flatten().collect();
+    for required in ["offset", "delay"] {
+        if !groups.contains(&required) {
+            return Err(CliError::Usage(format!("--pattern must contain a named group (?P<{}>...)", required)));
+        }
+    }
+
+    Ok(re)
+}
+
+/// Every device and plot type needs a file of its own, inside `--out-dir`.
+fn parse_file_template(template: &str) -> Result<String, CliError> {
+    if !template.contains("{device}") || !template.contains("{type}") {
+        return Err(CliError::Usage("--out-template must contain {device} and {type}".to_string()));
+    }
+    if template.contains(['/', '\\']) {
+        return Err(CliError::Usage("--out-template must not contain a path separator; use --out-dir".to_string()));
+    }
+    let rest = FILE_TEMPLATE_PLACEHOLDERS.iter().fold(template.to_string(), |rest, placeholder| rest.replace(placeholder, ""));
+    if rest.contains(['{', '}']) {
+        return Err(CliError::Usage(format!("--out-template placeholders are {}", FILE_TEMPLATE_PLACEHOLDERS.join(", "))));
+    }
+    Ok(template.to_string())
+}
+
+/// Looks up each name of a `--metrics` list.
+fn parse_metrics(list: &str) -> Result<Vec<&'static Metric>, CliError> {
+    list.split(',')
+        .map(|name| {
+            Metric::find(name.trim()).ok_or_else(|| {
+                let valid: Vec<&str> = METRICS.iter().map(|metric| metric.name).collect();
+                CliError::Usage(format!("unknown metric '{}' (expected {})", name.trim(), valid.join(", ")))
+            })
+        })
+        .collect()
+}
+
+fn parse_timestamp<I>(args: &mut I, flag: &str) -> Result<DateTime<FixedOffset>, CliError>
+where
+    I: Iterator<Item = String>,
+{
+    let value = next_value(args, flag)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map_err(|err| CliError::Usage(format!("invalid timestamp '{}' for {}: {}", value, flag, err)))
+}
+
+/// Keeps the machines named in `only`, if any, then drops those named in
+/// `skip`. Every name must match a machine exactly, so a typo fails rather
+/// than quietly analyzing the wrong set.
+fn filter_machines(machines: &mut Vec<Machine>, only: &[String], skip: &[String]) -> Result<(), CliError> {
+    for (flag, names) in [("--only", only), ("--skip", skip)] {
+        if let Some(name) = names.iter().find(|name| !machines.iter().any(|machine| &machine.name == *name)) {
+            let known: Vec<&str> = machines.iter().map(|machine| machine.name.as_str()).collect();
+            return Err(CliError::Usage(format!("{} '{}': no device has that name (devices: {})", flag, name, known.join(", "))));
+        }
+    }
+    if !only.is_empty() {
+        machines.retain(|machine| only.contains(&machine.name));
+    }
+    machines.retain(|machine| !skip.contains(&machine.name));
+    if machines.is_empty() {
+        return Err(CliError::Usage("--skip leaves no devices to analyze".to_string()));
+    }
+    Ok(())
+}
+
+/// Rejects two machines with the same name. Plot and CSV filenames are
+/// lowercased, so names differing only in case would overwrite each other
+/// too.
+fn check_unique_names(machines: &[Machine]) -> Result<(), CliError> {
+    for (index, machine) in machines.iter().enumerate() {
+        let name = machine.name.to_lowercase();
+        if let Some(other) = machines[..index].iter().find(|other| other.name.to_lowercase() == name) {
+            return Err(CliError::Usage(format!(
+                "{} and {} are both named '{}'; give one a different name with NAME=PATH",
+                other.display_paths(),
+                machine.display_paths(),
+                machine.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Parses a `[NAME=]PATH` token. A `=` only separates a name when the part
+/// before it doesn't look like a path, so `logs/a=b.log` stays a plain path.
+pub fn parse_machine(token: &str) -> Machine {
+    if let Some((name, path)) = token.split_once('=')
+        && !name.is_empty()
+        && !name.contains(std::path::MAIN_SEPARATOR)
+        && !name.contains('/')
+    {
+        let paths = path.split(',').map(PathBuf::from).collect();
+        return Machine { name: name.to_string(), paths, max_offset_ns: None, grandmaster: None };
+    }
+
+    if token == "-" {
+        return Machine::new("stdin".to_string(), PathBuf::from(token));
+    }
+
+    let path = PathBuf::from(token);
+    Machine::new(device_name(&path), path)
+}
+
+/// Whether the log is read through a decompressor, going by its suffix.
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz" || ext == "bz2" || ext == "zst")
+}
+
+/// Derives a device name from the file stem, looking through a compression
+/// suffix so `beta.log.gz` is named like `beta.log`.
+pub fn device_name(path: &Path) -> String {
+    let path = match is_compressed(path) {
+        true => Path::new(path.file_stem().unwrap_or_default()),
+        false => path,
+    };
+
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned())
+}
+
+fn machines_in_dir(dir: &Path) -> Result<Vec<Machine>, CliError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|err| CliError::Usage(format!("cannot read directory {}: {}", dir.display(), err)))?;
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    Ok(paths
+        .into_iter()
+        .map(|path| Machine::new(device_name(&path), path))
+        .collect())
+}
+
+fn machines_matching(pattern: &str) -> Result<Vec<Machine>, CliError> {
+    let paths = glob::expand(pattern).map_err(CliError::Usage)?;
+    if paths.is_empty() {
+        return Err(CliError::Usage(format!("--glob '{}' matched no files", pattern)));
+    }
+
+    Ok(paths
+        .into_iter()
+        .map(|path| Machine::new(device_name(&path), path))
+        .collect())
+}
@@ -0,0 +1,36 @@
+//! ANSI colors for terminal output. Color is only used when stdout is a
+//! terminal and `NO_COLOR` is unset, so piped output stays plain.
+
+use crate::check::Level;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && std::io::stdout().is_terminal()
+    })
+}
+
+/// Wraps `text` in the color for `level`. Text without a level, or with
+/// color disabled, comes back unchanged.
+pub fn paint(text: &str, level: Option<Level>) -> String {
+    paint_if(enabled(), text, level)
+}
+
+/// [`paint`] for output other than stdout, e.g. a file, where the caller
+/// decides whether color applies.
+pub fn paint_if(colored: bool, text: &str, level: Option<Level>) -> String {
+    let code = match level {
+        Some(Level::Good) => "32",
+        Some(Level::Warning) => "33",
+        Some(Level::Bad) => "31",
+        None => return text.to_string(),
+    };
+
+    if colored {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
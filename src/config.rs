@@ -0,0 +1,133 @@
+//! `--config` files: the machine list for recurring runs, in a small subset
+//! of TOML.
+//!
+//! ```toml
+//! [[machine]]
+//! name = "alpha"              # optional, defaults to the file stem
+//! path = "logs/alpha.log"     # relative to the config file
+//! max_offset_ns = 1000        # optional, overrides --max-offset-ns
+//! grandmaster = "001122.fffe.334455"  # optional, else read from the log
+//! ```
+//!
+//! Only `[[machine]]` tables with string and number values are understood;
+//! anything else is reported as an error rather than silently ignored.
+
+use crate::cli::{device_name, Machine};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reads the machines listed in the config file at `path`.
+pub fn load(path: &Path) -> Result<Vec<Machine>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    parse(&text, base).map_err(|err| format!("{}:{}", path.display(), err))
+}
+
+/// A `[[machine]]` table as written, before defaults are filled in.
+#[derive(Default)]
+struct Entry {
+    line: usize,
+    name: Option<String>,
+    path: Option<String>,
+    max_offset_ns: Option<f64>,
+    grandmaster: Option<String>,
+}
+
+impl Entry {
+    fn into_machine(self, base: &Path) -> Result<Machine, String> {
+        let path = self.path.ok_or_else(|| format!("{}: [[machine]] is missing a path", self.line))?;
+        let path = base.join(PathBuf::from(path));
+        let name = self.name.unwrap_or_else(|| device_name(&path));
+        Ok(Machine { max_offset_ns: self.max_offset_ns, grandmaster: self.grandmaster, ..Machine::new(name, path) })
+    }
+}
+
+/// Parses config text; errors are prefixed with the line number.
+fn parse(text: &str, base: &Path) -> Result<Vec<Machine>, String> {
+    let mut entries: Vec<Entry> = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            if line != "[[machine]]" {
+                return Err(format!("{}: unsupported table {}", line_no, line));
+            }
+            entries.push(Entry { line: line_no, ..Entry::default() });
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("{}: expected key = value", line_no))?;
+        let entry = entries
+            .last_mut()
+            .ok_or_else(|| format!("{}: {} must be inside a [[machine]] table", line_no, key.trim()))?;
+
+        let value = value.trim();
+        match key.trim() {
+            "name" => entry.name = Some(parse_string(value).ok_or_else(|| format!("{}: name must be a string", line_no))?),
+            "path" => entry.path = Some(parse_string(value).ok_or_else(|| format!("{}: path must be a string", line_no))?),
+            "grandmaster" => entry.grandmaster = Some(parse_string(value).ok_or_else(|| format!("{}: grandmaster must be a string", line_no))?),
+            "max_offset_ns" => {
+                let limit = value
+                    .replace('_', "")
+                    .parse()
+                    .map_err(|_| format!("{}: max_offset_ns must be a number", line_no))?;
+                entry.max_offset_ns = Some(limit);
+            }
+            other => return Err(format!("{}: unknown key '{}'", line_no, other)),
+        }
+    }
+
+    entries.into_iter().map(|entry| entry.into_machine(base)).collect()
+}
+
+/// Drops a trailing `#` comment, leaving any `#` inside a string alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(open), c) if c == open && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..index],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// A TOML basic (`"..."`, with escapes) or literal (`'...'`) string.
+fn parse_string(value: &str) -> Option<String> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        return literal.strip_suffix('\'').map(str::to_string);
+    }
+
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '"' => out.push('"'),
+            '\\' => out.push('\\'),
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,97 @@
+//! Cross-device offset correlation. Clocks following the same grandmaster
+//! that swing together point at the grandmaster or the network; a clock
+//! that swings alone points at itself.
+
+use crate::record::Record;
+use crate::stats;
+use crate::timing;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Fewest shared time buckets a pair needs before its correlation means
+/// anything.
+const MIN_COMMON_BUCKETS: usize = 3;
+
+/// A device's timestamped offsets as `(seconds since the epoch, offset)`.
+/// Samples without a timestamp can't be aligned and are left out.
+pub fn timed_offsets(records: &[Record]) -> Vec<(f64, f64)> {
+    records
+        .iter()
+        .filter_map(|record| record.timestamp.map(|t| (timing::seconds(&t), record.offset)))
+        .collect()
+}
+
+/// Bucket width that gives every device at most one sample per bucket on
+/// average: the coarsest median logging interval, or 1 s if no device has
+/// two timestamped samples.
+pub fn bucket_width(series: &[&[(f64, f64)]]) -> f64 {
+    series
+        .iter()
+        .filter_map(|samples| {
+            let intervals: Vec<f64> = samples.windows(2).map(|pair| pair[1].0 - pair[0].0).collect();
+            (!intervals.is_empty()).then(|| stats::median(&intervals))
+        })
+        .filter(|interval| *interval > 0.0)
+        .fold(None, |widest: Option<f64>, interval| Some(widest.map_or(interval, |w| w.max(interval))))
+        .unwrap_or(1.0)
+}
+
+/// Every device's offsets on one time grid, for cross-device math: a row
+/// for each `bucket`-wide time bucket any device has a sample in, keyed by
+/// the bucket's start in nanoseconds since the epoch. A row holds each
+/// device's mean offset in the bucket, in `series` order, or `None` where
+/// the device has no sample. Samples without a timestamp are left out.
+pub fn aligned_series(series: &[(String, Vec<Record>)], bucket: Duration) -> BTreeMap<i64, Vec<Option<f64>>> {
+    let width = i64::try_from(bucket.as_nanos()).unwrap_or(i64::MAX).max(1);
+    let mut sums: BTreeMap<i64, Vec<(f64, usize)>> = BTreeMap::new();
+    for (device, (_, records)) in series.iter().enumerate() {
+        for record in records {
+            let Some(nanos) = record.timestamp.and_then(|t| t.timestamp_nanos_opt()) else {
+                continue;
+            };
+            let row = sums.entry(nanos.div_euclid(width) * width).or_insert_with(|| vec![(0.0, 0); series.len()]);
+            row[device].0 += record.offset;
+            row[device].1 += 1;
+        }
+    }
+
+    sums.into_iter()
+        .map(|(start, row)| (start, row.into_iter().map(|(sum, count)| (count > 0).then(|| sum / count as f64)).collect()))
+        .collect()
+}
+
+/// Pairwise Pearson correlation of every pair of series, aligned on
+/// `bucket`-second time buckets (each bucket holding the mean offset of
+/// the samples in it). Entries are `None` where two devices share fewer
+/// than [`MIN_COMMON_BUCKETS`] buckets or either is constant over them.
+pub fn matrix(series: &[&[(f64, f64)]], bucket: f64) -> Vec<Vec<Option<f64>>> {
+    let bucketed: Vec<BTreeMap<i64, f64>> = series.iter().map(|samples| bucket_means(samples, bucket)).collect();
+
+    bucketed
+        .iter()
+        .map(|a| bucketed.iter().map(|b| correlate(a, b)).collect())
+        .collect()
+}
+
+fn bucket_means(samples: &[(f64, f64)], bucket: f64) -> BTreeMap<i64, f64> {
+    let mut sums: BTreeMap<i64, (f64, usize)> = BTreeMap::new();
+    for (time, offset) in samples {
+        let entry = sums.entry((time / bucket).floor() as i64).or_default();
+        entry.0 += offset;
+        entry.1 += 1;
+    }
+    sums.into_iter().map(|(key, (sum, count))| (key, sum / count as f64)).collect()
+}
+
+fn correlate(a: &BTreeMap<i64, f64>, b: &BTreeMap<i64, f64>) -> Option<f64> {
+    let (x, y): (Vec<f64>, Vec<f64>) = a
+        .iter()
+        .filter_map(|(key, value_a)| b.get(key).map(|value_b| (*value_a, *value_b)))
+        .unzip();
+
+    if x.len() < MIN_COMMON_BUCKETS {
+        return None;
+    }
+    let r = stats::pearson(&x, &y);
+    (!r.is_nan()).then_some(r)
+}
@@ -0,0 +1,115 @@
+//! `--demo`: made-up ptp4l logs for a few devices, to try every stage of
+//! the tool without a real log, and a known input for tests.
+
+use chrono::{DateTime, TimeDelta};
+use crate::cli::Machine;
+use crate::parser::{Parser, TimeWindow};
+use crate::record::Record;
+use crate::stats;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Samples per device: ten minutes at ptp4l's default one a second.
+pub const SAMPLES: usize = 600;
+
+/// When the first sample of every demo log was taken.
+const START: &str = "2024-05-01T12:00:00+00:00";
+
+/// One made-up device and what its offset does besides settling.
+struct Scenario {
+    name: &'static str,
+    /// The syslog host name, which the builtin pattern takes as one word.
+    host: &'static str,
+    /// Standard deviation of the offset noise, in ns.
+    noise_ns: f64,
+    /// Steady offset drift, in ns per second.
+    drift_ns_per_s: f64,
+    /// The sample carrying a one-off offset spike, and its size in ns.
+    spike: Option<(usize, f64)>,
+    delay_ns: f64,
+}
+
+const SCENARIOS: &[Scenario] = &[
+    Scenario { name: "demo-stable", host: "stable", noise_ns: 8.0, drift_ns_per_s: 0.0, spike: None, delay_ns: 600.0 },
+    Scenario { name: "demo-drifting", host: "drifting", noise_ns: 10.0, drift_ns_per_s: 0.2, spike: None, delay_ns: 1450.0 },
+    Scenario { name: "demo-spike", host: "spiky", noise_ns: 12.0, drift_ns_per_s: 0.0, spike: Some((400, 2500.0)), delay_ns: 900.0 },
+];
+
+/// The demo devices' names.
+pub fn device_names() -> impl Iterator<Item = &'static str> {
+    SCENARIOS.iter().map(|scenario| scenario.name)
+}
+
+/// Where `--demo` writes its logs: the same directory every run, so one
+/// run's logs replace the last's.
+pub fn dir() -> PathBuf {
+    std::env::temp_dir().join("ptp-stats-demo")
+}
+
+/// The demo devices, reading their logs from `dir` once [`write`] has put
+/// them there.
+pub fn machines(dir: &Path) -> Vec<Machine> {
+    device_names()
+        .map(|name| Machine { name: name.to_string(), paths: vec![log_path(dir, name)], max_offset_ns: None, grandmaster: None })
+        .collect()
+}
+
+/// Writes every demo device's log to `dir`, created if missing.
+pub fn write(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for name in device_names() {
+        fs::write(log_path(dir, name), log(name).unwrap_or_default())?;
+    }
+    Ok(())
+}
+
+fn log_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.log", name))
+}
+
+/// The log of the demo device `name`, or `None` if there is none: syslog
+/// ptp4l lines a second apart, [`SAMPLES`] of them, settling from a 500 ns
+/// offset through servo states s0 and s1 before locking in s2. The noise
+/// comes from a fixed seed, so every call gives the same log.
+pub fn log(name: &str) -> Option<String> {
+    let (index, scenario) = SCENARIOS.iter().enumerate().find(|(_, scenario)| scenario.name == name)?;
+    let start = DateTime::parse_from_rfc3339(START).ok()?;
+    let mut state = 0x5eed + index as u64;
+    let mut log = String::new();
+    for i in 0..SAMPLES {
+        let settling = 500.0 * 0.5f64.powi(i as i32);
+        let spike = scenario.spike.filter(|(at, _)| *at == i).map_or(0.0, |(_, size)| size);
+        let offset = settling + scenario.drift_ns_per_s * i as f64 + spike + scenario.noise_ns * gaussian(&mut state);
+        let freq = -1200.0 - 0.3 * offset + 2.0 * gaussian(&mut state);
+        let delay = scenario.delay_ns + 3.0 * gaussian(&mut state);
+        let timestamp = start + TimeDelta::seconds(i as i64);
+        log.push_str(&format!(
+            "{} {} ptp4l[812]: ptp4l[{:.3}]: master offset {:>10.0} s{} freq {:>+7.0} path delay {:>9.0}\n",
+            timestamp.format("%Y-%m-%dT%H:%M:%S%:z"),
+            scenario.host,
+            1000.25 + i as f64,
+            offset,
+            i.min(2),
+            freq,
+            delay
+        ));
+    }
+    Some(log)
+}
+
+/// The records the parser reads from [`log`]`(name)`, or `None` if there is
+/// no such device.
+pub fn records(name: &str) -> Option<Vec<Record>> {
+    let log = log(name)?;
+    Parser::new(None, TimeWindow::default()).parse_reader(log.as_bytes()).ok().map(|parsed| parsed.records)
+}
+
+/// A standard normal value, by the Box-Muller transform of two uniform
+/// ones.
+fn gaussian(state: &mut u64) -> f64 {
+    let uniform = |state: &mut u64| (stats::split_mix(state) >> 11) as f64 / (1u64 << 53) as f64;
+    // 1 - u is in (0, 1], which ln needs.
+    let radius = (-2.0 * (1.0 - uniform(state)).ln()).sqrt();
+    radius * (std::f64::consts::TAU * uniform(state)).cos()
+}
@@ -0,0 +1,79 @@
+//! `--detrend`: removes slow drift, such as an oscillator warming up, from
+//! an offset series so that what remains is the short-term jitter.
+
+use crate::stats;
+use std::fmt;
+use std::str::FromStr;
+
+/// What is subtracted from the series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detrend {
+    /// The least-squares line through the samples.
+    Linear,
+    /// A moving average centered on each sample, over this many samples.
+    MovingAverage(usize),
+}
+
+impl Detrend {
+    /// The residual of `data` after removing its trend, sample by sample.
+    /// The samples are taken to be evenly spaced.
+    pub fn apply(self, data: &[f64]) -> Vec<f64> {
+        match self {
+            Detrend::Linear => subtract_linear_fit(data),
+            Detrend::MovingAverage(window) => subtract_moving_average(data, window),
+        }
+    }
+}
+
+impl FromStr for Detrend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Detrend::Linear),
+            _ => match s.parse() {
+                Ok(window) if window > 1 => Ok(Detrend::MovingAverage(window)),
+                _ => Err(()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Detrend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Detrend::Linear => write!(f, "linear fit"),
+            Detrend::MovingAverage(window) => write!(f, "{}-sample moving average", window),
+        }
+    }
+}
+
+fn subtract_linear_fit(data: &[f64]) -> Vec<f64> {
+    let index: Vec<f64> = (0..data.len()).map(|i| i as f64).collect();
+    let (slope, intercept, _) = stats::linear_regression(&index, data);
+    // Fewer than two samples have no trend to remove.
+    if slope.is_nan() {
+        return data.to_vec();
+    }
+
+    data.iter().zip(&index).map(|(y, x)| y - (slope * x + intercept)).collect()
+}
+
+/// Near the ends the window is cut short rather than padded.
+fn subtract_moving_average(data: &[f64], window: usize) -> Vec<f64> {
+    let mut prefix = Vec::with_capacity(data.len() + 1);
+    prefix.push(0.0);
+    for value in data {
+        prefix.push(prefix[prefix.len() - 1] + value);
+    }
+
+    let before = (window - 1) / 2;
+    let after = window / 2;
+    (0..data.len())
+        .map(|i| {
+            let start = i.saturating_sub(before);
+            let end = (i + after + 1).min(data.len());
+            data[i] - (prefix[end] - prefix[start]) / (end - start) as f64
+        })
+        .collect()
+}
@@ -0,0 +1,75 @@
+//! Failures that end a run, or a device's part in it, reported to the user
+//! as one line rather than a panic.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+pub enum PtpError {
+    /// Reading or writing something other than a log, e.g. the output
+    /// directory. `context` says what, e.g. "cannot create plots".
+    Io { context: String, source: io::Error },
+    /// Reading a log. Lines that don't parse are skipped, not errors.
+    Parse { path: String, source: io::Error },
+    /// Drawing or writing one of a device's plots.
+    Plot { plot: String, source: Box<dyn Error> },
+    /// A baseline or other input file that can't be used.
+    Config(String),
+}
+
+impl PtpError {
+    pub fn io(context: impl Into<String>, source: io::Error) -> PtpError {
+        PtpError::Io { context: context.into(), source }
+    }
+
+    pub fn parse(path: impl Into<String>, source: io::Error) -> PtpError {
+        PtpError::Parse { path: path.into(), source }
+    }
+}
+
+impl fmt::Display for PtpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PtpError::Io { context, source } => write!(f, "{}: {}", context, source),
+            PtpError::Parse { path, source } => write!(f, "failed to parse {}: {}", path, source),
+            PtpError::Plot { plot, source } => write!(f, "failed to write {}: {}", plot, source),
+            PtpError::Config(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// The same as `Display`: an `Err` returned from `main` is printed as
+/// `Error: {:?}`, which should read as a message, not a struct.
+impl fmt::Debug for PtpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Error for PtpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PtpError::Io { source, .. } | PtpError::Parse { source, .. } => Some(source),
+            PtpError::Plot { source, .. } => Some(source.as_ref()),
+            PtpError::Config(_) => None,
+        }
+    }
+}
+
+/// Replaces the default panic message and backtrace hint with a one-line
+/// `Error: ...`, e.g. when stdout is a pipe closed early. Setting
+/// `RUST_BACKTRACE` keeps the default hook, to debug the panic itself.
+pub fn install_panic_handler() {
+    if std::env::var_os("RUST_BACKTRACE").is_some() {
+        return;
+    }
+    std::panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("internal error");
+        eprintln!("Error: {}", message);
+    }));
+}
@@ -0,0 +1,54 @@
+//! ptp4l lines that aren't samples but explain discontinuities in them:
+//! a grandmaster change, or the clock check catching the clock jump.
+
+use chrono::{DateTime, FixedOffset};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// The best master clock algorithm picked a grandmaster, given by its
+    /// clock identity (`selected best master clock ...`).
+    MasterSelected(String),
+    /// No better clock was found and this one became grandmaster.
+    GrandmasterRole,
+    /// The clock check saw the clock jump (`clock jumped forward ...`).
+    ClockJump,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::MasterSelected(clock) => write!(f, "selected best master clock {}", clock),
+            EventKind::GrandmasterRole => write!(f, "assumed the grandmaster role"),
+            EventKind::ClockJump => write!(f, "clock jumped"),
+        }
+    }
+}
+
+/// An event line of a log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    /// Line number in the log, counting from 1.
+    pub line: usize,
+}
+
+impl Event {
+    /// "at RFC3339", or "on line N" without a timestamp.
+    pub fn location(&self) -> String {
+        match self.timestamp {
+            Some(timestamp) => format!("at {}", timestamp.to_rfc3339()),
+            None => format!("on line {}", self.line),
+        }
+    }
+}
+
+/// The clock identity of the grandmaster most recently selected in
+/// `events`, the one a device ends the log following.
+pub fn last_grandmaster(events: &[Event]) -> Option<&str> {
+    events.iter().rev().find_map(|event| match &event.kind {
+        EventKind::MasterSelected(clock) => Some(clock.as_str()),
+        _ => None,
+    })
+}
@@ -0,0 +1,108 @@
+use crate::record::Record;
+use crate::summary::DeviceSummary;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// `name` made safe to use in a file name: lowercased, with whitespace
+/// turned into dashes and path separators and other characters Windows
+/// rejects into underscores. Only file names go through this; captions
+/// and reports show device names as given.
+pub fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            c if c.is_whitespace() => '-',
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Writes `{out_dir}/{device}.csv`. A device without records still gets a
+/// header-only file so downstream tools see a consistent set of outputs.
+pub fn write_csv(out_dir: &Path, device: &str, records: &[Record]) -> io::Result<()> {
+    fs::create_dir_all(out_dir)?;
+    let filename = out_dir.join(format!("{}.csv", file_stem(device)));
+    let mut writer = BufWriter::new(File::create(filename)?);
+
+    writeln!(writer, "sample_index,timestamp,offset_ns,freq,delay_ns")?;
+    for (index, record) in records.iter().enumerate() {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            index,
+            record.timestamp.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            record.offset,
+            record.freq.map(|f| f.to_string()).unwrap_or_default(),
+            record.delay.map(|d| d.to_string()).unwrap_or_default()
+        )?;
+    }
+
+    writer.flush()
+}
+
+/// Writes `{out_dir}/{device}.txt`: the device name as a heading, as on
+/// stdout, then whatever `write` produces. Creates the directory if needed.
+pub fn write_text(out_dir: &Path, device: &str, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+    fs::create_dir_all(out_dir)?;
+    let filename = out_dir.join(format!("{}.txt", file_stem(device)));
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writeln!(writer, "{}", device)?;
+    write(&mut writer)?;
+    writer.flush()
+}
+
+/// Metric name, help text and value for an exported gauge; devices without
+/// a value, such as the delay of a ts2phc log, are left out of it.
+type Metric = (&'static str, &'static str, fn(&DeviceSummary) -> Option<f64>);
+
+const PROMETHEUS_METRICS: &[Metric] = &[
+    ("ptp_samples_total", "Samples analyzed.", |s| Some(s.samples as f64)),
+    ("ptp_offset_mean_ns", "Mean offset from master in nanoseconds.", |s| Some(s.offset.mean)),
+    ("ptp_offset_rms_ns", "RMS offset from master in nanoseconds.", |s| Some(s.offset.rms)),
+    ("ptp_offset_max_ns", "Maximum absolute offset from master in nanoseconds.", |s| Some(s.abs_offset.max)),
+    ("ptp_delay_mean_ns", "Mean path delay in nanoseconds.", |s| Some(s.delay.as_ref()?.mean)),
+    ("ptp_delay_max_ns", "Maximum path delay in nanoseconds.", |s| Some(s.delay.as_ref()?.max)),
+    ("ptp_delay_jitter_ns", "Standard deviation of path delay in nanoseconds.", |s| s.delay_jitter()),
+];
+
+/// Writes every device's summary in the Prometheus text exposition format,
+/// for the node exporter's textfile collector. The file is written under a
+/// temporary name and renamed into place so a scrape never sees half of it.
+pub fn write_prometheus(summaries: &[DeviceSummary], path: &Path) -> io::Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp = Path::new(&tmp_name);
+
+    let mut writer = BufWriter::new(File::create(tmp)?);
+    for (name, help, value) in PROMETHEUS_METRICS {
+        writeln!(writer, "# HELP {} {}", name, help)?;
+        writeln!(writer, "# TYPE {} gauge", name)?;
+        for summary in summaries {
+            let Some(value) = value(summary) else {
+                continue;
+            };
+            writeln!(writer, "{}{{device=\"{}\"}} {}", name, escape_label(&summary.device), prometheus_number(value))?;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+
+    fs::rename(tmp, path)
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn prometheus_number(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,120 @@
+//! `--follow`: reading a log while it is being written, like `tail -f`.
+//!
+//! A regular file is re-read from where the last read stopped. A named
+//! pipe (FIFO) can't be rewound or sized, and reading it blocks until a
+//! writer shows up, so it is read on a thread of its own that hands over
+//! whatever arrives. When every writer closes the pipe the thread keeps
+//! it open and waits for the next one instead of treating that as the end
+//! of the log.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// How long the pipe reader waits for a new writer after the last one
+/// closed the pipe.
+const FIFO_POLL: Duration = Duration::from_millis(100);
+
+/// A log read incrementally, one batch of complete lines at a time.
+pub struct Follower {
+    path: PathBuf,
+    input: Input,
+    /// Bytes consumed so far, to notice the log shrinking.
+    position: u64,
+    /// The start of a line still being written.
+    partial: Vec<u8>,
+}
+
+enum Input {
+    File(BufReader<File>),
+    /// Chunks read from a named pipe by its reader thread.
+    Fifo(Receiver<io::Result<Vec<u8>>>),
+}
+
+impl Follower {
+    /// Opens `path` for following. A named pipe is opened by its reader
+    /// thread, so errors opening one surface from the first
+    /// [`Follower::read_new`] instead.
+    pub fn open(path: &Path) -> io::Result<Follower> {
+        let input = match is_fifo(path) {
+            true => Input::Fifo(read_fifo(path.to_path_buf())),
+            false => Input::File(BufReader::new(File::open(path)?)),
+        };
+        Ok(Follower { path: path.to_path_buf(), input, position: 0, partial: Vec::new() })
+    }
+
+    /// Everything appended since the last call, up to the last complete
+    /// line. A log that shrank (truncated, or rotated with copytruncate)
+    /// is read again from the start; a log renamed away is not noticed.
+    pub fn read_new(&mut self) -> io::Result<String> {
+        if let Input::File(reader) = &mut self.input
+            && fs::metadata(&self.path)?.len() < self.position
+        {
+            reader.seek(SeekFrom::Start(0))?;
+            self.position = 0;
+            self.partial.clear();
+        }
+
+        let mut bytes = std::mem::take(&mut self.partial);
+        let start = bytes.len();
+        match &mut self.input {
+            Input::File(reader) => self.position += reader.read_to_end(&mut bytes)? as u64,
+            Input::Fifo(chunks) => {
+                for chunk in chunks.try_iter() {
+                    bytes.extend(chunk?);
+                }
+            }
+        }
+        // Only the newly read part can hold the last newline.
+        let complete = bytes[start..].iter().rposition(|b| *b == b'\n').map_or(0, |i| start + i + 1);
+        self.partial = bytes.split_off(complete);
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+/// Opens and reads the pipe at `path` on a new thread until it fails or
+/// the receiver is dropped.
+fn read_fifo(path: PathBuf) -> Receiver<io::Result<Vec<u8>>> {
+    let (chunks, received) = mpsc::channel();
+    thread::spawn(move || {
+        let mut pipe = match File::open(&path) {
+            Ok(pipe) => pipe,
+            Err(err) => {
+                let _ = chunks.send(Err(err));
+                return;
+            }
+        };
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match pipe.read(&mut buffer) {
+                // No writer has the pipe open; the next may open it any time.
+                Ok(0) => thread::sleep(FIFO_POLL),
+                Ok(n) => {
+                    if chunks.send(Ok(buffer[..n].to_vec())).is_err() {
+                        return;
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    let _ = chunks.send(Err(err));
+                    return;
+                }
+            }
+        }
+    });
+    received
+}
@@ -0,0 +1,17 @@
+//! Shell-style globbing for `--glob`, by the `glob` crate: `*`, `?`,
+//! `[...]` classes and `**` for any number of directories.
+
+use ::glob::MatchOptions;
+use std::path::PathBuf;
+
+/// Expands `pattern` to the sorted list of matching files. Like a shell, a
+/// wildcard never matches a leading dot; entries that can't be read are
+/// skipped. An error says what is wrong with a malformed pattern, e.g. an
+/// unclosed `[`.
+pub fn expand(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let options = MatchOptions { require_literal_leading_dot: true, ..MatchOptions::new() };
+    let paths = ::glob::glob_with(pattern, options).map_err(|err| format!("invalid --glob '{}': {}", pattern, err))?;
+    let mut files: Vec<PathBuf> = paths.filter_map(Result::ok).filter(|path| path.is_file()).collect();
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,187 @@
+//! `--format html`: a self-contained page per device with interactive
+//! charts. The data and a small canvas renderer are embedded in the page,
+//! so it opens offline and hovering a point reads back its exact time and
+//! value.
+
+use crate::json;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// How X values are labeled on hover and along the axis.
+pub enum XAxis {
+    Index,
+    /// Seconds since the Unix epoch, shown in the log's own UTC offset
+    /// (`utc_offset` seconds east).
+    Time { utc_offset: i32 },
+    /// Seconds since the first sample, or on the daemon's monotonic clock.
+    Elapsed,
+}
+
+impl XAxis {
+    fn to_json(&self) -> String {
+        match self {
+            XAxis::Index => json::Object::new().string("kind", "index").finish(),
+            XAxis::Time { utc_offset } => json::Object::new()
+                .string("kind", "time")
+                .raw("utc_offset", utc_offset.to_string())
+                .finish(),
+            XAxis::Elapsed => json::Object::new().string("kind", "elapsed").finish(),
+        }
+    }
+}
+
+/// One chart on the page.
+pub struct Chart<'a> {
+    pub title: &'a str,
+    /// Unit shown after hovered values.
+    pub unit: &'a str,
+    /// CSS color of the line, e.g. `#ff0000`.
+    pub color: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Chart<'_> {
+    fn to_json(&self) -> String {
+        json::Object::new()
+            .string("title", self.title)
+            .string("unit", self.unit)
+            .string("color", &self.color)
+            .raw("x", json::array(self.points.iter().map(|(x, _)| json::number(*x))))
+            .raw("y", json::array(self.points.iter().map(|(_, y)| json::number(*y))))
+            .finish()
+    }
+}
+
+/// Writes the page to `path`, with X values labeled as `x_axis` says.
+pub fn write_page(path: &Path, title: &str, x_axis: XAxis, charts: &[Chart]) -> io::Result<()> {
+    // Nothing in the data may close the script element early.
+    let data = json::array(charts.iter().map(Chart::to_json)).replace("</", "<\\/");
+    let page = TEMPLATE
+        .replace("{{title}}", &escape_html(title))
+        .replace("{{x_axis}}", &x_axis.to_json())
+        .replace("{{charts}}", &data);
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(page.as_bytes())?;
+    out.flush()
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+  body { font-family: sans-serif; margin: 20px; }
+  canvas { display: block; width: 100%; height: 360px; cursor: crosshair; }
+  .readout { font-family: monospace; min-height: 1.4em; margin-bottom: 24px; }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<div id="charts"></div>
+<script>
+const xAxis = {{x_axis}};
+const charts = {{charts}};
+
+function formatX(x) {
+  if (xAxis.kind === "index") return "sample " + x;
+  if (xAxis.kind === "elapsed") return x.toFixed(3) + " s";
+  const utcOffset = xAxis.utc_offset;
+  const local = new Date((x + utcOffset) * 1000).toISOString().replace("Z", "");
+  const abs = Math.abs(utcOffset);
+  const hours = String(Math.floor(abs / 3600)).padStart(2, "0");
+  const minutes = String(Math.floor((abs % 3600) / 60)).padStart(2, "0");
+  return local + (utcOffset < 0 ? "-" : "+") + hours + ":" + minutes;
+}
+
+function nearest(xs, x) {
+  let lo = 0, hi = xs.length - 1;
+  while (hi - lo > 1) {
+    const mid = (lo + hi) >> 1;
+    if (xs[mid] < x) lo = mid; else hi = mid;
+  }
+  return Math.abs(xs[lo] - x) <= Math.abs(xs[hi] - x) ? lo : hi;
+}
+
+function addChart(chart) {
+  const section = document.createElement("section");
+  const heading = document.createElement("h2");
+  heading.textContent = chart.title;
+  const canvas = document.createElement("canvas");
+  const readout = document.createElement("div");
+  readout.className = "readout";
+  section.append(heading, canvas, readout);
+  document.getElementById("charts").append(section);
+
+  const xs = chart.x, ys = chart.y;
+  if (xs.length === 0) return;
+  const xMin = xs[0], xMax = xs[xs.length - 1] > xs[0] ? xs[xs.length - 1] : xs[0] + 1;
+  let yMin = Infinity, yMax = -Infinity;
+  for (const y of ys) { if (y < yMin) yMin = y; if (y > yMax) yMax = y; }
+  if (yMax <= yMin) { yMin -= 1; yMax += 1; }
+  const pad = { left: 80, right: 20, top: 10, bottom: 30 };
+
+  function draw(hover) {
+    const ratio = window.devicePixelRatio || 1;
+    const width = canvas.clientWidth, height = canvas.clientHeight;
+    canvas.width = width * ratio;
+    canvas.height = height * ratio;
+    const ctx = canvas.getContext("2d");
+    ctx.scale(ratio, ratio);
+    const px = x => pad.left + (x - xMin) / (xMax - xMin) * (width - pad.left - pad.right);
+    const py = y => pad.top + (yMax - y) / (yMax - yMin) * (height - pad.top - pad.bottom);
+
+    ctx.strokeStyle = "#000";
+    ctx.strokeRect(pad.left, pad.top, width - pad.left - pad.right, height - pad.top - pad.bottom);
+    ctx.fillStyle = "#000";
+    ctx.font = "12px sans-serif";
+    ctx.textAlign = "right";
+    for (const y of [yMin, (yMin + yMax) / 2, yMax]) {
+      ctx.fillText(y.toFixed(2), pad.left - 6, py(y) + 4);
+    }
+    ctx.textAlign = "left";
+    ctx.fillText(formatX(xMin), pad.left, height - 10);
+    ctx.textAlign = "right";
+    ctx.fillText(formatX(xs[xs.length - 1]), width - pad.right, height - 10);
+
+    ctx.strokeStyle = chart.color;
+    ctx.beginPath();
+    xs.forEach((x, i) => i === 0 ? ctx.moveTo(px(x), py(ys[i])) : ctx.lineTo(px(x), py(ys[i])));
+    ctx.stroke();
+
+    if (hover !== undefined) {
+      ctx.strokeStyle = "#888";
+      ctx.beginPath();
+      ctx.moveTo(px(xs[hover]), pad.top);
+      ctx.lineTo(px(xs[hover]), height - pad.bottom);
+      ctx.stroke();
+      ctx.fillStyle = "#00c";
+      ctx.beginPath();
+      ctx.arc(px(xs[hover]), py(ys[hover]), 4, 0, 2 * Math.PI);
+      ctx.fill();
+    }
+  }
+
+  canvas.addEventListener("mousemove", event => {
+    const rect = canvas.getBoundingClientRect();
+    const fraction = (event.clientX - rect.left - pad.left) / (rect.width - pad.left - pad.right);
+    const index = nearest(xs, xMin + Math.min(Math.max(fraction, 0), 1) * (xMax - xMin));
+    readout.textContent = formatX(xs[index]) + "  " + ys[index] + " " + chart.unit;
+    draw(index);
+  });
+  canvas.addEventListener("mouseleave", () => { readout.textContent = ""; draw(); });
+  window.addEventListener("resize", () => draw());
+  draw();
+}
+
+charts.forEach(addChart);
+</script>
+</body>
+</html>
+"##;
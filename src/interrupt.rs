@@ -0,0 +1,35 @@
+//! Ctrl-C during a run: instead of dying with nothing printed, the run
+//! stops reading and reports the devices it has already finished.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Catches the first SIGINT, which sets [`requested`]; a second one ends
+/// the process as usual, for a run that doesn't stop soon enough.
+#[cfg(unix)]
+pub fn install() {
+    extern "C" fn handle(_: libc::c_int) {
+        REQUESTED.store(true, Ordering::Relaxed);
+        // signal() is async-signal-safe.
+        unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL) };
+    }
+
+    let handler: extern "C" fn(libc::c_int) = handle;
+    unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+}
+
+/// Ctrl-C is left alone elsewhere.
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// Whether Ctrl-C has been pressed since [`install`].
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+/// What reading a log fails with once Ctrl-C has been pressed.
+pub fn error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "interrupted")
+}
@@ -0,0 +1,201 @@
+//! Parsing and statistics for linuxptp (ptp4l, phc2sys and ts2phc) logs.
+//! The `ptp-stats` binary is a thin command-line front end over this crate.
+
+pub mod baseline;
+pub mod bundle;
+pub mod check;
+pub mod cli;
+pub mod color;
+pub mod config;
+pub mod correlation;
+pub mod demo;
+pub mod detrend;
+pub mod error;
+pub mod event;
+pub mod export;
+pub mod follow;
+pub mod glob;
+pub mod html;
+pub mod interrupt;
+pub mod json;
+pub mod logging;
+pub mod outliers;
+pub mod parser;
+pub mod plot;
+pub mod progress;
+pub mod record;
+pub mod report;
+pub mod servo;
+pub mod sparkline;
+pub mod spectrum;
+pub mod stats;
+pub mod streaming;
+pub mod summary;
+pub mod timing;
+pub mod unit;
+
+use parser::{Parser, TimeWindow};
+use record::Record;
+use statrs::statistics::Data;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::sync::OnceLock;
+use summary::{DeviceSummary, Drift, MeanShift, NegativeDelays, OffsetRate, Oscillation, Peak, StatBlock, CONFIDENCE_LEVEL, MIN_CORRELATION_SAMPLES};
+
+/// Why a log could not be parsed. Lines that don't match are skipped rather
+/// than treated as errors, so only reading the input can fail.
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "cannot read log: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
+fn default_parser() -> &'static Parser {
+    static PARSER: OnceLock<Parser> = OnceLock::new();
+    PARSER.get_or_init(|| Parser::new(None, TimeWindow::default()))
+}
+
+/// Parses one line of ptp4l, phc2sys or ts2phc output; `None` for anything
+/// that isn't a well-formed sample.
+pub fn parse_line(line: &str) -> Option<Record> {
+    default_parser().parse_line(line)
+}
+
+/// Parses ptp4l, phc2sys and ts2phc output into records, in log order.
+pub fn parse_reader<R: BufRead>(reader: R) -> Result<Vec<Record>, ParseError> {
+    RecordIter::new(reader).collect()
+}
+
+/// The records of ptp4l, phc2sys and ts2phc output, parsed one line at a
+/// time as they are read, so a log of any size takes constant memory.
+/// Lines that aren't samples, including ones that aren't valid UTF-8, are
+/// skipped as by [`parse_reader`]. A read error is yielded once and ends
+/// the iteration.
+pub struct RecordIter<R> {
+    lines: io::Split<R>,
+    failed: bool,
+}
+
+impl<R: BufRead> RecordIter<R> {
+    pub fn new(reader: R) -> RecordIter<R> {
+        RecordIter { lines: reader.split(b'\n'), failed: false }
+    }
+}
+
+impl<R: BufRead> Iterator for RecordIter<R> {
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err.into()));
+                }
+            };
+            if let Ok(line) = std::str::from_utf8(&line)
+                && let Some(record) = default_parser().parse_line(line)
+            {
+                return Some(Ok(record));
+            }
+        }
+        None
+    }
+}
+
+/// Computes the summary statistics for one device's records. The device
+/// name and grandmaster, which records don't carry, are left for the
+/// caller to fill in, as is the `trend`, which needs a threshold (see
+/// [`summary::Trend`]). `cleaned_offset`, `detrended_offset`,
+/// `time_weighted_offset` and `hourly` are only computed on request (see
+/// [`outliers::filter`], [`detrend::Detrend`], [`summary::TimeWeighted`]
+/// and [`summary::HourlyStats`]).
+pub fn summarize(records: &[Record]) -> DeviceSummary {
+    let offsets: Vec<f64> = records.iter().map(|r| r.offset).collect();
+    // Offsets paired with the delays of the records that have one.
+    let (delays, delay_offsets): (Vec<f64>, Vec<f64>) = records.iter().filter_map(|r| Some((r.delay?, r.offset))).unzip();
+    let freqs: Vec<f64> = records.iter().filter_map(|r| r.freq).collect();
+    let sample_interval = timing::median_interval(records);
+
+    DeviceSummary {
+        device: String::new(),
+        grandmaster: None,
+        samples: records.len(),
+        sample_interval,
+        completeness_pct: sample_interval.and_then(|interval| timing::completeness_pct(records, interval)),
+        offset: StatBlock::from_values(&offsets),
+        offset_mean_ci: stats::confidence_interval(&Data::new(offsets.clone()), CONFIDENCE_LEVEL),
+        abs_offset: abs_offset_stats(records, &offsets),
+        abs_offset_integral: sample_interval.map(|_| timing::integrate_abs(records)),
+        worst_offset: Peak::largest(records, |r| Some(r.worst_offset())),
+        time_in_spec: None,
+        settling: None,
+        cleaned_offset: None,
+        detrended_offset: None,
+        time_weighted_offset: None,
+        hourly: None,
+        locked_fraction: servo::locked_fraction(records),
+        oscillation: Oscillation::new(stats::zero_crossings(&offsets), records.len(), sample_interval),
+        offset_rate: OffsetRate::from_records(records, sample_interval),
+        mean_shifts: Some(MeanShift::find(records)),
+        trend: None,
+        delay: (!delays.is_empty()).then(|| StatBlock::from_values(&delays)),
+        worst_delay: Peak::largest(records, |r| r.delay),
+        negative_delays: NegativeDelays::from_records(records),
+        percentile_sample: None,
+        delay_offset_correlation: Some(stats::delta_correlation(&delays, &delay_offsets)).filter(|r| !r.is_nan()),
+        offset_delay_level_correlation: (delays.len() >= MIN_CORRELATION_SAMPLES).then(|| stats::pearson(&delay_offsets, &delays)).filter(|r| !r.is_nan()),
+        freq: (freqs.len() == records.len()).then(|| StatBlock::from_values(&freqs)),
+        freq_drift: freq_drift(records),
+    }
+}
+
+/// |offset| statistics. Pre-aggregated records report their interval's
+/// max |offset|, which their RMS values hide, so it raises the max.
+fn abs_offset_stats(records: &[Record], offsets: &[f64]) -> StatBlock {
+    let mut block = StatBlock::from_values(&stats::abs_values(offsets));
+    if let Some(max) = records.iter().filter_map(|r| r.max_offset).reduce(f64::max) {
+        block.max = block.max.max(max);
+    }
+    block
+}
+
+/// Fits freq against hours since the first sample. Needs every record to
+/// carry both a timestamp and a freq value.
+fn freq_drift(records: &[Record]) -> Option<Drift> {
+    let first = records.first()?.timestamp?;
+    let (hours, freqs): (Vec<f64>, Vec<f64>) = records
+        .iter()
+        .map(|r| Some(((r.timestamp? - first).as_seconds_f64() / 3600.0, r.freq?)))
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let (slope, _, r_squared) = stats::linear_regression(&hours, &freqs);
+    (!slope.is_nan()).then_some(Drift { ppb_per_hour: slope, r_squared })
+}
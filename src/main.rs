@@ -1,130 +1,1487 @@
+use chrono::Local;
+use log::debug;
+use ptp_stats::baseline::{self, Baseline, Comparison};
+use ptp_stats::check::{Level, RunStatus, Thresholds};
+use ptp_stats::cli::{self, Args, CliError, Machine, STDOUT_PATH};
+use ptp_stats::error::{self, PtpError};
+use ptp_stats::event;
+use ptp_stats::follow::Follower;
+use ptp_stats::parser::{parse_all, split_by_source, ParsedLog, Parser};
+use ptp_stats::plot::{ascii_offset_plot, create_adev_plot, create_autocorrelation_plot, create_box_plot, create_cdf_plot, create_combined_plot, create_comparison_plot, create_contact_sheet, create_delay_plot, create_diff_plot, create_detrended_plot, create_freq_plot, create_histogram, create_html_plot, create_mtie_plot, create_offset_plot, create_offset_rate_plot, create_rolling_stddev_plot, create_scatter_plot, create_spectrum_plot, metadata_subtitle, ImageFormat, PlotOptions, TimeAxis};
+use ptp_stats::progress::Progress;
+use ptp_stats::record::Record;
+use ptp_stats::sparkline::sparkline;
+use ptp_stats::summary::{to_json_document, DeviceSummary, FleetSummary, HourlyStats, MeanShift, NegativeDelays, Oscillation, Peak, RankBy, Settling, StatBlock, TimeWeighted, Trend, TrendDirection, CONFIDENCE_LEVEL, LOW_COMPLETENESS_PCT};
+use ptp_stats::timing::OutOfOrder;
+use ptp_stats::unit::Unit;
+use ptp_stats::{bundle, color, correlation, demo, export, interrupt, logging, outliers, report, servo, spectrum, stats, timing};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::Duration;
 
-use regex::Regex;
-use statrs::statistics::{Data, Max, Median, Min, Statistics};
-use std::fs::File;
-use std::io::{self, BufRead};
-use statrs::statistics::Distribution;
-use plotters::prelude::*;
-use std::path::PathBuf;
+fn write_servo_states(out: &mut dyn Write, records: &[Record]) -> io::Result<()> {
+    let counts = servo::state_counts(records);
+    if counts.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "\tServo States:")?;
+    for (state, count) in counts {
+        writeln!(out, "\t  s{}: {}", state, count)?;
+    }
+
+    for transition in servo::state_transitions(records) {
+        if transition.from == servo::LOCKED {
+            let when = match transition.timestamp {
+                Some(timestamp) => timestamp.to_rfc3339(),
+                None => format!("sample {}", transition.index),
+            };
+            writeln!(out, "\t  Lost lock (s{} -> s{}) at {}", transition.from, transition.to, when)?;
+        }
+    }
 
-fn create_offset_plot(data: &Vec<f64>, device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    create_plot(data, device_name, "Offset")
+    let runs = servo::state_runs(records);
+    let locked_runs = runs.iter().filter(|run| run.state == servo::LOCKED).count();
+    writeln!(out, "\t  Runs: {} (locked {})", runs.len(), if locked_runs == 1 { "once".to_string() } else { format!("{} times", locked_runs) })?;
+    for run in runs.iter().take(LISTED_RUNS) {
+        let duration = run.duration.map_or_else(String::new, |secs| format!(", {:.1} s", secs));
+        writeln!(out, "\t    s{} from sample {}: {}{}", run.state, run.index, plural(run.samples, "sample"), duration)?;
+    }
+    if runs.len() > LISTED_RUNS {
+        writeln!(out, "\t    ... {} more", runs.len() - LISTED_RUNS)?;
+    }
+    Ok(())
 }
 
-fn create_delay_plot(data: &Vec<f64>, device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    create_plot(data, device_name, "Delay")
+/// Servo state runs listed per device; a flapping servo can have thousands.
+const LISTED_RUNS: usize = 10;
+
+fn write_gaps(out: &mut dyn Write, gaps: &[timing::Gap]) -> io::Result<()> {
+    if gaps.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "\tGaps: {}", gaps.len())?;
+    for gap in gaps {
+        writeln!(out, "\t  {} -> {} ({:.2} s)", gap.start.to_rfc3339(), gap.end.to_rfc3339(), gap.duration())?;
+    }
+    Ok(())
 }
 
-fn create_plot(data: &Vec<f64>, device_name: &str, plot_type: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let filename = format!("plots/{}-{}.png", device_name, plot_type).to_lowercase();
-    let root = BitMapBackend::new(&filename, (640, 480)).into_drawing_area();
-    root.fill(&WHITE)?;
+/// Mean shifts listed per device; a drifting offset can have many.
+const LISTED_MEAN_SHIFTS: usize = 10;
+
+fn write_mean_shifts(out: &mut dyn Write, shifts: &[MeanShift], unit: Unit, precision: usize) -> io::Result<()> {
+    if shifts.is_empty() {
+        return Ok(());
+    }
 
-    let max_value = data.max();
-    let min_value = data.min();
+    writeln!(out)?;
+    writeln!(out, "\tMean shifts: {}", shifts.len())?;
+    for shift in shifts.iter().take(LISTED_MEAN_SHIFTS) {
+        writeln!(out, "\t  {}: {:.precision$} -> {:.precision$} {}", shift.location(), shift.before / unit.scale(), shift.after / unit.scale(), unit.label())?;
+    }
+    if shifts.len() > LISTED_MEAN_SHIFTS {
+        writeln!(out, "\t  ... {} more", shifts.len() - LISTED_MEAN_SHIFTS)?;
+    }
+    Ok(())
+}
+
+/// Explains why a log produced no samples, which is almost always a format
+/// mismatch rather than a genuinely empty capture.
+fn print_no_data<T>(log: &ParsedLog<T>) {
+    if log.total_lines == 0 {
+        println!("\tNo data: the file is empty.");
+    } else if log.total_samples > 0 {
+        println!("\tNo data: none of the {} samples fall inside the --from/--to window.", log.total_samples);
+    } else if log.encoding_errors == log.total_lines {
+        println!("\tNo data: none of the {} lines is valid UTF-8 text.", log.total_lines);
+    } else if log.excluded_lines == log.total_lines {
+        println!("\tNo data: all {} lines were excluded by --exclude.", log.total_lines);
+    } else if log.parse_failures > 0 {
+        println!("\tNo data: {} of {} lines matched but failed to parse.", log.parse_failures, log.total_lines);
+    } else if log.skipped_lines == 0 {
+        // With no samples, the lines left out of every count are events.
+        let event_lines = log.total_lines - log.encoding_errors - log.excluded_lines;
+        if event_lines > 0 {
+            println!("\tNo data: the log holds only events ({} of {} lines), no samples.", event_lines, log.total_lines);
+        } else {
+            println!("\tNo data: of the {} lines, {} were excluded by --exclude and the rest are not valid UTF-8.", log.total_lines, log.excluded_lines);
+        }
+    } else {
+        println!("\tNo data: the file has {} lines but none matched the expected pattern.", log.total_lines);
+        if !log.unmatched_examples.is_empty() {
+            println!("\tFirst non-matching lines:");
+            for line in &log.unmatched_examples {
+                println!("\t  > {}", line);
+            }
+        }
+    }
+}
+
+fn write_allan_deviation(out: &mut dyn Write, adev: &[(f64, f64)]) -> io::Result<()> {
+    writeln!(out, "\tAllan Deviation:")?;
+    for (tau, dev) in adev {
+        writeln!(out, "\t  tau {} s: {:.3e}", tau, dev)?;
+    }
+    Ok(())
+}
+
+fn write_mtie(out: &mut dyn Write, mtie: &[(usize, f64)], unit: Unit, precision: usize) -> io::Result<()> {
+    writeln!(out, "\tMTIE ({}):", unit.label())?;
+    for (window, value) in mtie {
+        writeln!(out, "\t  {} samples: {:.precision$}", window, value / unit.scale())?;
+    }
+    Ok(())
+}
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption(format!("{} {}", device_name, plot_type), ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(30)
-        .y_label_area_size(70)
-        .build_cartesian_2d(0..data.len(), min_value..max_value)?;
+/// Characters in each device's offset trend in the `--quiet` table.
+const SPARKLINE_WIDTH: usize = 20;
 
-    chart.configure_mesh().x_desc("Sample Number").y_desc("Value (nanoseconds)").draw()?;
+fn print_summary_table(summaries: &[DeviceSummary], limits: &[Thresholds], trends: &[String], unit: Unit, precision: usize) {
+    let name_width = summaries.iter().map(|s| s.device.len()).max().unwrap_or(0).max("Device".len());
+    let label = unit.label();
+    // Only shown when some device's grandmaster is known.
+    let grandmaster_column = |text: &str| match summaries.iter().any(|s| s.grandmaster.is_some()) {
+        true => format!("  {}", text),
+        false => String::new(),
+    };
 
-    chart.draw_series(LineSeries::new(
-        data.iter().enumerate().map(|(x, y)| (x, *y)),
-        &RED,
-    ))?;
+    println!(
+        "{:<name_width$}  {:>8}  {:<SPARKLINE_WIDTH$}  {:>17}  {:>17}  {:>17}  {:>17}{}",
+        "Device",
+        "Samples",
+        "Offset Trend",
+        format!("RMS Offset ({})", label),
+        format!("Max |Offset| ({})", label),
+        format!("Max Delay ({})", label),
+        format!("Delay Jitter ({})", label),
+        grandmaster_column("Grandmaster")
+    );
+    // ts2phc devices have no delay to show.
+    let delay_cell = |value: Option<f64>| value.map_or(format!("{:>17}", "-"), |value| format!("{:>17.precision$}", value / unit.scale()));
+    for ((summary, thresholds), trend) in summaries.iter().zip(limits).zip(trends) {
+        println!(
+            "{:<name_width$}  {:>8}  {:<SPARKLINE_WIDTH$}  {:>17.precision$}  {}  {}  {}{}",
+            summary.device,
+            summary.samples,
+            trend,
+            summary.offset.rms / unit.scale(),
+            color::paint(&format!("{:>17.precision$}", summary.abs_offset.max / unit.scale()), thresholds.offset_level(summary)),
+            color::paint(&delay_cell(summary.delay.as_ref().map(|delay| delay.max)), thresholds.delay_level(summary)),
+            color::paint(&delay_cell(summary.delay_jitter()), thresholds.jitter_level(summary)),
+            grandmaster_column(summary.grandmaster.as_deref().unwrap_or("-"))
+        );
+    }
+}
 
+/// Writes one stats block's `--metrics` with `--precision` fractional
+/// digits, coloring the max by `max_level` if given.
+fn write_stats(out: &mut dyn Write, colored: bool, label: &str, unit: &str, block: &StatBlock, max_level: Option<Level>, args: &Args) -> io::Result<()> {
+    writeln!(out, "\t{} Stats ({}):", label, unit)?;
+    for metric in &args.metrics {
+        let value = (metric.value)(block);
+        // Streamed blocks have no order statistics.
+        if metric.order_statistic && value.is_nan() {
+            continue;
+        }
+        let precision = if metric.shape { 2 } else { args.precision };
+        let mut text = format!("{:.precision$}", value);
+        if metric.name == "max" {
+            text = color::paint_if(colored, &text, max_level);
+        }
+        writeln!(out, "\t  {}: {}", metric.label.replace("{series}", label), text)?;
+    }
     Ok(())
 }
 
+/// `--hourly`: one row per clock hour.
+fn write_hourly(out: &mut dyn Write, hours: &[HourlyStats], unit: Unit, precision: usize) -> io::Result<()> {
+    let scale = unit.scale();
+    writeln!(out, "\tHourly Offset ({}):", unit.label())?;
+    writeln!(out, "\t  {:<16}  {:>7}  {:>12}  {:>12}", "Hour", "Samples", "Mean", "Max |offset|")?;
+    for hour in hours {
+        writeln!(
+            out,
+            "\t  {:<16}  {:>7}  {:>12.precision$}  {:>12.precision$}",
+            hour.start.format("%Y-%m-%d %H:00"),
+            hour.samples,
+            hour.mean / scale,
+            hour.max_abs / scale
+        )?;
+    }
+    Ok(())
+}
 
-fn parse_file(path: PathBuf, name: &str) -> io::Result<()> {
-    let file = File::open(path)?;
-    let reader = io::BufReader::new(file);
+/// Writes every stats block of a device in the chosen unit.
+fn write_device_stats(out: &mut dyn Write, colored: bool, summary: &DeviceSummary, thresholds: &Thresholds, args: &Args) -> io::Result<()> {
+    let unit = args.plot_options.unit;
+    let precision = args.precision;
+    let scale = unit.scale();
+    if let Some(grandmaster) = &summary.grandmaster {
+        writeln!(out, "\tGrandmaster: {}", grandmaster)?;
+        writeln!(out)?;
+    }
+    if let Some(trend) = &summary.trend {
+        write_trend(out, colored, trend, unit, precision)?;
+        writeln!(out)?;
+    }
+    if let Some(fraction) = summary.locked_fraction {
+        let locked = format!("{:.1}%", fraction * 100.0);
+        writeln!(out, "\tLocked: {} of samples", color::paint_if(colored, &locked, thresholds.lock_level(summary)))?;
+        writeln!(out)?;
+    }
+    if let Some(size) = summary.percentile_sample {
+        writeln!(out, "\tMedians and percentiles estimated from a random sample of {} of {} samples", size, summary.samples)?;
+        writeln!(out)?;
+    }
+    write_stats(out, colored, "Offset", unit.label(), &summary.offset.scaled(scale), None, args)?;
+    let (low, high) = summary.offset_mean_ci;
+    if low.is_finite() && high.is_finite() {
+        writeln!(out, "\t  Mean {:.0}% CI: {:.precision$} to {:.precision$}", CONFIDENCE_LEVEL * 100.0, low / scale, high / scale)?;
+    }
+    if let Some(weighted) = &summary.time_weighted_offset {
+        writeln!(out, "\t  Time-weighted mean: {:.precision$}", weighted.mean / scale)?;
+        writeln!(out, "\t  Time-weighted std dev: {:.precision$}", weighted.std_dev / scale)?;
+    }
+    write_oscillation(out, colored, &summary.oscillation)?;
+    writeln!(out)?;
+    write_stats(out, colored, "Abs Offset", unit.label(), &summary.abs_offset.scaled(scale), thresholds.offset_level(summary), args)?;
+    if let Some(peak) = &summary.worst_offset {
+        writeln!(out, "\t  Max |offset| {:.precision$} {} {}", peak.value / scale, unit.label(), peak.location())?;
+    }
+    if let Some(integral) = summary.abs_offset_integral {
+        writeln!(out, "\t  Integrated |offset|: {:.precision$} {}·s", integral / scale, unit.label())?;
+    }
+    if let (Some(fraction), Some(bound)) = (summary.time_in_spec, thresholds.max_offset_ns) {
+        writeln!(out, "\t  Within ±{:.precision$} {}: {:.2}%", bound / scale, unit.label(), fraction * 100.0)?;
+    }
+    if let (Some(mask), Some(margin)) = (thresholds.mask_ns, thresholds.mask_margin(summary)) {
+        let margin = match margin < 0.0 {
+            true => format!("{:.precision$} {} over", -margin / scale, unit.label()),
+            false => format!("{:.precision$} {} under", margin / scale, unit.label()),
+        };
+        writeln!(
+            out,
+            "\t  Mask margin: worst-case |offset| is {:.precision$} {}, {} the {:.precision$} {} mask",
+            summary.abs_offset.max / scale,
+            unit.label(),
+            color::paint_if(colored, &margin, thresholds.mask_level(summary)),
+            mask / scale,
+            unit.label()
+        )?;
+    }
+    if let Some(settling) = &summary.settling {
+        let bound = format!("±{:.precision$} {}", settling.bound / scale, unit.label());
+        match settling.seconds {
+            Some(seconds) => writeln!(out, "\t  Settled within {} after {:.1} s", bound, seconds)?,
+            None => writeln!(out, "\t  Not settled within {} by the end of the log", bound)?,
+        }
+    }
+    writeln!(out)?;
+    if let Some((cleaned, removed)) = &summary.cleaned_offset {
+        writeln!(out, "\tOutliers removed: {}", removed)?;
+        write_stats(out, colored, "Cleaned Offset", unit.label(), &cleaned.scaled(scale), None, args)?;
+        writeln!(out)?;
+    }
+    if let Some(rate) = &summary.offset_rate {
+        write_stats(out, colored, "Offset Rate", &format!("{}/{}", unit.label(), rate.per()), &rate.stats.scaled(scale), None, args)?;
+        if let Some(peak) = &rate.largest {
+            writeln!(out, "\t  Largest change {:.precision$} {}/{} {}", peak.value / scale, unit.label(), rate.per(), peak.location())?;
+        }
+        writeln!(out)?;
+    }
+    if let Some((detrended, detrend)) = &summary.detrended_offset {
+        writeln!(out, "\tDetrended by {}:", detrend)?;
+        write_stats(out, colored, "Detrended Offset", unit.label(), &detrended.scaled(scale), None, args)?;
+        let drift_share = 1.0 - (detrended.std_dev / summary.offset.std_dev).powi(2);
+        writeln!(
+            out,
+            "\t  Std dev {:.precision$} {} detrended, {:.precision$} raw; drift is {:.1}% of the variance",
+            detrended.std_dev / scale,
+            unit.label(),
+            summary.offset.std_dev / scale,
+            drift_share * 100.0
+        )?;
+        writeln!(out)?;
+    }
+    if let Some(hours) = summary.hourly.as_ref().filter(|hours| !hours.is_empty()) {
+        write_hourly(out, hours, unit, precision)?;
+        writeln!(out)?;
+    }
+    match &summary.delay {
+        Some(delay) => {
+            write_stats(out, colored, "Delay", unit.label(), &delay.scaled(scale), thresholds.delay_level(summary), args)?;
+            if let Some(peak) = &summary.worst_delay {
+                writeln!(out, "\t  Max delay {:.precision$} {} {}", peak.value / scale, unit.label(), peak.location())?;
+            }
+            let jitter = format!("{:.precision$}", delay.std_dev / scale);
+            writeln!(out, "\t  Jitter (std dev): {} {}", color::paint_if(colored, &jitter, thresholds.jitter_level(summary)), unit.label())?;
+        }
+        // ts2phc measures no path delay.
+        None => writeln!(out, "\tDelay: none logged")?,
+    }
+    if let Some(negative) = &summary.negative_delays {
+        let warning = format!("Warning: {} a negative path delay, the first {}; timestamping may be broken", negative_delay_count(negative), negative_delay_example(negative, unit, precision));
+        writeln!(out, "\t  {}", color::paint_if(colored, &warning, Some(Level::Bad)))?;
+    }
+    if let Some(r) = summary.offset_delay_level_correlation {
+        writeln!(out, "\t  Delay/offset correlation: {:.2}", r)?;
+    }
+    if let Some(r) = summary.delay_offset_correlation {
+        writeln!(out, "\t  Delay/offset step correlation: {:.2}", r)?;
+    }
+    if let Some(ratio) = summary.delay_offset_ratio() {
+        writeln!(out, "\t  Delay/|offset| ratio: {:.1}", ratio)?;
+    }
+    if summary.asymmetry_suspected() {
+        let warning = "Warning: offset moves with path delay; the network path may have become asymmetric";
+        writeln!(out, "\t  {}", color::paint_if(colored, warning, Some(Level::Bad)))?;
+    }
+    if let Some(freq) = &summary.freq {
+        writeln!(out)?;
+        write_stats(out, colored, "Freq", "ppb", freq, None, args)?;
+    }
+    if let Some(drift) = &summary.freq_drift {
+        writeln!(out, "\t  Drift: {:.2} ppb/hour (R² {:.2})", drift.ppb_per_hour, drift.r_squared)?;
+    }
+    Ok(())
+}
 
-    let re = Regex::new(
-            r#"(?x)
-            ^(?P<timestamp>\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}-\d{2}:\d{2})\s+
-            \w+\s+
-            ptp4l
+/// The trend verdict, colored green when improving and yellow when
+/// degrading, with the slope behind it.
+fn write_trend(out: &mut dyn Write, colored: bool, trend: &Trend, unit: Unit, precision: usize) -> io::Result<()> {
+    writeln!(
+        out,
+        "\tTrend: {} (|offset| {:+.precision$} {}/h, R² {:.2})",
+        color::paint_if(colored, trend.direction.label(), trend_level(trend.direction)),
+        trend.ns_per_hour / unit.scale(),
+        unit.label(),
+        trend.r_squared
+    )
+}
 
-        \[\d+\]
+fn trend_level(direction: TrendDirection) -> Option<Level> {
+    match direction {
+        TrendDirection::Improving => Some(Level::Good),
+        TrendDirection::Stable => None,
+        TrendDirection::Degrading => Some(Level::Warning),
+    }
+}
 
-        :\s+
-            ptp4l
+fn write_oscillation(out: &mut dyn Write, colored: bool, oscillation: &Oscillation) -> io::Result<()> {
+    match oscillation.per_minute {
+        Some(rate) => writeln!(out, "\t  Zero crossings: {} ({:.1}/min)", oscillation.zero_crossings, rate)?,
+        None => writeln!(out, "\t  Zero crossings: {}", oscillation.zero_crossings)?,
+    }
+    if oscillation.oscillating {
+        let warning = "Warning: offset changes sign on almost every sample; the servo may be oscillating";
+        writeln!(out, "\t  {}", color::paint_if(colored, warning, Some(Level::Bad)))?;
+    }
+    Ok(())
+}
 
-        \[(?P<internal_ts>\d+\.\d+)\]
+/// What `report_device` computed for one device, for its text output.
+struct DeviceDetails<'a> {
+    summary: &'a DeviceSummary,
+    records: &'a [Record],
+    gaps: &'a [timing::Gap],
+    adev: &'a [(f64, f64)],
+    mtie: &'a [(usize, f64)],
+    /// Offset autocorrelation by lag, `interval` seconds apart.
+    autocorrelation: &'a [f64],
+    interval: f64,
+    /// The largest path delays, for `--delay-spikes`.
+    delay_spikes: &'a [Peak],
+}
 
-        :\s+
-            master\s+offset\s+(?P<offset>[+-]?\d+)\s+s\d\s+
-            freq\s+(?P<freq>[+-]?\d+)\s+
-            path\s+delay\s+(?P<delay>[+-]?\d+)
-            "#
-        ).unwrap();
+impl DeviceDetails<'_> {
+    fn write_delay_spikes(&self, out: &mut dyn Write, unit: Unit, precision: usize) -> io::Result<()> {
+        writeln!(out, "\tLargest delays:")?;
+        for (rank, peak) in self.delay_spikes.iter().enumerate() {
+            let when = match peak.timestamp {
+                Some(timestamp) => timestamp.to_rfc3339(),
+                None => format!("sample {}", peak.index),
+            };
+            let offset = self.records[peak.index].offset;
+            writeln!(
+                out,
+                "\t  {:>2}. {}  delay {:.precision$} {}  offset {:.precision$} {}",
+                rank + 1,
+                when,
+                peak.value / unit.scale(),
+                unit.label(),
+                offset / unit.scale(),
+                unit.label()
+            )?;
+        }
+        Ok(())
+    }
 
-    let mut offsets = Vec::new();
-    let mut delays = Vec::new();
+    /// The lag at which the offset decorrelates, as the servo's effective
+    /// time constant.
+    fn write_autocorrelation(&self, out: &mut dyn Write) -> io::Result<()> {
+        match stats::decorrelation_lag(self.autocorrelation) {
+            Some(lag) => writeln!(out, "\tAutocorrelation: below 1/e after {} ({:.1} s)", plural(lag, "sample"), lag as f64 * self.interval),
+            None => writeln!(out, "\tAutocorrelation: still above 1/e at {}", plural(self.autocorrelation.len() - 1, "sample")),
+        }
+    }
 
-    for line in reader.lines() {
-        let line = line?;
-        if let Some(cap) = re.captures(&line) {
-            let offset: f64 = cap["offset"].parse().unwrap();
-            let delay: f64 = cap["delay"].parse().unwrap();
-            offsets.push(offset);
-            delays.push(delay);
+    /// Writes the device's full text block: stats, timing, servo states,
+    /// Allan deviation, MTIE and autocorrelation.
+    fn write(&self, out: &mut dyn Write, colored: bool, thresholds: &Thresholds, args: &Args) -> io::Result<()> {
+        let (unit, precision) = (args.plot_options.unit, args.precision);
+        write_device_stats(out, colored, self.summary, thresholds, args)?;
+        if !self.delay_spikes.is_empty() {
+            writeln!(out)?;
+            self.write_delay_spikes(out, unit, precision)?;
+        }
+        if let Some(interval) = self.summary.sample_interval {
+            writeln!(out)?;
+            writeln!(out, "\tSample interval: {:.2} s", interval)?;
+            if timing::is_irregular(self.records) {
+                writeln!(out, "\t  Warning: intervals vary widely; log lines may have been dropped")?;
+            }
+        }
+        if let Some(pct) = self.summary.completeness_pct {
+            let level = (pct < LOW_COMPLETENESS_PCT).then_some(Level::Bad);
+            writeln!(out, "\tCompleteness: {} of expected samples", color::paint_if(colored, &format!("{:.1}%", pct), level))?;
+            if level.is_some() {
+                writeln!(out, "\t  Warning: log lines are missing; the statistics may be biased")?;
+            }
+        }
+        write_gaps(out, self.gaps)?;
+        write_mean_shifts(out, self.summary.mean_shifts.as_deref().unwrap_or_default(), unit, precision)?;
+        write_servo_states(out, self.records)?;
+        if !self.adev.is_empty() {
+            writeln!(out)?;
+            write_allan_deviation(out, self.adev)?;
+        }
+        if !self.mtie.is_empty() {
+            writeln!(out)?;
+            write_mtie(out, self.mtie, unit, precision)?;
         }
+        if !self.autocorrelation.is_empty() {
+            writeln!(out)?;
+            self.write_autocorrelation(out)?;
+        }
+        Ok(())
     }
+}
 
-    if !offsets.is_empty() && !delays.is_empty() {
-        let offset_data = Data::new(offsets.clone());
-        let delay_data = Data::new(delays.clone());
+/// Ends the run when stdout can't be written. A closed pipe (e.g. `| head`)
+/// means the reader has all it wanted, so that is not an error.
+fn stdout_failed(err: io::Error) -> ! {
+    if err.kind() == io::ErrorKind::BrokenPipe {
+        process::exit(0);
+    }
+    eprintln!("Error: {}", stdout_error(err));
+    process::exit(1);
+}
 
-        println!("\tOffset Stats:");
-        println!("\t  Mean: {:.2}", offset_data.mean().unwrap());
-        println!("\t  Median: {:.2}", offset_data.median());
-        println!("\t  Min: {:.2}", offset_data.min());
-        println!("\t  Max: {:.2}", offset_data.max());
-        println!("\t  Std Dev: {:.2}", offset_data.std_dev().unwrap());
-        
-        let _ = create_offset_plot(&offsets, name);
+fn stdout_error(err: io::Error) -> PtpError {
+    PtpError::io("cannot write to stdout", err)
+}
 
-        println!("\n\tDelay Stats:");
-        println!("\t  Mean: {:.2}", delay_data.mean().unwrap());
-        println!("\t  Median: {:.2}", delay_data.median());
-        println!("\t  Min: {:.2}", delay_data.min());
-        println!("\t  Max: {:.2}", delay_data.max());
-        println!("\t  Std Dev: {:.2}", delay_data.std_dev().unwrap());
-        let _ = create_delay_plot(&delays, name);
+/// Sends a device's text block to stdout, colored when stdout allows, if
+/// the run prints details, and to `--text-out` if given.
+fn emit_text(args: &Args, device: &str, write: impl Fn(&mut dyn Write, bool) -> io::Result<()>) {
+    if args.detailed()
+        && let Err(err) = write(&mut io::stdout().lock(), color::enabled())
+    {
+        stdout_failed(err);
+    }
 
-        return Ok(());
-    } else {
-        println!("No valid offset or delay data found.");
+    if let Some(dir) = &args.text_out
+        && let Err(err) = export::write_text(dir, device, |out| write(out, false))
+    {
+        eprintln!("[{}] Error writing text report: {}", device, err);
     }
+}
 
-    Ok(())
+/// Prints the log's grandmaster changes and clock jumps, which usually
+/// explain a sudden step in the offset.
+fn print_events<T>(log: &ParsedLog<T>) {
+    if log.events.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("\tEvents: {}", log.events.len());
+    for event in &log.events {
+        println!("\t  {} {}", event.kind, event.location());
+    }
 }
 
-fn main() {
+/// Prints how many of one source's samples don't follow the sample before
+/// in time, if any.
+fn print_out_of_order(order: OutOfOrder, sorted: bool) {
+    if order.total() > 0 {
+        let fix = if sorted { "sorted by time" } else { "time plots and intervals are unreliable; see --sort-by-time" };
+        println!("\tOut-of-order timestamps: {} ({} duplicated, {} backward); {}", order.total(), order.duplicates, order.backward, fix);
+    }
+}
 
-    let base_path = "/home/agebhard/Documents/repos/ptp-stats/data";
+/// Prints how many lines of a log were not usable samples.
+fn print_line_counts<T>(log: &ParsedLog<T>) {
+    if log.skipped_lines > 0 || log.parse_failures > 0 || log.encoding_errors > 0 || log.excluded_lines > 0 {
+        println!();
+    }
+    if log.excluded_lines > 0 {
+        println!("\tExcluded lines: {}", log.excluded_lines);
+    }
+    if log.skipped_lines > 0 {
+        println!("\tSkipped lines: {}", log.skipped_lines);
+    }
+    if log.parse_failures > 0 {
+        println!("\tSkipped lines (parse errors): {}", log.parse_failures);
+    }
+    if log.encoding_errors > 0 {
+        println!("\tSkipped lines (invalid UTF-8): {}", log.encoding_errors);
+    }
+    println!();
+}
 
-    let machines = [
-        ("Beta", PathBuf::from(base_path).join("beta.log")),
-        ("Charlie", PathBuf::from(base_path).join("charlie.log")),
-        ("Delta", PathBuf::from(base_path).join("delta.log")),
-        ("Echo", PathBuf::from(base_path).join("echo.log")),
-    ];
+/// Sample and line counts across every log, for the closing summary line,
+/// and the logs Ctrl-C kept out of it.
+#[derive(Debug, Default)]
+struct RunTotals {
+    samples: usize,
+    skipped_lines: usize,
+    /// Logs with a line `--strict` rejected.
+    nonconforming_logs: usize,
+    /// Logs being read when Ctrl-C was pressed.
+    interrupted_logs: Vec<String>,
+    /// Logs Ctrl-C kept from being read or, once read, reported.
+    unreported_logs: Vec<String>,
+}
 
-    for (name, path) in machines {
-        println!("{}", name);
+impl RunTotals {
+    fn add<T>(&mut self, log: &ParsedLog<T>, samples: usize) {
+        self.samples += samples;
+        self.skipped_lines += log.skipped_lines + log.parse_failures + log.encoding_errors;
+    }
 
-        let status = parse_file(path, name);
+    /// `status`, unless Ctrl-C kept `machine` from being read or reported.
+    /// `read_in_full` is whether every log was read before Ctrl-C, which
+    /// then stops the reporting instead.
+    fn unless_interrupted<T>(&mut self, machine: &Machine, status: Option<io::Result<T>>, read_in_full: bool) -> Option<io::Result<T>> {
         match status {
-            Ok(_) => { println!(""); }
-            Err(err) => { println!("[{}] Error: {:?}", name, err) }
+            Some(Err(err)) if err.kind() == io::ErrorKind::Interrupted => self.interrupted_logs.push(machine.name.clone()),
+            Some(status) if !(read_in_full && interrupt::requested()) => return Some(status),
+            _ => self.unreported_logs.push(machine.name.clone()),
         }
+        None
+    }
+
+    fn interrupted(&self) -> bool {
+        !self.interrupted_logs.is_empty() || !self.unreported_logs.is_empty()
+    }
+}
+
+/// Says which logs the results above leave out after Ctrl-C.
+fn print_interrupted(totals: &RunTotals) {
+    let mut missing = Vec::new();
+    if !totals.interrupted_logs.is_empty() {
+        missing.push(format!("stopped reading {}", totals.interrupted_logs.join(", ")));
+    }
+    if !totals.unreported_logs.is_empty() {
+        missing.push(format!("{} not reported: {}", plural(totals.unreported_logs.len(), "log"), totals.unreported_logs.join(", ")));
     }
+    eprintln!("Interrupted: {}.", missing.join("; "));
+}
+
+/// Formats `n` with comma thousands separators, e.g. `1,234,567`.
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// `-v`: what became of each line of `log`, with `kept` samples left after
+/// the `--from`/`--to` window and `--tail`.
+fn debug_counts<T>(name: &str, log: &ParsedLog<T>, kept: usize) {
+    debug!(
+        "[{}] matched {} of {} lines, kept {}; excluded {}; skipped {} unmatched, {} parse errors, {} invalid UTF-8; {} events",
+        name,
+        log.total_samples,
+        log.total_lines,
+        kept,
+        log.excluded_lines,
+        log.skipped_lines,
+        log.parse_failures,
+        log.encoding_errors,
+        log.events.len()
+    );
+}
+
+fn negative_delay_count(negative: &NegativeDelays) -> String {
+    match negative.count {
+        1 => "1 sample has".to_string(),
+        n => format!("{} samples have", thousands(n)),
+    }
+}
+
+fn negative_delay_example(negative: &NegativeDelays, unit: Unit, precision: usize) -> String {
+    format!("{:.precision$} {} {}", negative.first.value / unit.scale(), unit.label(), negative.first.location())
+}
+
+/// `--strict`: reports a device with a negative path delay, which is as
+/// impossible as a malformed line. Returns whether it had one.
+fn report_negative_delays(args: &Args, summary: &DeviceSummary) -> bool {
+    let Some(negative) = summary.negative_delays.as_ref().filter(|_| args.strict) else {
+        return false;
+    };
+    let (unit, precision) = (args.plot_options.unit, args.precision);
+    eprintln!("[{}] Error: {} a negative path delay, the first {}", summary.device, negative_delay_count(negative), negative_delay_example(negative, unit, precision));
+    true
+}
+
+/// `--strict`: reports the first line of `log` that is not a sample or an
+/// event. Returns whether there was one.
+fn report_unparsed<T>(args: &Args, machine: &Machine, log: &ParsedLog<T>) -> bool {
+    let Some((number, line)) = log.first_unparsed.as_ref().filter(|_| args.strict) else {
+        return false;
+    };
+    eprintln!("[{}] Error: line {} of {} does not match the log format: {}", machine.name, number, machine.display_paths(), line);
+    true
+}
+
+fn plural(n: usize, word: &str) -> String {
+    format!("{} {}{}", thousands(n), word, if n == 1 { "" } else { "s" })
+}
+
+/// Prints a sub-device heading when one log holds several sources.
+fn print_source_heading(device: &str, index: usize) {
+    if index > 0 {
+        println!();
+    }
+    println!("  {}", device);
+}
+
+/// Every device taken together, after the device blocks of a run with
+/// more than one.
+fn print_fleet_summary(fleet: &FleetSummary, unit: Unit, precision: usize) {
+    let label = unit.label();
+    println!();
+    println!("Fleet Summary:");
+    println!("  Devices: {}, samples: {}", fleet.devices, thousands(fleet.samples));
+    println!("  Pooled RMS offset: {:.precision$} {}", fleet.pooled_rms / unit.scale(), label);
+    println!("  Median device RMS offset: {:.precision$} {}", fleet.median_rms / unit.scale(), label);
+    println!("  Worst device: {} (RMS {:.precision$} {})", fleet.worst_device, fleet.worst_rms / unit.scale(), label);
+    println!("  Max |offset|: {:.precision$} {} on {}", fleet.max_abs_offset / unit.scale(), label, fleet.max_abs_offset_device);
+}
+
+/// `--top`: the worst devices by `args.rank_by`, one line each.
+fn print_ranking(args: &Args, summaries: &[DeviceSummary], n: usize) {
+    let ranked = args.rank_by.worst(summaries, n);
+    if ranked.is_empty() {
+        return;
+    }
+
+    let unit = args.plot_options.unit;
+    let precision = args.precision;
+    let width = ranked.iter().map(|summary| summary.device.len()).max().unwrap_or(0);
+    println!();
+    println!("Worst {} by {}:", ranked.len(), args.rank_by.label());
+    let values: Vec<String> = ranked
+        .iter()
+        .map(|summary| match args.rank_by {
+            RankBy::Rms => format!("{:.precision$} {}", summary.offset.rms / unit.scale(), unit.label()),
+            RankBy::MaxOffset => format!("{:.precision$} {}", summary.abs_offset.max / unit.scale(), unit.label()),
+            RankBy::DelayJitter => match summary.delay_jitter() {
+                Some(jitter) => format!("{:.precision$} {}", jitter / unit.scale(), unit.label()),
+                None => "no delays".to_string(),
+            },
+            RankBy::Lock => match summary.locked_fraction {
+                Some(fraction) => format!("{:.1}% locked", fraction * 100.0),
+                None => "no servo states".to_string(),
+            },
+        })
+        .collect();
+    let value_width = values.iter().map(|value| value.len()).max().unwrap_or(0);
+    for (rank, (summary, value)) in ranked.iter().zip(&values).enumerate() {
+        // Who is getting worse, not just who is worst now.
+        match &summary.trend {
+            Some(trend) => println!(
+                "  {:>2}. {:width$}  {:value_width$}  {}",
+                rank + 1,
+                summary.device,
+                value,
+                color::paint(trend.direction.label(), trend_level(trend.direction))
+            ),
+            None => println!("  {:>2}. {:width$}  {}", rank + 1, summary.device, value),
+        }
+    }
+}
+
+/// `--baseline`: each device's change since the earlier run, with
+/// regressions flagged.
+fn print_baseline_comparison(summaries: &[DeviceSummary], baselines: &[Baseline], unit: Unit, precision: usize) {
+    let width = summaries.iter().map(|summary| summary.device.len()).max().unwrap_or(0);
+    let percent = |delta: f64, then: f64| if then == 0.0 { String::new() } else { format!(" ({:+.1}%)", delta / then * 100.0) };
+
+    println!();
+    println!("Compared with baseline:");
+    for summary in summaries {
+        let Some(baseline) = baselines.iter().find(|baseline| baseline.device == summary.device) else {
+            println!("  {:width$}  not in baseline", summary.device);
+            continue;
+        };
+
+        let comparison = Comparison::new(summary, baseline);
+        let mut line = format!(
+            "  {:width$}  RMS {:+.precision$} {}{}, max |offset| {:+.precision$} {}{}",
+            summary.device,
+            comparison.rms_delta / unit.scale(),
+            unit.label(),
+            percent(comparison.rms_delta, baseline.rms),
+            comparison.max_offset_delta / unit.scale(),
+            unit.label(),
+            percent(comparison.max_offset_delta, baseline.max_offset),
+        );
+        if let Some(delta) = comparison.lock_delta {
+            line.push_str(&format!(", locked {:+.1} pts", delta));
+        }
+        if !comparison.regressions.is_empty() {
+            let flag = format!("regressed: {}", comparison.regressions.join(", "));
+            line.push_str(&format!("  {}", color::paint(&flag, Some(Level::Bad))));
+        }
+        println!("{}", line);
+    }
+}
+
+/// The grandmaster of a log's devices: the config file's, else the last
+/// one the log shows selected.
+fn grandmaster<T>(machine: &Machine, log: &ParsedLog<T>) -> Option<String> {
+    machine
+        .grandmaster
+        .clone()
+        .or_else(|| event::last_grandmaster(&log.events).map(str::to_string))
+}
+
+/// Per-device series grouped by the grandmaster in each device's summary,
+/// in device order within a group. Devices without a known grandmaster
+/// form the `None` group.
+fn by_grandmaster<'a, T>(series: &'a [(String, T)], summaries: &[DeviceSummary]) -> BTreeMap<Option<String>, Vec<(&'a str, &'a T)>> {
+    let mut groups: BTreeMap<Option<String>, Vec<(&str, &T)>> = BTreeMap::new();
+    for (name, data) in series {
+        let grandmaster = summaries.iter().find(|summary| &summary.device == name).and_then(|summary| summary.grandmaster.clone());
+        groups.entry(grandmaster).or_default().push((name, data));
+    }
+    groups
+}
+
+/// Prints the offset correlation matrix, one row and column per device,
+/// with one matrix per grandmaster when devices follow different ones.
+fn print_correlation(series: &[(String, Vec<(f64, f64)>)], summaries: &[DeviceSummary]) {
+    let groups = by_grandmaster(series, summaries);
+    if groups.len() < 2 {
+        let group: Vec<(&str, &Vec<(f64, f64)>)> = groups.into_values().flatten().collect();
+        print_correlation_matrix(&group, "Offset correlation");
+        return;
+    }
+    for (grandmaster, group) in &groups {
+        let heading = match grandmaster {
+            Some(grandmaster) => format!("Offset correlation, grandmaster {}", grandmaster),
+            None => "Offset correlation, unknown grandmaster".to_string(),
+        };
+        print_correlation_matrix(group, &heading);
+    }
+}
+
+fn print_correlation_matrix(series: &[(&str, &Vec<(f64, f64)>)], heading: &str) {
+    println!();
+    if series.len() < 2 {
+        println!("{} needs at least two devices with samples", heading);
+        return;
+    }
+
+    let names: Vec<&str> = series.iter().map(|(name, _)| *name).collect();
+    let samples: Vec<&[(f64, f64)]> = series.iter().map(|(_, samples)| samples.as_slice()).collect();
+    let bucket = correlation::bucket_width(&samples);
+    let matrix = correlation::matrix(&samples, bucket);
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0).max(6);
+
+    println!("{} ({:.2} s buckets):", heading, bucket);
+    print!("  {:width$}", "");
+    for name in &names {
+        print!("  {:>width$}", name);
+    }
+    println!();
+    for (name, row) in names.iter().zip(&matrix) {
+        print!("  {:width$}", name);
+        for value in row {
+            match value {
+                Some(r) => print!("  {:>width$.2}", r),
+                None => print!("  {:>width$}", "-"),
+            }
+        }
+        println!();
+    }
+}
+
+/// How many of a log's samples survived `--from`/`--to` and `--tail`,
+/// when either is in effect.
+fn print_samples_kept(args: &Args, kept: usize, total: usize) {
+    if args.tail.is_some() {
+        println!("\tSamples kept: {} of {}", kept, total);
+    } else if args.window.is_set() {
+        println!("\tSamples in window: {} of {}", kept, total);
+    }
+}
+
+/// Notes a device skipped for having fewer than `--min-samples` samples.
+fn write_insufficient_data(out: &mut dyn Write, samples: usize, min_samples: usize) -> io::Result<()> {
+    writeln!(out, "\tInsufficient data ({}); stats and plots need at least {}.", plural(samples, "sample"), min_samples)
+}
+
+fn report_plot_error(name: &str, plot: &str, result: Result<(), Box<dyn std::error::Error>>) {
+    if let Err(err) = result {
+        eprintln!("[{}] Error: {}", name, PtpError::Plot { plot: plot.to_string(), source: err });
+    }
+}
+
+fn report_device(
+    name: &str,
+    records: &[Record],
+    args: &Args,
+    thresholds: &Thresholds,
+    grandmaster: Option<String>,
+    offset_series: &mut Vec<(String, Vec<f64>)>,
+) -> Option<DeviceSummary> {
+    if records.is_empty() {
+        return None;
+    }
+    if records.len() < args.min_samples {
+        emit_text(args, name, |out, _| write_insufficient_data(out, records.len(), args.min_samples));
+        return None;
+    }
+
+    let offsets: Vec<f64> = records.iter().map(|r| r.offset).collect();
+    // Delay plots share the offset's time axis, so they need every delay.
+    let delays: Option<Vec<f64>> = records.iter().map(|r| r.delay).collect();
+    let freqs: Vec<f64> = records.iter().filter_map(|r| r.freq).collect();
+    let gaps = timing::gaps(records, args.gap_factor);
+    let axis = TimeAxis::from_records(records, args.plot_options.x_axis).with_gaps(&gaps);
+
+    let summary = DeviceSummary {
+        device: name.to_string(),
+        grandmaster,
+        time_in_spec: thresholds.max_offset_ns.map(|bound| stats::fraction_within(&offsets, bound)),
+        settling: args.settle_ns.and_then(|bound| Settling::from_records(records, bound)),
+        trend: Trend::from_records(records, args.trend_ns_per_hour),
+        cleaned_offset: args.drop_outliers.then(|| {
+            let (cleaned, removed) = outliers::filter(&offsets);
+            (StatBlock::from_values(&cleaned), removed)
+        }),
+        detrended_offset: args.detrend.map(|detrend| (StatBlock::from_values(&detrend.apply(&offsets)), detrend)),
+        time_weighted_offset: args.time_weighted.then(|| TimeWeighted::from_records(records)).flatten(),
+        hourly: args.hourly.then(|| HourlyStats::buckets(records)),
+        // --sample-rate gives the rate the log should have been written at.
+        completeness_pct: match args.sample_rate {
+            Some(_) => timing::completeness_pct(records, args.tau0),
+            None => timing::median_interval(records).and_then(|interval| timing::completeness_pct(records, interval)),
+        },
+        ..ptp_stats::summarize(records)
+    };
+
+    let subtitled = args.subtitle.then(|| PlotOptions {
+        subtitle: Some(metadata_subtitle(records, summary.offset.rms, args.plot_options.unit, args.precision)),
+        ..args.plot_options.clone()
+    });
+    let plot_options = subtitled.as_ref().unwrap_or(&args.plot_options);
+    let ascii_chart = (!args.no_plots && plot_options.format == ImageFormat::Ascii && args.detailed())
+        .then(|| ascii_offset_plot(plot_options, &axis, &offsets));
+    // Without --resample the samples are taken to be evenly spaced.
+    let resampled = args.resample.then(|| timing::resample(records, args.tau0)).flatten();
+    let uniform = resampled.as_deref().unwrap_or(&offsets);
+    let adev = stats::allan_deviation(uniform, args.tau0);
+    let mtie = stats::mtie(uniform, &stats::octave_windows(uniform.len()));
+    let autocorrelation = stats::autocorrelation(uniform, (uniform.len() / 4).min(AUTOCORRELATION_MAX_LAG));
+    // Without timestamps, --tau0 is the best guess at the sample interval;
+    // --sample-rate overrides whatever coarse timestamps suggest.
+    let interval = summary.sample_interval.filter(|_| args.sample_rate.is_none()).unwrap_or(args.tau0);
+    if !args.no_plots {
+        let shifts: Vec<usize> = summary.mean_shifts.iter().flatten().filter(|_| args.mark_shifts).map(|shift| shift.index).collect();
+        report_plot_error(name, "offset plot", create_offset_plot(plot_options, &axis.marked(&shifts), &offsets, name));
+        if let Some(detrend) = args.detrend {
+            report_plot_error(name, "detrended offset plot", create_detrended_plot(plot_options, &axis, &detrend.apply(&offsets), name));
+        }
+        report_plot_error(name, "offset histogram", create_histogram(plot_options, &offsets, name, "Offset", args.bins, args.fit_normal));
+        report_plot_error(name, "offset CDF", create_cdf_plot(plot_options, &stats::abs_values(&offsets), name, "Abs Offset"));
+        if let Some(rate) = &summary.offset_rate {
+            let interval = summary.sample_interval.filter(|_| rate.per_second).unwrap_or(1.0);
+            let rates: Vec<f64> = stats::first_difference(&offsets).iter().map(|step| step / interval).collect();
+            report_plot_error(name, "offset rate plot", create_offset_rate_plot(plot_options, &axis, &rates, rate.per(), name));
+        }
+        report_plot_error(name, "rolling std dev plot", create_rolling_stddev_plot(plot_options, &axis, &offsets, args.window_size, name));
+        report_plot_error(name, "Allan deviation plot", create_adev_plot(plot_options, &adev, name));
+        report_plot_error(name, "MTIE plot", create_mtie_plot(plot_options, &mtie, name));
+        let spectrum = spectrum::amplitude_spectrum(uniform, interval);
+        report_plot_error(name, "spectrum plot", create_spectrum_plot(plot_options, &spectrum, name));
+        report_plot_error(name, "autocorrelation plot", create_autocorrelation_plot(plot_options, &autocorrelation, interval, name));
+        if let Some(delays) = &delays {
+            report_plot_error(name, "delay plot", create_delay_plot(plot_options, &axis, delays, name));
+            report_plot_error(name, "combined plot", create_combined_plot(plot_options, &axis, &offsets, delays, name));
+        }
+        if summary.delay.is_some() {
+            report_plot_error(name, "scatter plot", create_scatter_plot(plot_options, records, name));
+        }
+        if summary.freq.is_some() {
+            report_plot_error(name, "freq plot", create_freq_plot(plot_options, &axis, &freqs, name));
+        }
+        if plot_options.format == ImageFormat::Html {
+            let freqs = summary.freq.is_some().then_some(&freqs[..]);
+            report_plot_error(name, "HTML plots", create_html_plot(plot_options, &axis, &offsets, delays.as_deref(), freqs, name));
+        }
+        offset_series.push((name.to_string(), offsets));
+    }
+
+    let delay_spikes = args.delay_spikes.map_or_else(Vec::new, |n| Peak::largest_n(records, |r| r.delay, n));
+    let details = DeviceDetails { summary: &summary, records, gaps: &gaps, adev: &adev, mtie: &mtie, autocorrelation: &autocorrelation, interval, delay_spikes: &delay_spikes };
+    emit_text(args, name, |out, colored| details.write(out, colored, thresholds, args));
+    if let Some(chart) = ascii_chart {
+        println!();
+        for line in chart {
+            println!("\t{}", line);
+        }
+    }
+
+    Some(summary)
+}
+
+/// Longest lag of the offset autocorrelation, in samples; a quarter of the
+/// series if that is shorter.
+const AUTOCORRELATION_MAX_LAG: usize = 600;
+
+/// Default seconds between `--follow` updates.
+const FOLLOW_INTERVAL: f64 = 10.0;
+
+/// Lines of each log read by `--check`.
+const CHECK_LINES: usize = 1000;
+
+/// `--check`: reports for each log whether it can be read and its first
+/// lines hold samples. Returns whether every log passed.
+fn check_logs(args: &Args, parser: &Parser) -> bool {
+    let name_width = args.machines.iter().map(|m| m.name.len()).max().unwrap_or(0);
+    let mut all_ok = true;
+    for machine in &args.machines {
+        let (name, path) = (&machine.name, machine.display_paths());
+        let mut example = None;
+        let (ok, detail) = match parser.check_log(&machine.paths, CHECK_LINES) {
+            Err(err) => (false, format!("cannot read {}: {}", path, err)),
+            Ok(log) if log.total_lines == 0 => (false, format!("{} is empty", path)),
+            Ok(log) if log.total_samples == 0 => {
+                example = log.unmatched_examples.into_iter().next();
+                (false, format!("no samples in the first {} lines of {}", log.total_lines, path))
+            }
+            Ok(ParsedLog { first_unparsed: Some((number, line)), .. }) if args.strict => {
+                example = Some(line);
+                (false, format!("line {} of {} does not match the log format", number, path))
+            }
+            Ok(log) => (true, format!("{} of the first {} lines are samples in {}", log.total_samples, log.total_lines, path)),
+        };
+
+        let status = if ok { color::paint("ok  ", Some(Level::Good)) } else { color::paint("FAIL", Some(Level::Bad)) };
+        println!("{:<name_width$}  {}  {}", name, status, detail);
+        if let Some(line) = example {
+            println!("{:<name_width$}        first unmatched line: {}", "", line);
+        }
+        all_ok &= ok;
+    }
+    all_ok
+}
+
+/// `--jsonl` and `--influx`: writes every sample of every log to `out`,
+/// one log after another, as it is read, formatted by `line`; samples it
+/// returns `None` for are left out. Returns whether all logs could be read.
+fn write_records(args: &Args, parser: &Parser, out: &mut dyn Write, line: fn(&Record, &str) -> Option<String>) -> bool {
+    let progress = Progress::hidden();
+    let mut all_read = true;
+    for machine in &args.machines {
+        let result = parser.scan_log(&machine.paths, &progress, |record| {
+            let Some(line) = line(&record, &machine.name) else {
+                return;
+            };
+            if let Err(err) = writeln!(out, "{}", line) {
+                // The consumer went away (e.g. `| head`); nothing left to do.
+                if err.kind() == io::ErrorKind::BrokenPipe {
+                    process::exit(0);
+                }
+                eprintln!("Error: cannot write samples: {}", err);
+                process::exit(1);
+            }
+        });
+        if let Err(err) = result {
+            eprintln!("[{}] Error: {}", machine.name, PtpError::parse(machine.display_paths(), err));
+            all_read = false;
+        }
+    }
+    if let Err(err) = out.flush() {
+        eprintln!("Error: cannot write samples: {}", err);
+        return false;
+    }
+    all_read
+}
+
+/// Opens the `--influx` destination, which is stdout for `-`.
+fn open_influx(path: &Path) -> io::Result<Box<dyn Write>> {
+    if path.as_os_str() == STDOUT_PATH {
+        return Ok(Box::new(io::stdout().lock()));
+    }
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
+
+/// `--follow`: reads whatever the log gained every `--interval` seconds and
+/// redraws its stats in place. Runs until interrupted or the log can no
+/// longer be read.
+fn follow_log(args: &Args, parser: &Parser) -> Result<(), PtpError> {
+    let machine = &args.machines[0];
+    let (name, path) = (&machine.name, &machine.paths[0]);
+    let thresholds = args.thresholds_for(machine);
+    let interval = args.interval.unwrap_or(FOLLOW_INTERVAL);
+    let clear = io::stdout().is_terminal();
+    let mut follower = Follower::open(path).map_err(|err| PtpError::parse(path.display().to_string(), err))?;
+    let mut records: VecDeque<Record> = VecDeque::new();
+
+    loop {
+        let scanned = follower.read_new().and_then(|text| {
+            parser.scan(text.as_bytes(), |record| {
+                if args.tail.is_some_and(|limit| records.len() == limit) {
+                    records.pop_front();
+                }
+                records.push_back(record);
+            })
+        });
+        scanned.map_err(|err| PtpError::parse(path.display().to_string(), err))?;
+
+        if clear {
+            print!("\x1b[2J\x1b[H");
+        }
+        println!("{} (updated {}; every {} s, Ctrl-C to stop)", name, Local::now().format("%H:%M:%S"), interval);
+        let groups = split_by_source(name, records.iter().cloned().collect());
+        let multiple_sources = groups.len() > 1;
+        let mut stdout = io::stdout().lock();
+        for (index, (device, mut group)) in groups.into_iter().enumerate() {
+            if multiple_sources {
+                print_source_heading(&device, index);
+            }
+            if args.sort_by_time {
+                timing::sort_by_time(&mut group);
+            }
+            if group.len() < args.min_samples {
+                write_insufficient_data(&mut stdout, group.len(), args.min_samples).map_err(stdout_error)?;
+                continue;
+            }
+            let summary = DeviceSummary { device, ..ptp_stats::summarize(&group) };
+            write_device_stats(&mut stdout, color::enabled(), &summary, &thresholds, args)
+                .map_err(stdout_error)?;
+            for violation in thresholds.violations(&summary) {
+                println!("FAIL {}: {}", summary.device, violation);
+            }
+        }
+        stdout.flush().map_err(stdout_error)?;
+        drop(stdout);
+
+        thread::sleep(Duration::from_secs_f64(interval));
+    }
+}
+
+/// `--streaming` counterpart of the main reporting loop: text and summaries
+/// only, from per-source running statistics. Devices are named as in
+/// `split_by_source`.
+fn stream_all(
+    args: &Args,
+    parser: &Parser,
+    summaries: &mut Vec<DeviceSummary>,
+    limits: &mut Vec<Thresholds>,
+    trends: &mut Vec<String>,
+    failed_files: &mut Vec<String>,
+    totals: &mut RunTotals,
+) {
+    let progress = Progress::new(&args.machines, !args.quiet && args.verbosity == 0);
+    let parsed = parse_all(&args.machines, &progress, |paths| parser.stream_log(paths, &progress, args.reservoir));
+    let read_in_full = !interrupt::requested();
+
+    for (machine, status) in args.machines.iter().zip(parsed) {
+        let Some(status) = totals.unless_interrupted(machine, status, read_in_full) else {
+            continue;
+        };
+        let name = &machine.name;
+        let thresholds = args.thresholds_for(machine);
+        if args.detailed() {
+            println!("{}", name);
+        }
+
+        let log = match status {
+            Ok(log) => log,
+            Err(err) => {
+                eprintln!("[{}] Error: {}", name, PtpError::parse(machine.display_paths(), err));
+                failed_files.push(machine.display_paths());
+                continue;
+            }
+        };
+
+        let kept: usize = log.records.iter().map(|(_, stats)| stats.samples()).sum();
+        totals.add(&log, kept);
+        debug_counts(name, &log, kept);
+        if report_unparsed(args, machine, &log) {
+            totals.nonconforming_logs += 1;
+        }
+        if args.detailed() {
+            print_samples_kept(args, kept, log.total_samples);
+        }
+        if log.records.is_empty() && args.detailed() {
+            print_no_data(&log);
+        }
+
+        let multiple_sources = log.records.len() > 1;
+        for (index, (source, stats)) in log.records.iter().enumerate() {
+            let device = if multiple_sources { format!("{}-{}", name, source) } else { name.clone() };
+            if multiple_sources && args.detailed() {
+                print_source_heading(&device, index);
+            }
+            if stats.samples() < args.min_samples {
+                emit_text(args, &device, |out, _| write_insufficient_data(out, stats.samples(), args.min_samples));
+                continue;
+            }
+            let summary = DeviceSummary { grandmaster: grandmaster(machine, &log), ..stats.summary(&device) };
+            emit_text(args, &device, |out, colored| {
+                write_device_stats(out, colored, &summary, &thresholds, args)
+            });
+            if let Some(offsets) = stats.sampled_offsets().filter(|_| !args.no_plots) {
+                report_plot_error(&device, "offset histogram", create_histogram(&args.plot_options, &offsets, &device, "Offset", args.bins, args.fit_normal));
+            }
+            summaries.push(summary);
+            limits.push(thresholds.clone());
+            // Streaming keeps no samples to draw.
+            trends.push(String::new());
+        }
+
+        if args.detailed() {
+            print_events(&log);
+            print_line_counts(&log);
+        }
+    }
+}
+
+fn main() -> Result<(), PtpError> {
+    error::install_panic_handler();
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let mut args = match Args::parse(arguments.iter().cloned()) {
+        Ok(args) => args,
+        Err(CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    logging::init(args.verbosity);
+    if args.demo {
+        let dir = demo::dir();
+        demo::write(&dir).map_err(|err| PtpError::io(format!("cannot write the demo logs to {}", dir.display()), err))?;
+    }
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    args.plot_options.file_template = args.plot_options.file_template.replace("{date}", &today);
+    let parser = Parser::new(args.pattern.as_ref(), args.window.clone())
+        .with_tail(args.tail)
+        .with_input_unit(args.input_unit)
+        .with_input_format(args.input_format)
+        .with_log_format(args.log_format)
+        .with_exclude(args.exclude.clone())
+        .with_read_timeout(args.read_timeout.map(Duration::from_secs_f64));
+    if args.check {
+        process::exit(if check_logs(&args, &parser) { 0 } else { 1 });
+    }
+    if args.jsonl {
+        let ok = write_records(&args, &parser, &mut io::stdout().lock(), |record, device| Some(record.to_json(device)));
+        process::exit(if ok { 0 } else { 1 });
+    }
+    if let Some(path) = &args.influx {
+        let mut out = open_influx(path).map_err(|err| PtpError::io(format!("cannot create {}", path.display()), err))?;
+        process::exit(if write_records(&args, &parser, &mut out, Record::to_influx) { 0 } else { 1 });
+    }
+    if args.follow {
+        // --tail applies to the whole followed log, not to each batch of lines.
+        let parser = Parser::new(args.pattern.as_ref(), args.window.clone())
+            .with_input_unit(args.input_unit)
+            .with_input_format(args.input_format)
+            .with_log_format(args.log_format)
+            .with_exclude(args.exclude.clone());
+        return follow_log(&args, &parser);
+    }
+
+    let baselines = match &args.baseline {
+        Some(path) => baseline::load(path).map_err(|err| PtpError::Config(format!("cannot read baseline {}: {}", path.display(), err)))?,
+        None => Vec::new(),
+    };
+
+    let out_dir = &args.plot_options.out_dir;
+    if !args.no_plots
+        && args.plot_options.format != ImageFormat::Ascii
+        && let Err(err) = fs::create_dir_all(out_dir)
+    {
+        return Err(PtpError::io(format!("cannot create output directory {}", out_dir.display()), err));
+    }
+
+    let mut offset_series = Vec::new();
+    // Timestamped offsets per device, kept only for --correlation.
+    let mut timed_series = Vec::new();
+    // The records of the two --diff devices.
+    let mut diff_series: Vec<(String, Vec<Record>)> = Vec::new();
+    let mut summaries = Vec::new();
+    // The thresholds each summary is checked against, index for index.
+    let mut limits = Vec::new();
+    // A sparkline of each summary's offsets, likewise.
+    let mut trends = Vec::new();
+    let mut failed_files = Vec::new();
+    let mut totals = RunTotals::default();
+    // Only now: --follow and the record exports run until Ctrl-C kills them.
+    interrupt::install();
+
+    if args.streaming {
+        stream_all(&args, &parser, &mut summaries, &mut limits, &mut trends, &mut failed_files, &mut totals);
+    } else {
+        let progress = Progress::new(&args.machines, !args.quiet && args.verbosity == 0);
+        let parsed = parse_all(&args.machines, &progress, |paths| parser.parse_log(paths, &progress));
+        let read_in_full = !interrupt::requested();
+
+        for (machine, status) in args.machines.iter().zip(parsed) {
+            let Some(status) = totals.unless_interrupted(machine, status, read_in_full) else {
+                continue;
+            };
+            let name = &machine.name;
+            let thresholds = args.thresholds_for(machine);
+            if args.detailed() {
+                println!("{}", name);
+            }
+
+            match status {
+                Ok(mut log) => {
+                    totals.add(&log, log.records.len());
+                    debug_counts(name, &log, log.records.len());
+                    if report_unparsed(&args, machine, &log) {
+                        totals.nonconforming_logs += 1;
+                    }
+                    if args.detailed() {
+                        print_samples_kept(&args, log.records.len(), log.total_samples);
+                    }
+                    if log.records.is_empty() && args.detailed() {
+                        print_no_data(&log);
+                    }
+
+                    // Daemons sharing a log often share a syslog second, so
+                    // only each one's own samples have an order to check.
+                    let groups = split_by_source(name, std::mem::take(&mut log.records));
+                    let multiple_sources = groups.len() > 1;
+
+                    for (index, (device, mut records)) in groups.into_iter().enumerate() {
+                        if multiple_sources && args.detailed() {
+                            print_source_heading(&device, index);
+                        }
+                        let order = OutOfOrder::count(&records);
+                        if args.sort_by_time {
+                            timing::sort_by_time(&mut records);
+                        }
+                        if args.detailed() {
+                            print_out_of_order(order, args.sort_by_time);
+                        }
+                        if let Some(warmup) = args.warmup {
+                            let skipped = warmup.count(&records);
+                            records.drain(..skipped);
+                            if args.detailed() {
+                                println!("\tWarmup skipped: {}", plural(skipped, "sample"));
+                            }
+                        }
+                        if let Some(summary) = report_device(&device, &records, &args, &thresholds, grandmaster(machine, &log), &mut offset_series) {
+                            if report_negative_delays(&args, &summary) {
+                                totals.nonconforming_logs += 1;
+                            }
+                            summaries.push(summary);
+                            limits.push(thresholds.clone());
+                            let offsets: Vec<f64> = records.iter().map(|r| r.offset).collect();
+                            trends.push(sparkline(&offsets, SPARKLINE_WIDTH));
+                            if args.correlation {
+                                timed_series.push((device.clone(), correlation::timed_offsets(&records)));
+                            }
+                            if args.diff.as_ref().is_some_and(|(a, b)| device == *a || device == *b) {
+                                diff_series.push((device.clone(), records.clone()));
+                            }
+                        }
+
+                        if let Some(csv_dir) = &args.csv
+                            && let Err(err) = export::write_csv(csv_dir, &device, &records)
+                        {
+                            eprintln!("[{}] Error writing CSV: {:?}", device, err);
+                        }
+                    }
+
+                    if args.detailed() {
+                        print_events(&log);
+                        print_line_counts(&log);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("[{}] Error: {}", name, PtpError::parse(machine.display_paths(), err));
+                    failed_files.push(machine.display_paths());
+                }
+            }
+        }
+    }
+
+    // Comparing only the devices reported before Ctrl-C would replace
+    // complete plots from an earlier run.
+    let plot_groups = if totals.interrupted() { BTreeMap::new() } else { by_grandmaster(&offset_series, &summaries) };
+    for (grandmaster, series) in &plot_groups {
+        if series.len() < 2 {
+            continue;
+        }
+        // One plot per grandmaster: clocks on different ones have no common reference.
+        let plot_type = match (plot_groups.len(), grandmaster) {
+            (1, _) => "Offset".to_string(),
+            (_, Some(grandmaster)) => format!("Offset-{}", grandmaster),
+            (_, None) => "Offset-unknown-grandmaster".to_string(),
+        };
+        let series: Vec<(String, Vec<f64>)> = series.iter().map(|(name, offsets)| (name.to_string(), offsets.to_vec())).collect();
+        report_plot_error("comparison", "offset plot", create_comparison_plot(&args.plot_options, &series, &plot_type));
+    }
+    if let Some(columns) = args.contact_sheet
+        && !offset_series.is_empty()
+        && !totals.interrupted()
+    {
+        report_plot_error("contact sheet", "offset plots", create_contact_sheet(&args.plot_options, &offset_series, columns));
+    }
+    if args.box_plot && !totals.interrupted() {
+        report_plot_error("comparison", "box plot", create_box_plot(&args.plot_options, &offset_series));
+    }
+    if let Some((a, b)) = &args.diff
+        && !args.no_plots
+        && !totals.interrupted()
+    {
+        let find = |name: &String| diff_series.iter().find(|(device, _)| device == name).map(|(_, records)| records);
+        match (find(a), find(b)) {
+            (Some(a_records), Some(b_records)) => {
+                report_plot_error("diff", "offset difference plot", create_diff_plot(&args.plot_options, a_records, b_records, (a, b)));
+            }
+            _ => {
+                let missing: Vec<&str> = [a, b].into_iter().filter(|name| find(name).is_none()).map(String::as_str).collect();
+                eprintln!("Error: --diff: no device named {} was reported", missing.join(" or "));
+            }
+        }
+    }
+
+    let mut verdicts = Vec::new();
+    for (summary, thresholds) in summaries.iter().zip(&limits) {
+        let breaches = thresholds.breaches(summary);
+        for breach in &breaches {
+            eprintln!("FAIL {}: {}", summary.device, breach.message);
+        }
+        verdicts.push((summary.device.clone(), breaches));
+    }
+    let failed_devices = verdicts.iter().filter(|(_, breaches)| !breaches.is_empty()).count();
+
+    if let Some(path) = &args.report
+        && let Err(err) = report::write_markdown(path, &summaries, &args.plot_options, args.precision, !args.streaming && !args.no_plots && args.plot_options.format != ImageFormat::Ascii)
+    {
+        eprintln!("Error writing report {}: {}", path.display(), err);
+    }
+
+    if let Some(path) = &args.prom
+        && let Err(err) = export::write_prometheus(&summaries, path)
+    {
+        eprintln!("Error writing metrics {}: {}", path.display(), err);
+    }
+
+    let status = RunStatus {
+        devices: verdicts,
+        unreadable_logs: failed_files.clone(),
+        nonconforming_logs: totals.nonconforming_logs,
+        unfinished_logs: totals.interrupted_logs.iter().chain(&totals.unreported_logs).cloned().collect(),
+    };
+    if let Some(path) = &args.status_file
+        && let Err(err) = status.write(path)
+    {
+        eprintln!("Error writing status {}: {}", path.display(), err);
+    }
+
+    let generated_at = Local::now().fixed_offset();
+    if let Some(dir) = &args.bundle
+        && let Err(err) = bundle::write(dir, &to_json_document(&summaries, &arguments, generated_at), &summaries, &args.plot_options, args.precision)
+    {
+        eprintln!("Error writing bundle {}: {}", dir.display(), err);
+    }
+
+    if args.json {
+        println!("{}", to_json_document(&summaries, &arguments, generated_at));
+    } else if args.quiet {
+        print_summary_table(&summaries, &limits, &trends, args.plot_options.unit, args.precision);
+    }
+
+    let fleet = FleetSummary::from_summaries(&summaries).filter(|fleet| fleet.devices > 1 && args.detailed());
+    if let Some(fleet) = &fleet {
+        print_fleet_summary(fleet, args.plot_options.unit, args.precision);
+    }
+
+    if let Some(n) = args.top
+        && !args.json
+    {
+        print_ranking(&args, &summaries, n);
+    }
+
+    if args.baseline.is_some() && !args.json {
+        print_baseline_comparison(&summaries, &baselines, args.plot_options.unit, args.precision);
+    }
+
+    if args.correlation && !args.json {
+        print_correlation(&timed_series, &summaries);
+    }
+
+    if args.detailed() {
+        // Device blocks end with a blank line; the closing sections above don't.
+        if fleet.is_some() || args.top.is_some() || args.baseline.is_some() || args.correlation {
+            println!();
+        }
+        println!(
+            "Processed {}, {}, {}, {} over threshold.",
+            plural(summaries.len(), "device"),
+            plural(totals.samples, "sample"),
+            plural(totals.skipped_lines, "skipped line"),
+            plural(failed_devices, "device")
+        );
+    }
+
+    if !failed_files.is_empty() {
+        eprintln!(
+            "Error: {} of {} log files could not be read: {}",
+            failed_files.len(),
+            args.machines.len(),
+            failed_files.join(", ")
+        );
+    }
+
+    if totals.interrupted() {
+        print_interrupted(&totals);
+        process::exit(130);
+    }
+    if !status.success() {
+        process::exit(1);
+    }
+    Ok(())
 }
@@ -0,0 +1,33 @@
+use crate::stats;
+
+/// Modified z-score above which a sample counts as an outlier
+/// (Iglewicz & Hoaglin's recommended cutoff).
+const MAX_MODIFIED_Z: f64 = 3.5;
+
+/// Scales the MAD to the standard deviation for normally distributed data.
+const MAD_TO_SIGMA: f64 = 1.4826;
+
+/// Removes outliers using the median absolute deviation rule and returns
+/// the remaining samples (in their original order) with the number removed.
+/// A series with zero MAD has no meaningful spread, so nothing is removed.
+pub fn filter(data: &[f64]) -> (Vec<f64>, usize) {
+    if data.is_empty() {
+        return (Vec::new(), 0);
+    }
+
+    let center = stats::median(data);
+    let deviations: Vec<f64> = data.iter().map(|x| (x - center).abs()).collect();
+    let mad = stats::median(&deviations);
+    if mad == 0.0 {
+        return (data.to_vec(), 0);
+    }
+
+    let kept: Vec<f64> = data
+        .iter()
+        .copied()
+        .filter(|x| (x - center).abs() / (MAD_TO_SIGMA * mad) <= MAX_MODIFIED_Z)
+        .collect();
+    let removed = data.len() - kept.len();
+
+    (kept, removed)
+}
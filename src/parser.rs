@@ -0,0 +1,1001 @@
+//! Turning log lines into records.
+
+use chrono::{DateTime, FixedOffset};
+use crate::cli::Machine;
+use crate::event::{Event, EventKind};
+use crate::interrupt;
+use crate::json::{self, Value};
+use crate::progress::{Progress, REDRAW_INTERVAL};
+use crate::record::{Record, Source};
+use crate::streaming::DeviceStats;
+use crate::unit::Unit;
+use flate2::read::GzDecoder;
+use log::{debug, trace};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Inclusive time range of samples to keep. Either end may be open.
+#[derive(Debug, Clone, Default)]
+pub struct TimeWindow {
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>,
+}
+
+impl TimeWindow {
+    pub fn is_set(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+
+    /// Whether a sample falls inside the window. Samples without a
+    /// timestamp can't be placed, so they only pass an unset window.
+    pub fn contains(&self, timestamp: Option<&DateTime<FixedOffset>>) -> bool {
+        match timestamp {
+            Some(timestamp) => {
+                self.from.is_none_or(|from| *timestamp >= from) && self.to.is_none_or(|to| *timestamp <= to)
+            }
+            None => !self.is_set(),
+        }
+    }
+}
+
+/// How log lines are read, for `--input-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputFormat {
+    /// Lines that are JSON objects as [`InputFormat::Json`], the rest as
+    /// [`InputFormat::Text`], so plain logs need no flag.
+    #[default]
+    Auto,
+    /// Syslog or foreground text, matched by the line patterns.
+    Text,
+    /// One JSON object per line with an `offset` and `delay` in the log's
+    /// unit, and optionally `timestamp` (RFC 3339), `internal_ts`,
+    /// `freq`, `max_offset`, `servo_state` (0 to 2, or `state` as e.g.
+    /// `"s2"`) and `source` (as written by `--jsonl`, which these logs
+    /// round-trip). Other lines are not samples.
+    Json,
+}
+
+impl std::str::FromStr for InputFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<InputFormat, ()> {
+        match value {
+            "auto" => Ok(InputFormat::Auto),
+            "text" => Ok(InputFormat::Text),
+            "json" => Ok(InputFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One log's samples (`records`, or per-source running statistics in
+/// `--streaming` mode) and what happened to the lines that weren't samples.
+pub struct ParsedLog<T = Vec<Record>> {
+    pub records: T,
+    /// Matched samples before the `--from`/`--to` window was applied.
+    pub total_samples: usize,
+    pub skipped_lines: usize,
+    pub parse_failures: usize,
+    /// Lines skipped for not being valid UTF-8, e.g. binary garbage from a
+    /// corrupted log.
+    pub encoding_errors: usize,
+    /// Lines dropped by `--exclude` before any pattern was tried.
+    pub excluded_lines: usize,
+    /// Every line read, matched or not.
+    pub total_lines: usize,
+    /// The first few non-blank lines that matched no pattern, to show the
+    /// user what their log actually looks like.
+    pub unmatched_examples: Vec<String>,
+    /// The first non-blank line that is neither a sample nor an event,
+    /// with its 1-based line number, for `--strict`. Undecodable bytes are
+    /// shown as U+FFFD.
+    pub first_unparsed: Option<(usize, String)>,
+    /// In-window ptp4l event lines, in log order. Only the builtin formats
+    /// are searched for events.
+    pub events: Vec<Event>,
+}
+
+impl<T> ParsedLog<T> {
+    pub fn with_records<U>(self, records: U) -> ParsedLog<U> {
+        ParsedLog {
+            records,
+            total_samples: self.total_samples,
+            skipped_lines: self.skipped_lines,
+            parse_failures: self.parse_failures,
+            encoding_errors: self.encoding_errors,
+            excluded_lines: self.excluded_lines,
+            total_lines: self.total_lines,
+            unmatched_examples: self.unmatched_examples,
+            first_unparsed: self.first_unparsed,
+            events: self.events,
+        }
+    }
+}
+
+/// How far into each part of a rotated log to look for its first timestamp.
+const PEEK_LINES: usize = 1000;
+
+/// How many non-matching lines to keep for the "no data" diagnostic.
+const UNMATCHED_EXAMPLES: usize = 3;
+
+/// The `freq` and `delay` fields after the offset on ptp4l and phc2sys
+/// lines, each found on its own so that their order doesn't matter: some
+/// configurations and wrappers print `path delay` before `freq`.
+fn keyed_fields() -> &'static [(&'static str, Regex)] {
+    static FIELDS: OnceLock<Vec<(&'static str, Regex)>> = OnceLock::new();
+    FIELDS.get_or_init(|| {
+        vec![
+            ("freq", Regex::new(&r"(?:^|\s)freq\s+(NUMBER)(?:\s|$)".replace("NUMBER", NUMBER)).unwrap()),
+            ("delay", Regex::new(&r"(?:^|\s)(?:path\s+)?delay\s+(NUMBER)(?:\s|$)".replace("NUMBER", NUMBER)).unwrap()),
+        ]
+    })
+}
+
+/// The text of field `name`: its capture group, or for a builtin pattern
+/// the keyed field of that name in the `fields` group.
+fn capture<'a>(cap: &regex::Captures<'a>, name: &str) -> Option<&'a str> {
+    if let Some(m) = cap.name(name) {
+        return Some(m.as_str());
+    }
+    let fields = cap.name("fields")?.as_str();
+    let (_, re) = keyed_fields().iter().find(|(key, _)| *key == name)?;
+    Some(re.captures(fields)?.get(1)?.as_str())
+}
+
+/// Whether a builtin line carries every keyed field; one without them is
+/// not a sample line, just as when the fields were matched in place.
+fn has_keyed_fields(cap: &regex::Captures) -> bool {
+    cap.name("fields").is_none_or(|fields| keyed_fields().iter().all(|(_, re)| re.is_match(fields.as_str())))
+}
+
+/// Parses an optional capture group. The outer `None` means the group
+/// matched but failed to parse; `Some(None)` means it is absent.
+fn optional_capture<T: std::str::FromStr>(cap: &regex::Captures, name: &str) -> Option<Option<T>> {
+    match capture(cap, name) {
+        Some(text) => text.parse().ok().map(Some),
+        None => Some(None),
+    }
+}
+
+/// Builds a record from a matched line, or `None` if any captured field
+/// fails to parse (e.g. an impossible date in a line cut off mid-write).
+fn record_from_captures(source: Source, cap: &regex::Captures) -> Option<Record> {
+    let timestamp = match cap.name("timestamp") {
+        Some(m) => Some(DateTime::parse_from_rfc3339(m.as_str()).ok()?),
+        None => None,
+    };
+
+    Some(Record {
+        source,
+        timestamp,
+        internal_ts: optional_capture(cap, "internal_ts")?,
+        offset: cap["offset"].parse().ok()?,
+        max_offset: optional_capture(cap, "max_offset")?,
+        servo_state: optional_capture(cap, "state")?,
+        freq: optional_capture(cap, "freq")?,
+        delay: match capture(cap, "delay") {
+            Some(text) => Some(text.parse().ok()?),
+            None if source == Source::Ts2phc => None,
+            None => return None,
+        },
+    })
+}
+
+/// Builds a record from a JSON log line. `None` when the line is not a
+/// JSON object with an `offset`, e.g. a logged event; `Some(None)` when a
+/// field is missing or of the wrong type.
+fn record_from_json(line: &str) -> Option<Option<Record>> {
+    let value = json::parse(line).ok()?;
+    let offset = value.get("offset")?;
+    Some(json_record(&value, offset))
+}
+
+fn json_record(value: &Value, offset: &Value) -> Option<Record> {
+    // Absent and null fields alike are unset; present ones must parse.
+    let field = |key: &str| value.get(key).filter(|field| **field != Value::Null);
+    let number = |key: &str| field(key).map_or(Some(None), |field| field.as_f64().map(Some));
+
+    let source = match field("source").map(Value::as_str) {
+        None => Source::Ptp4l,
+        Some(Some("ptp4l")) => Source::Ptp4l,
+        Some(Some("ptp4l-summary")) => Source::Ptp4lSummary,
+        Some(Some("phc2sys")) => Source::Phc2sys,
+        Some(Some("ts2phc")) => Source::Ts2phc,
+        Some(Some("custom")) => Source::Custom,
+        Some(_) => return None,
+    };
+    let timestamp = match field("timestamp") {
+        Some(field) => Some(DateTime::parse_from_rfc3339(field.as_str()?).ok()?),
+        None => None,
+    };
+    let servo_state = match (field("servo_state"), field("state")) {
+        (Some(state), _) => Some(state.as_f64().filter(|state| [0.0, 1.0, 2.0].contains(state))? as u8),
+        (None, Some(state)) => Some(state.as_str()?.strip_prefix('s')?.parse().ok().filter(|state| *state <= 2)?),
+        (None, None) => None,
+    };
+
+    Some(Record {
+        source,
+        timestamp,
+        internal_ts: number("internal_ts")?,
+        offset: offset.as_f64()?,
+        max_offset: number("max_offset")?,
+        servo_state,
+        freq: number("freq")?,
+        delay: match number("delay")? {
+            Some(delay) => Some(delay),
+            None if source == Source::Ts2phc => None,
+            None => return None,
+        },
+    })
+}
+
+/// Path argument that reads the log from standard input.
+pub const STDIN_PATH: &str = "-";
+
+/// A signed integer, decimal or scientific-notation number such as `-42`,
+/// `1.5` or `1.2345e+03`; some ptp4l builds print offsets as floats.
+/// Substituted for `NUMBER` in the builtin patterns.
+const NUMBER: &str = r"[+-]?(?:\d+(?:\.\d*)?|\.\d+)(?:[eE][+-]?\d+)?";
+
+/// An RFC 3339 timestamp to the second, in UTC (`Z`) or with a `+hh:mm` or
+/// `-hh:mm` offset. Substituted for `TIMESTAMP` in the builtin patterns.
+const TIMESTAMP: &str = r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:Z|[+-]\d{2}:\d{2})";
+
+/// The builtin patterns accept lines with a syslog prefix (timestamp,
+/// host and `ptp4l[pid]:`) or without one, as printed by a daemon run in
+/// the foreground. Sample lines fix the order up to the servo state and
+/// leave the rest to [`keyed_fields`].
+fn builtin_patterns() -> Vec<(Source, Regex)> {
+    let ptp4l_re = ptp4l_pattern(None);
+
+    // With summary_interval set, ptp4l logs one line per interval with the
+    // RMS and max offset and the mean +/- std dev of freq and path delay.
+    let ptp4l_summary_re = Regex::new(
+            &r#"(?x)
+            ^(?:(?P<timestamp>TIMESTAMP)\s+
+            \w+\s+
+            ptp4l
+
+        \[\d+\]
+
+        :\s+)?
+            ptp4l
+
+        \[(?P<internal_ts>\d+\.\d+)\]
+
+        :\s+
+            rms\s+(?P<offset>NUMBER)\s+max\s+(?P<max_offset>NUMBER)\s+
+            freq\s+(?P<freq>NUMBER)\s+\+/-\s+NUMBER\s+
+            delay\s+(?P<delay>NUMBER)\s+\+/-\s+NUMBER
+            "#.replace("TIMESTAMP", TIMESTAMP).replace("NUMBER", NUMBER)
+        ).unwrap();
+
+    // phc2sys names the clock pair being synchronized ("CLOCK_REALTIME phc",
+    // "eth0 sys", ...) and reports a plain "delay" rather than "path delay".
+    let phc2sys_re = Regex::new(
+            &r#"(?x)
+            ^(?:(?P<timestamp>TIMESTAMP)\s+
+            \w+\s+
+            phc2sys
+
+        \[\d+\]
+
+        :\s+)?
+            phc2sys
+
+        \[(?P<internal_ts>\d+\.\d+)\]
+
+        :\s+
+            (?:\S+\s+)?(?:phc|sys)\s+offset\s+(?P<offset>NUMBER)\s+s(?P<state>\d)\s+
+            (?P<fields>.+)
+            "#.replace("TIMESTAMP", TIMESTAMP).replace("NUMBER", NUMBER)
+        ).unwrap();
+
+    // ts2phc disciplines a PHC from a PPS source, names the clock ("eth0",
+    // "/dev/ptp0") and, in older releases, says "master offset". A PPS edge
+    // has no path delay to report.
+    let ts2phc_re = Regex::new(
+            &r#"(?x)
+            ^(?:(?P<timestamp>TIMESTAMP)\s+
+            \w+\s+
+            ts2phc\[\d+\]:\s+)?
+            ts2phc\[(?P<internal_ts>\d+\.\d+)\]:\s+
+            (?:\S+\s+)?(?:master\s+)?offset\s+(?P<offset>NUMBER)\s+s(?P<state>\d)\s+
+            freq\s+(?P<freq>NUMBER)
+            "#.replace("TIMESTAMP", TIMESTAMP).replace("NUMBER", NUMBER)
+        ).unwrap();
+
+    vec![
+        (Source::Ptp4l, ptp4l_re),
+        (Source::Ptp4lSummary, ptp4l_summary_re),
+        (Source::Phc2sys, phc2sys_re),
+        (Source::Ts2phc, ts2phc_re),
+    ]
+}
+
+/// ptp4l sample lines with a syslog prefix if `syslog` is `Some(true)`,
+/// without one if `Some(false)`, or either way.
+fn ptp4l_pattern(syslog: Option<bool>) -> Regex {
+    let prefix = r"(?P<timestamp>TIMESTAMP)\s+\w+\s+ptp4l\[\d+\]:\s+";
+    let prefix = match syslog {
+        Some(true) => prefix.to_string(),
+        Some(false) => String::new(),
+        None => format!("(?:{})?", prefix),
+    };
+    Regex::new(
+        &format!(r"^{}ptp4l\[(?P<internal_ts>\d+\.\d+)\]:\s+master\s+offset\s+(?P<offset>NUMBER)\s+s(?P<state>\d)\s+(?P<fields>.+)", prefix)
+            .replace("TIMESTAMP", TIMESTAMP)
+            .replace("NUMBER", NUMBER),
+    )
+    .unwrap()
+}
+
+/// One kind of line to read instead of every builtin pattern, for
+/// `--log-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// ptp4l samples with a syslog prefix, as in `/var/log/messages`.
+    Ptp4lSyslog,
+    /// ptp4l samples as printed by a daemon run in the foreground.
+    Ptp4lBare,
+    Phc2sys,
+    /// ptp4l's `summary_interval` lines.
+    Summary,
+}
+
+impl LogFormat {
+    /// The patterns of the preset, from the builtin ones.
+    fn patterns(self) -> Vec<(Source, Regex)> {
+        let builtin = |wanted: Source| builtin_patterns().into_iter().filter(move |(source, _)| *source == wanted);
+        match self {
+            LogFormat::Ptp4lSyslog => vec![(Source::Ptp4l, ptp4l_pattern(Some(true)))],
+            LogFormat::Ptp4lBare => vec![(Source::Ptp4l, ptp4l_pattern(Some(false)))],
+            LogFormat::Phc2sys => builtin(Source::Phc2sys).collect(),
+            LogFormat::Summary => builtin(Source::Ptp4lSummary).collect(),
+        }
+    }
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<LogFormat, ()> {
+        match value {
+            "ptp4l-syslog" => Ok(LogFormat::Ptp4lSyslog),
+            "ptp4l-bare" => Ok(LogFormat::Ptp4lBare),
+            "phc2sys" => Ok(LogFormat::Phc2sys),
+            "summary" => Ok(LogFormat::Summary),
+            _ => Err(()),
+        }
+    }
+}
+
+/// ptp4l event lines (see [`EventKind`]), with or without a syslog prefix.
+fn event_pattern() -> Regex {
+    Regex::new(
+            &r#"(?x)
+            ^(?:(?P<timestamp>TIMESTAMP)\s+)?
+            .*?ptp4l.*?
+            (?:
+                selected\s+best\s+master\s+clock\s+(?P<master>\S+)
+                | (?P<grandmaster>assuming\s+the\s+grand\s+master\s+role)
+                | (?P<jump>clock\s+jumped)
+            )
+            "#.replace("TIMESTAMP", TIMESTAMP)
+        ).unwrap()
+}
+
+/// Builds an event from a matched line; `None` for an impossible date.
+fn event_from_captures(cap: &regex::Captures, line: usize) -> Option<Event> {
+    let timestamp = match cap.name("timestamp") {
+        Some(m) => Some(DateTime::parse_from_rfc3339(m.as_str()).ok()?),
+        None => None,
+    };
+    let kind = match cap.name("master") {
+        Some(clock) => EventKind::MasterSelected(clock.as_str().to_string()),
+        None if cap.name("grandmaster").is_some() => EventKind::GrandmasterRole,
+        None => EventKind::ClockJump,
+    };
+    Some(Event { kind, timestamp, line })
+}
+
+/// Line patterns and sample filters shared by every log in a run.
+pub struct Parser {
+    patterns: Vec<(Source, Regex)>,
+    /// `None` with a custom `--pattern`, whose logs may not be ptp4l's.
+    events: Option<Regex>,
+    window: TimeWindow,
+    /// Keep only this many of each log's last in-window samples.
+    tail: Option<usize>,
+    /// Give up on a log not read within this long.
+    read_timeout: Option<Duration>,
+    /// What the log's offsets and delays are written in.
+    input_unit: Unit,
+    /// Lines to drop unread, for `--exclude`.
+    exclude: Option<Regex>,
+    input_format: InputFormat,
+}
+
+impl Parser {
+    /// A parser for `pattern`, or for ptp4l, phc2sys and ts2phc output when
+    /// none is given, keeping only samples inside `window`.
+    pub fn new(pattern: Option<&Regex>, window: TimeWindow) -> Parser {
+        let (patterns, events) = match pattern {
+            Some(pattern) => (vec![(Source::Custom, pattern.clone())], None),
+            None => (builtin_patterns(), Some(event_pattern())),
+        };
+
+        Parser { patterns, events, window, tail: None, read_timeout: None, input_unit: Unit::Ns, exclude: None, input_format: InputFormat::Auto }
+    }
+
+    /// Keeps only the final `tail` in-window samples of each log, for
+    /// `--tail`. The whole log is still read.
+    pub fn with_tail(self, tail: Option<usize>) -> Parser {
+        Parser { tail, ..self }
+    }
+
+    /// Fails reading a log, all its parts together, with a
+    /// [`io::ErrorKind::TimedOut`] error once it has taken longer than
+    /// `timeout`, for `--read-timeout`. Files are then opened and read on
+    /// threads of their own, so even a read stuck on an unresponsive
+    /// network filesystem is given up on; standard input never times out.
+    pub fn with_read_timeout(self, read_timeout: Option<Duration>) -> Parser {
+        Parser { read_timeout, ..self }
+    }
+
+    /// Reads offsets and delays as written in `input_unit`, for logs
+    /// converted before they were written, and scales them to nanoseconds
+    /// like every other log. `--from`/`--to` and the rest are unaffected.
+    pub fn with_input_unit(self, input_unit: Unit) -> Parser {
+        Parser { input_unit, ..self }
+    }
+
+    /// Drops lines matching `exclude` before trying any sample or event
+    /// pattern on them, e.g. debug lines that look like samples. They are
+    /// counted in [`ParsedLog::excluded_lines`] and never treated as
+    /// unparsed, and [`Parser::parse_line`] still reads them.
+    pub fn with_exclude(self, exclude: Option<Regex>) -> Parser {
+        Parser { exclude, ..self }
+    }
+
+    /// Reads only the lines of `log_format`, for `--log-format`, instead of
+    /// those of every builtin pattern. Has no effect with a custom pattern.
+    pub fn with_log_format(self, log_format: Option<LogFormat>) -> Parser {
+        match log_format {
+            Some(format) if self.events.is_some() => Parser { patterns: format.patterns(), ..self },
+            _ => self,
+        }
+    }
+
+    /// Reads lines as `input_format` says, for `--input-format`.
+    pub fn with_input_format(self, input_format: InputFormat) -> Parser {
+        Parser { input_format, ..self }
+    }
+
+    /// The sample on `line`, or `None` when it matches no pattern or a
+    /// field fails to parse. Surrounding whitespace, such as a stray `\r`
+    /// from a Windows copy of the log, is ignored. The `--from`/`--to`
+    /// window is not applied.
+    pub fn parse_line(&self, line: &str) -> Option<Record> {
+        self.match_line(line)?
+    }
+
+    /// `None` when no pattern matches; `Some(None)` when one does but a
+    /// captured field fails to parse.
+    fn match_line(&self, line: &str) -> Option<Option<Record>> {
+        let line = line.trim();
+        match self.input_format {
+            InputFormat::Json => return record_from_json(line).map(|record| record.map(|record| self.in_nanoseconds(record))),
+            InputFormat::Auto if line.starts_with('{') => {
+                if let Some(record) = record_from_json(line) {
+                    return Some(record.map(|record| self.in_nanoseconds(record)));
+                }
+            }
+            _ => {}
+        }
+        self.patterns
+            .iter()
+            .find_map(|(source, re)| {
+                let cap = re.captures(line).filter(has_keyed_fields)?;
+                Some(record_from_captures(*source, &cap).map(|record| self.in_nanoseconds(record)))
+            })
+    }
+
+    fn in_nanoseconds(&self, record: Record) -> Record {
+        let scale = self.input_unit.scale();
+        if scale == 1.0 {
+            return record;
+        }
+        Record { offset: record.offset * scale, max_offset: record.max_offset.map(|max| max * scale), delay: record.delay.map(|delay| delay * scale), ..record }
+    }
+
+    pub fn parse_file(&self, path: &Path, progress: &Progress) -> io::Result<ParsedLog> {
+        self.parse_log(&[path.to_path_buf()], progress)
+    }
+
+    /// Parses a log rotated across `paths` as one series (see
+    /// [`Parser::scan_log`]).
+    pub fn parse_log(&self, paths: &[PathBuf], progress: &Progress) -> io::Result<ParsedLog> {
+        let mut records = Vec::new();
+        let log = self.scan_log(paths, progress, |record| records.push(record))?;
+        Ok(log.with_records(records))
+    }
+
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<ParsedLog> {
+        let mut records = Vec::new();
+        let log = self.scan(reader, |record| records.push(record))?;
+        Ok(log.with_records(records))
+    }
+
+    /// Folds each source's samples into running statistics instead of
+    /// keeping them, for `--streaming`, with a random sample of up to
+    /// `reservoir` of them kept for `--reservoir`.
+    pub fn stream_log(&self, paths: &[PathBuf], progress: &Progress, reservoir: Option<usize>) -> io::Result<ParsedLog<Vec<(Source, DeviceStats)>>> {
+        let mut groups: Vec<(Source, DeviceStats)> = Vec::new();
+        let log = self.scan_log(paths, progress, |record| {
+            match groups.iter_mut().find(|(source, _)| *source == record.source) {
+                Some((_, stats)) => stats.push(&record),
+                None => {
+                    let mut stats = DeviceStats::new(reservoir);
+                    stats.push(&record);
+                    groups.push((record.source, stats));
+                }
+            }
+        })?;
+        Ok(log.with_records(groups))
+    }
+
+    /// Matches every line, handing each in-window sample to `on_record`.
+    /// With a tail set, samples are held back until the end and only the
+    /// last ones are handed over.
+    pub fn scan<R: BufRead>(&self, reader: R, on_record: impl FnMut(Record)) -> io::Result<ParsedLog<()>> {
+        self.scan_lines(raw_lines(reader), on_record)
+    }
+
+    /// [`Parser::scan`] over the parts of a log rotated across `paths`,
+    /// read oldest first as though they were one file. Parts are ordered
+    /// by their first timestamps; line numbers count on from one part into
+    /// the next.
+    pub fn scan_log(&self, paths: &[PathBuf], progress: &Progress, on_record: impl FnMut(Record)) -> io::Result<ParsedLog<()>> {
+        self.scan_lines(self.open_parts(paths, progress)?, on_record)
+    }
+
+    /// Checks that the first `lines` lines of the log at `paths` can be
+    /// read and parsed, for `--check`. No samples are kept.
+    pub fn check_log(&self, paths: &[PathBuf], lines: usize) -> io::Result<ParsedLog<()>> {
+        let progress = Progress::hidden();
+        self.scan_lines(self.open_parts(paths, &progress)?.take(lines), |_| {})
+    }
+
+    /// Opens every part of a log, oldest first, as one run of lines. Errors
+    /// name the part they came from when there are several.
+    fn open_parts<'a>(&self, paths: &[PathBuf], progress: &'a Progress) -> io::Result<impl Iterator<Item = io::Result<Vec<u8>>> + 'a> {
+        let named = paths.len() > 1;
+        let in_part = move |path: &Path, err: io::Error| match named {
+            true => io::Error::new(err.kind(), format!("{}: {}", path.display(), err)),
+            false => err,
+        };
+
+        let deadline = self.read_timeout.map(|timeout| Deadline { at: Instant::now() + timeout, timeout });
+        let mut parts = Vec::new();
+        for path in self.chronological(paths, deadline) {
+            let reader = open_log_until(&path, progress, deadline).map_err(|err| in_part(&path, err))?;
+            parts.push((path, reader));
+        }
+        Ok(parts
+            .into_iter()
+            .flat_map(move |(path, reader)| raw_lines(reader).map(move |line| line.map_err(|err| in_part(&path, err)))))
+    }
+
+    /// `paths` sorted by the first timestamp within [`PEEK_LINES`] lines of
+    /// each; parts without one keep their place after those with one. A
+    /// single path is left alone, so stdin is never read here.
+    fn chronological(&self, paths: &[PathBuf], deadline: Option<Deadline>) -> Vec<PathBuf> {
+        let mut paths = paths.to_vec();
+        if paths.len() > 1 {
+            paths.sort_by_cached_key(|path| {
+                let first = self.first_timestamp(path, deadline);
+                (first.is_none(), first)
+            });
+            let order: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+            debug!("reading rotated parts oldest first: {}", order.join(", "));
+        }
+        paths
+    }
+
+    fn first_timestamp(&self, path: &Path, deadline: Option<Deadline>) -> Option<DateTime<FixedOffset>> {
+        let progress = Progress::hidden();
+        let reader = open_log_until(path, &progress, deadline).ok()?;
+        raw_lines(reader)
+            .take(PEEK_LINES)
+            .map_while(Result::ok)
+            .filter_map(|line| self.parse_line(&String::from_utf8_lossy(&line)))
+            .find_map(|record| record.timestamp)
+    }
+
+    /// [`Parser::scan`] over lines that have already been split.
+    fn scan_lines(
+        &self,
+        lines: impl Iterator<Item = io::Result<Vec<u8>>>,
+        mut on_record: impl FnMut(Record),
+    ) -> io::Result<ParsedLog<()>> {
+        let mut total_samples = 0;
+        let mut skipped_lines = 0;
+        let mut parse_failures = 0;
+        let mut encoding_errors = 0;
+        let mut excluded_lines = 0;
+        let mut total_lines = 0;
+        let mut unmatched_examples = Vec::new();
+        let mut first_unparsed = None;
+        let mut events = Vec::new();
+        let mut tail = VecDeque::new();
+
+        for line in lines {
+            if interrupt::requested() {
+                return Err(interrupt::error());
+            }
+            total_lines += 1;
+            let line = match String::from_utf8(line?) {
+                Ok(line) => line,
+                Err(err) => {
+                    trace!("line {}: not valid UTF-8", total_lines);
+                    encoding_errors += 1;
+                    first_unparsed.get_or_insert_with(|| (total_lines, String::from_utf8_lossy(err.as_bytes()).into_owned()));
+                    continue;
+                }
+            };
+            if let Some(exclude) = &self.exclude
+                && exclude.is_match(&line)
+            {
+                trace!("line {}: excluded: {}", total_lines, line.trim());
+                excluded_lines += 1;
+                continue;
+            }
+            match self.match_line(&line) {
+                Some(Some(record)) => {
+                    total_samples += 1;
+                    if !self.window.contains(record.timestamp.as_ref()) {
+                        continue;
+                    }
+                    match self.tail {
+                        Some(limit) => {
+                            if tail.len() == limit {
+                                tail.pop_front();
+                            }
+                            tail.push_back(record);
+                        }
+                        None => on_record(record),
+                    }
+                }
+                Some(None) => {
+                    trace!("line {}: matched but could not be parsed: {}", total_lines, line.trim());
+                    parse_failures += 1;
+                    first_unparsed.get_or_insert_with(|| (total_lines, line.clone()));
+                }
+                None => {
+                    let event = self
+                        .events
+                        .as_ref()
+                        .and_then(|re| re.captures(line.trim()))
+                        .and_then(|cap| event_from_captures(&cap, total_lines));
+                    if let Some(event) = event {
+                        if self.window.contains(event.timestamp.as_ref()) {
+                            events.push(event);
+                        }
+                        continue;
+                    }
+                    skipped_lines += 1;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    trace!("line {}: matched no pattern: {}", total_lines, line.trim());
+                    first_unparsed.get_or_insert_with(|| (total_lines, line.clone()));
+                    if unmatched_examples.len() < UNMATCHED_EXAMPLES {
+                        unmatched_examples.push(line);
+                    }
+                }
+            }
+        }
+        tail.into_iter().for_each(on_record);
+
+        Ok(ParsedLog {
+            records: (),
+            total_samples,
+            skipped_lines,
+            parse_failures,
+            encoding_errors,
+            excluded_lines,
+            total_lines,
+            unmatched_examples,
+            first_unparsed,
+            events,
+        })
+    }
+}
+
+/// The lines of `reader` as raw bytes, without their `\n` or `\r\n`, so a
+/// line that isn't valid UTF-8 can be skipped instead of ending the read.
+fn raw_lines<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<Vec<u8>>> {
+    reader.split(b'\n').map(|line| {
+        line.map(|mut line| {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            line
+        })
+    })
+}
+
+/// Compressed formats without a bundled decoder, by file extension, and
+/// the program on `PATH` that decompresses them to stdout with `-dc`.
+const DECOMPRESSORS: &[(&str, &str)] = &[("bz2", "bzip2"), ("zst", "zstd")];
+
+/// Opens a log for reading: standard input for `-`, decompressed on the fly
+/// for `.gz`, `.bz2` and `.zst` files. Bytes read are counted towards
+/// `progress`, except for logs handed to an external decompressor, which
+/// only count once they are done.
+pub fn open_log<'a>(path: &Path, progress: &'a Progress) -> io::Result<Box<dyn BufRead + 'a>> {
+    open_log_until(path, progress, None)
+}
+
+/// [`open_log`], failing once `deadline` passes if one is given.
+fn open_log_until<'a>(path: &Path, progress: &'a Progress, deadline: Option<Deadline>) -> io::Result<Box<dyn BufRead + 'a>> {
+    if path.as_os_str() == STDIN_PATH {
+        return Ok(Box::new(io::BufReader::new(progress.track(io::stdin().lock()))));
+    }
+
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    if let Some((_, program)) = DECOMPRESSORS.iter().find(|(ext, _)| *ext == extension) {
+        debug!("decompressing {} with {}", path.display(), program);
+        if let Some(deadline) = deadline {
+            let path = path.to_path_buf();
+            return Ok(Box::new(io::BufReader::new(TimedReader::spawn(move || Decompressor::spawn(program, &path), deadline)?)));
+        }
+        return Ok(Box::new(io::BufReader::new(Decompressor::spawn(program, path)?)));
+    }
+
+    let file: Box<dyn Read> = match deadline {
+        Some(deadline) => {
+            let path = path.to_path_buf();
+            Box::new(TimedReader::spawn(move || File::open(path), deadline)?)
+        }
+        None => Box::new(File::open(path)?),
+    };
+    let file = progress.track(file);
+    if extension == "gz" {
+        return Ok(Box::new(io::BufReader::new(GzDecoder::new(file))));
+    }
+
+    Ok(Box::new(io::BufReader::new(file)))
+}
+
+/// The output of an external decompressor. A failed exit turns the end of
+/// its output into an error, so a corrupt archive isn't mistaken for a
+/// short log.
+struct Decompressor {
+    program: &'static str,
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Decompressor {
+    fn spawn(program: &'static str, path: &Path) -> io::Result<Decompressor> {
+        // Opening first gives the usual error for a missing file.
+        File::open(path)?;
+        let mut child = Command::new(program)
+            .arg("-dc")
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => io::Error::new(err.kind(), format!("{} is needed to read this file but was not found", program)),
+                _ => err,
+            })?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Decompressor { program, child, stdout })
+    }
+}
+
+impl Read for Decompressor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("{} failed ({})", self.program, status)));
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl Drop for Decompressor {
+    /// Stops a decompressor whose output wasn't read to the end, e.g. by
+    /// `--check`.
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// When a `--read-timeout` runs out, and how long it was.
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    at: Instant,
+    timeout: Duration,
+}
+
+impl Deadline {
+    fn timed_out(&self) -> io::Error {
+        io::Error::new(io::ErrorKind::TimedOut, format!("timed out after {} s", self.timeout.as_secs_f64()))
+    }
+}
+
+/// What a [`TimedReader`]'s thread hands over: the open result, then
+/// chunks until the end of the input.
+type Chunk = io::Result<Vec<u8>>;
+
+/// Input opened and read on a thread of its own and handed over in
+/// chunks, so waiting for it can stop at a deadline. The thread of an
+/// input given up on is left blocked; it ends when its read does, or with
+/// the process.
+struct TimedReader {
+    chunks: Receiver<Chunk>,
+    deadline: Deadline,
+    /// Unread bytes of the last chunk.
+    buffered: Vec<u8>,
+    position: usize,
+}
+
+impl TimedReader {
+    /// Runs `open` and reads what it returns on a new thread, waiting for
+    /// it to open only until the deadline.
+    fn spawn<R: Read, F: FnOnce() -> io::Result<R> + Send + 'static>(open: F, deadline: Deadline) -> io::Result<TimedReader> {
+        // A few chunks ahead is enough to keep the parser busy without
+        // holding a fast file's contents in memory.
+        let (sender, chunks) = mpsc::sync_channel::<Chunk>(4);
+        thread::spawn(move || {
+            let mut input = match open() {
+                Ok(input) => {
+                    if sender.send(Ok(Vec::new())).is_err() {
+                        return;
+                    }
+                    input
+                }
+                Err(err) => {
+                    let _ = sender.send(Err(err));
+                    return;
+                }
+            };
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                match input.read(&mut buffer) {
+                    Ok(0) => return,
+                    Ok(n) => {
+                        if sender.send(Ok(buffer[..n].to_vec())).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => {
+                        let _ = sender.send(Err(err));
+                        return;
+                    }
+                }
+            }
+        });
+
+        let mut reader = TimedReader { chunks, deadline, buffered: Vec::new(), position: 0 };
+        match reader.next_chunk()? {
+            Some(_) => Ok(reader),
+            None => Err(io::Error::other("input closed before it was opened")),
+        }
+    }
+
+    /// The next chunk, or `None` at the end of the input.
+    fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let wait = self.deadline.at.saturating_duration_since(Instant::now());
+        match self.chunks.recv_timeout(wait) {
+            Ok(chunk) => chunk.map(Some),
+            Err(RecvTimeoutError::Timeout) => Err(self.deadline.timed_out()),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+}
+
+impl Read for TimedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffered.len() {
+            match self.next_chunk()? {
+                Some(chunk) => {
+                    self.buffered = chunk;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.buffered.len() - self.position);
+        buf[..n].copy_from_slice(&self.buffered[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Parses every machine's log on a pool of worker threads. Results come
+/// back in the same order as `machines`, so reporting stays deterministic.
+/// `progress` is redrawn from its own thread until every log is done.
+/// After Ctrl-C (see [`interrupt`]) no more logs are started, and those
+/// not started are `None`.
+pub fn parse_all<T, F>(machines: &[Machine], progress: &Progress, parse: F) -> Vec<Option<io::Result<T>>>
+where
+    T: Send,
+    F: Fn(&[PathBuf]) -> io::Result<T> + Sync,
+{
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(machines.len());
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<io::Result<T>>>> =
+        Mutex::new((0..machines.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while !interrupt::requested() {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(machine) = machines.get(index) else { break };
+                    progress.start_file(&machine.name);
+                    let result = parse(&machine.paths);
+                    progress.finish_file();
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+
+        if progress.enabled() {
+            scope.spawn(|| {
+                while !progress.is_finished() && !interrupt::requested() {
+                    thread::sleep(REDRAW_INTERVAL);
+                    progress.draw();
+                }
+                progress.clear();
+            });
+        }
+    });
+
+    results.into_inner().unwrap()
+}
+
+/// Splits a device's records by the daemon that logged them. A log holding
+/// lines of more than one daemon, e.g. ptp4l and phc2sys, describes
+/// different clocks, so each gets its own `{device}-{source}` name; a
+/// single-source log keeps the device name.
+pub fn split_by_source(name: &str, records: Vec<Record>) -> Vec<(String, Vec<Record>)> {
+    let mut groups: Vec<(Source, Vec<Record>)> = Vec::new();
+    for record in records {
+        match groups.iter_mut().find(|(source, _)| *source == record.source) {
+            Some((_, group)) => group.push(record),
+            None => groups.push((record.source, vec![record])),
+        }
+    }
+
+    if groups.len() <= 1 {
+        let records = groups.pop().map(|(_, records)| records).unwrap_or_default();
+        return vec![(name.to_string(), records)];
+    }
+
+    groups
+        .into_iter()
+        .map(|(source, records)| (format!("{}-{}", name, source), records))
+        .collect()
+}
@@ -0,0 +1,1263 @@
+use chrono::{DateTime, FixedOffset};
+use crate::correlation;
+use crate::export;
+use crate::html;
+use crate::record::Record;
+use crate::spectrum;
+use crate::stats;
+use crate::timing::{self, Gap};
+use crate::unit::Unit;
+use plotters::chart::MeshStyle;
+use plotters::coord::Shift;
+use plotters::element::DashedPathElement;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use plotters::prelude::*;
+use statrs::distribution::{Continuous, Normal};
+use statrs::statistics::{Data, Median, OrderStatistics, Statistics};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Image formats the plots can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+    /// One interactive page per device (see [`create_html_plot`]) instead
+    /// of image files.
+    Html,
+    /// An offset chart in braille characters on stdout (see
+    /// [`ascii_offset_plot`]) instead of image files.
+    Ascii,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Svg => "svg",
+            ImageFormat::Html => "html",
+            ImageFormat::Ascii => "txt",
+        }
+    }
+}
+
+/// Settings shared by every plot in a run.
+#[derive(Debug, Clone)]
+pub struct PlotOptions {
+    pub out_dir: PathBuf,
+    pub format: ImageFormat,
+    /// Unit for offset and delay axes.
+    pub unit: Unit,
+    /// Image size in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Longer series are downsampled to about this many points. Defaults to
+    /// a minimum and a maximum for every horizontal pixel.
+    pub max_points: Option<usize>,
+    /// Window of the moving-average trend drawn over offset, delay and freq
+    /// plots, for `--smooth`.
+    pub smooth: Option<usize>,
+    pub x_axis: XAxis,
+    pub grid: Grid,
+    /// Font size of tick labels and axis descriptions, in pixels; `None`
+    /// for the plotting library's default.
+    pub label_size: Option<u32>,
+    pub colors: PlotColors,
+    /// Alpha of each line on the comparison plots, above 0 and at most 1,
+    /// for `--opacity`: overlapping faint lines show dense regions darker.
+    pub opacity: f64,
+    /// Offset axis limits in `unit`, for `--y-min` and `--y-max`, so
+    /// devices can be compared on one scale. Unset ends are auto-scaled.
+    pub y_min: Option<f64>,
+    pub y_max: Option<f64>,
+    /// Standard deviations from the mean beyond which offset plot samples
+    /// are dotted, for `--highlight-sigma`.
+    pub highlight_sigma: Option<f64>,
+    /// Caption template with `{device}` and `{type}` placeholders, or
+    /// `None` for no caption (`--no-title`).
+    pub title: Option<String>,
+    /// A line of run metadata drawn under the caption, for `--subtitle`;
+    /// set per device, so `None` in the run's own options.
+    pub subtitle: Option<String>,
+    /// File name template, without the extension, with `{device}` and
+    /// `{type}` placeholders; `{date}` is filled in before plotting.
+    pub file_template: String,
+}
+
+/// The caption template used unless `--plot-title` gives another.
+pub const DEFAULT_TITLE: &str = "{device} {type}";
+
+/// The file name template used unless `--out-template` gives another.
+pub const DEFAULT_FILE_TEMPLATE: &str = "{device}-{type}";
+
+/// Placeholders an `--out-template` may use.
+pub const FILE_TEMPLATE_PLACEHOLDERS: &[&str] = &["{device}", "{type}", "{date}"];
+
+/// Line colors for each kind of series, for `--color`. Plots derived from
+/// the offset (histogram, rolling std dev, ADEV, MTIE) use its color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotColors {
+    pub offset: RGBColor,
+    pub delay: RGBColor,
+    pub freq: RGBColor,
+}
+
+impl Default for PlotColors {
+    fn default() -> Self {
+        PlotColors { offset: RED, delay: BLUE, freq: RGBColor(0, 150, 0) }
+    }
+}
+
+/// Parses `#rrggbb` (the `#` is optional).
+pub fn parse_hex_color(value: &str) -> Option<RGBColor> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(RGBColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn hex_color(color: RGBColor) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
+
+/// How [`create_plot`] draws its series: the line color, and the window of
+/// a moving-average trend drawn on top, if any.
+#[derive(Debug, Clone, Copy)]
+pub struct LineStyle {
+    pub color: RGBColor,
+    pub smooth: Option<usize>,
+    /// Whether the Y axis honors `--y-min` and `--y-max`.
+    pub offset_axis: bool,
+    /// Dot the samples more than this many standard deviations from the
+    /// mean, for `--highlight-sigma`.
+    pub highlight_sigma: Option<f64>,
+}
+
+impl PlotOptions {
+    /// Output path for `device`'s `plot_type` plot from the file name
+    /// template, e.g. `plots/gm-01-offset.png` for device `GM-01`, made
+    /// safe by [`export::file_stem`]; with no type, separators left at the
+    /// ends are dropped.
+    pub fn path(&self, device: &str, plot_type: &str) -> PathBuf {
+        let stem = export::file_stem(&self.file_template.replace("{device}", device).replace("{type}", plot_type));
+        let stem = stem.trim_matches(|c: char| c == '-' || c == '_' || c == '.');
+        self.out_dir.join(format!("{}.{}", stem, self.format.extension()))
+    }
+
+    pub fn max_points(&self) -> usize {
+        self.max_points.unwrap_or(2 * self.width as usize)
+    }
+
+    /// The caption for `device`'s `plot_type` plot, if captions are on.
+    /// Plots of every device pass an empty `device`.
+    fn caption(&self, device: &str, plot_type: &str) -> Option<String> {
+        let title = self.title.as_ref()?;
+        Some(title.replace("{device}", device).replace("{type}", plot_type).trim().to_string())
+    }
+
+    /// The offset axis range: the data's own `min..max` unless `--y-min`
+    /// or `--y-max` fix an end.
+    fn offset_range(&self, min: f64, max: f64) -> Range<f64> {
+        if self.y_min.is_none() && self.y_max.is_none() {
+            return padded(min, max);
+        }
+        let low = self.y_min.unwrap_or(min);
+        let high = self.y_max.unwrap_or(max);
+        // One fixed end can land beyond all the data.
+        if high > low { low..high } else { low..low + 1.0 }
+    }
+}
+
+/// `min..max`, widened around a constant series (e.g. a stuck reading of
+/// 0) by 1 or 5% of its value, whichever is more, so it plots as a flat
+/// line mid-chart rather than along an edge with no axis labels.
+fn padded(min: f64, max: f64) -> Range<f64> {
+    if max > min {
+        return min..max;
+    }
+    let pad = (min.abs() * 0.05).max(1.0);
+    min - pad..min + pad
+}
+
+/// Starts a chart on `root` with its caption and subtitle, if any, and
+/// the usual margin.
+fn chart_builder<'a, 'b, DB: DrawingBackend>(
+    root: &'a DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    device: &str,
+    plot_type: &str,
+) -> Result<ChartBuilder<'a, 'b, DB>, DrawingAreaErrorKind<DB::ErrorType>> {
+    let mut builder = ChartBuilder::on(root);
+    let caption = options.caption(device, plot_type);
+    let Some(subtitle) = &options.subtitle else {
+        if let Some(caption) = caption {
+            builder.caption(caption, ("sans-serif", 30));
+        }
+        builder.margin(20);
+        return Ok(builder);
+    };
+    // The library has room for one caption line only, so both lines are
+    // drawn here, spaced as it spaces a caption, above a taller margin.
+    let center = root.dim_in_pixel().0 as i32 / 2;
+    let mut top = 20;
+    for (text, size) in caption.iter().map(|caption| (caption.as_str(), 30)).chain([(subtitle.as_str(), 16)]) {
+        let style = TextStyle::from(("sans-serif", size).into_font()).pos(Pos::new(HPos::Center, VPos::Top));
+        let height = root.estimate_text_size(text, &style)?.1 as i32;
+        let padding = (height / 2).min(5);
+        root.draw_text(text, &style, (center, top + padding))?;
+        top += height + 2 * padding;
+    }
+    builder.margin(20).margin_top(top);
+    Ok(builder)
+}
+
+/// The `--subtitle` line for a device's plots: how many samples, over
+/// what time span if they're timestamped, and their RMS offset (`rms`, in
+/// ns) in `unit` with `precision` fractional digits.
+pub fn metadata_subtitle(records: &[Record], rms: f64, unit: Unit, precision: usize) -> String {
+    let mut parts = vec![format!("{} samples", records.len())];
+    let mut timestamps = records.iter().filter_map(|record| record.timestamp);
+    if let Some(start) = timestamps.next() {
+        let end = timestamps.next_back().unwrap_or(start);
+        let end_format = if end.date_naive() == start.date_naive() { "%H:%M:%S" } else { "%Y-%m-%d %H:%M:%S" };
+        parts.push(format!("{} to {}", start.format("%Y-%m-%d %H:%M:%S"), end.format(end_format)));
+    }
+    parts.push(format!("RMS offset {:.precision$} {}", rms / unit.scale(), unit.label()));
+    parts.join(", ")
+}
+
+/// Applies `--grid` and `--label-size` to a chart's mesh, before the
+/// axis descriptions and anything else particular to the chart.
+fn style_mesh<'m, 'a, 'b, X: Ranged, Y: Ranged, DB: DrawingBackend>(
+    mesh: &'m mut MeshStyle<'a, 'b, X, Y, DB>,
+    options: &PlotOptions,
+) -> &'m mut MeshStyle<'a, 'b, X, Y, DB> {
+    match options.grid {
+        Grid::Full => {}
+        Grid::Coarse => {
+            mesh.max_light_lines(0);
+        }
+        Grid::None => {
+            mesh.disable_mesh();
+        }
+    }
+    if let Some(size) = options.label_size {
+        mesh.label_style(("sans-serif", size)).axis_desc_style(("sans-serif", size));
+    }
+    mesh
+}
+
+/// Runs the drawing code in `$body` against a drawing area named `$root`
+/// backed by whichever image format `$options` selects. The chart code is
+/// the same for every backend; only the backend's type differs, which a
+/// closure can't abstract over.
+macro_rules! render {
+    ($options:expr, $path:expr, |$root:ident| $body:block) => {
+        match $options.format {
+            ImageFormat::Png => {
+                let $root = BitMapBackend::new(&$path, ($options.width, $options.height)).into_drawing_area();
+                let result: Result<(), Box<dyn std::error::Error>> = $body;
+                result
+            }
+            ImageFormat::Svg => {
+                let $root = SVGBackend::new(&$path, ($options.width, $options.height)).into_drawing_area();
+                let result: Result<(), Box<dyn std::error::Error>> = $body;
+                result
+            }
+            // The HTML page or terminal chart replaces the images.
+            ImageFormat::Html | ImageFormat::Ascii => {
+                let _ = &$path;
+                Ok(())
+            }
+        }
+    };
+}
+
+/// Grid lines drawn behind a chart, for `--grid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Grid {
+    /// A line at every tick label and fainter ones between them.
+    #[default]
+    Full,
+    /// Only the lines at tick labels, for print.
+    Coarse,
+    /// Just the axes.
+    None,
+}
+
+impl std::str::FromStr for Grid {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Grid, ()> {
+        match value {
+            "full" => Ok(Grid::Full),
+            "coarse" => Ok(Grid::Coarse),
+            "none" => Ok(Grid::None),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What the X axis of time-series plots shows, for `--x-axis`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum XAxis {
+    /// Sample number.
+    Index,
+    /// Wall-clock time from the syslog timestamps.
+    #[default]
+    Time,
+    /// Seconds since the first sample, from the daemon's monotonic
+    /// timestamps where the log has them. Unlike wall-clock time this
+    /// doesn't depend on the clock being disciplined.
+    Elapsed,
+    /// The daemon's monotonic timestamps themselves, to sub-second
+    /// precision, to line samples up with other lines of the same daemon.
+    Internal,
+}
+
+impl std::str::FromStr for XAxis {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<XAxis, ()> {
+        match value {
+            "index" => Ok(XAxis::Index),
+            "time" => Ok(XAxis::Time),
+            "elapsed" => Ok(XAxis::Elapsed),
+            "internal" => Ok(XAxis::Internal),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The scale a [`TimeAxis`] ended up with.
+#[derive(Debug, Clone, Copy)]
+enum AxisKind {
+    Index,
+    /// Seconds since the Unix epoch, labeled in the log's UTC offset.
+    WallClock(FixedOffset),
+    Elapsed,
+    /// Seconds on the daemon's monotonic clock.
+    Internal,
+}
+
+/// X coordinates for time-series plots. Wall-clock, elapsed or monotonic
+/// time needs every sample to carry the timestamp it is based on;
+/// otherwise the axis falls back to the sample index.
+pub struct TimeAxis {
+    values: Vec<f64>,
+    kind: AxisKind,
+    /// Spans with no samples, shaded on time plots.
+    gaps: Vec<(f64, f64)>,
+    /// Positions drawn as vertical lines, e.g. mean shifts.
+    marks: Vec<f64>,
+}
+
+impl TimeAxis {
+    pub fn from_records(records: &[Record], x_axis: XAxis) -> TimeAxis {
+        let timestamps: Option<Vec<DateTime<FixedOffset>>> = records.iter().map(|r| r.timestamp).collect();
+        let timestamps = timestamps.filter(|timestamps| !timestamps.is_empty());
+        let internal: Option<Vec<f64>> = records.iter().map(|r| r.internal_ts).collect();
+        let internal = internal.filter(|internal| !internal.is_empty());
+
+        let (values, kind) = match (x_axis, timestamps, internal) {
+            (XAxis::Time, Some(timestamps), _) => (
+                timestamps.iter().map(timing::seconds).collect(),
+                AxisKind::WallClock(*timestamps[0].offset()),
+            ),
+            (XAxis::Elapsed, _, Some(internal)) => {
+                (internal.iter().map(|t| t - internal[0]).collect(), AxisKind::Elapsed)
+            }
+            (XAxis::Internal, _, Some(internal)) => (internal, AxisKind::Internal),
+            (XAxis::Elapsed, Some(timestamps), None) => (
+                timestamps.iter().map(|t| (*t - timestamps[0]).as_seconds_f64()).collect(),
+                AxisKind::Elapsed,
+            ),
+            _ => ((0..records.len()).map(|i| i as f64).collect(), AxisKind::Index),
+        };
+
+        TimeAxis { values, kind, gaps: Vec::new(), marks: Vec::new() }
+    }
+
+    /// A wall-clock axis at `seconds` since the epoch, labeled in
+    /// `utc_offset`, for series not read straight from records.
+    pub fn wall_clock(seconds: Vec<f64>, utc_offset: FixedOffset) -> TimeAxis {
+        TimeAxis { values: seconds, kind: AxisKind::WallClock(utc_offset), gaps: Vec::new(), marks: Vec::new() }
+    }
+
+    /// Marks `gaps` for shading. Only a wall-clock axis shows them; on an
+    /// index axis a gap takes no space.
+    pub fn with_gaps(mut self, gaps: &[Gap]) -> TimeAxis {
+        if let AxisKind::WallClock(_) = self.kind {
+            self.gaps = gaps.iter().map(|gap| (timing::seconds(&gap.start), timing::seconds(&gap.end))).collect();
+        }
+        self
+    }
+
+    /// The axis with a line at each of the samples at `indices`.
+    pub fn marked(&self, indices: &[usize]) -> TimeAxis {
+        let marks = indices.iter().filter_map(|&i| self.values.get(i).copied()).collect();
+        TimeAxis { values: self.values.clone(), kind: self.kind, gaps: self.gaps.clone(), marks }
+    }
+
+    /// The axis without its first `n` points, for derived series that
+    /// start partway through the samples.
+    pub fn skip(&self, n: usize) -> TimeAxis {
+        TimeAxis {
+            values: self.values[n.min(self.values.len())..].to_vec(),
+            kind: self.kind,
+            gaps: self.gaps.clone(),
+            marks: self.marks.clone(),
+        }
+    }
+
+    fn desc(&self) -> &'static str {
+        match self.kind {
+            AxisKind::Index => "Sample Number",
+            AxisKind::WallClock(_) => "Time",
+            AxisKind::Elapsed => "Elapsed (s)",
+            AxisKind::Internal => "Monotonic Time (s)",
+        }
+    }
+
+    fn label(&self, x: f64) -> String {
+        match self.kind {
+            AxisKind::WallClock(offset) => DateTime::from_timestamp(x.floor() as i64, 0)
+                .map(|t| t.with_timezone(&offset).format("%H:%M:%S").to_string())
+                .unwrap_or_default(),
+            _ if x.fract().abs() < 1e-9 => format!("{:.0}", x),
+            _ => format!("{:.1}", x),
+        }
+    }
+
+    /// How the HTML page should label this axis.
+    fn html_axis(&self) -> html::XAxis {
+        match self.kind {
+            AxisKind::Index => html::XAxis::Index,
+            AxisKind::WallClock(offset) => html::XAxis::Time { utc_offset: offset.local_minus_utc() },
+            AxisKind::Elapsed | AxisKind::Internal => html::XAxis::Elapsed,
+        }
+    }
+
+    /// Caps the tick count so ticks are at least one second (or one
+    /// sample) apart; closer ticks would repeat the same label.
+    fn label_count(&self) -> usize {
+        let range = self.range();
+        ((range.end - range.start).floor() as usize + 1).clamp(2, 10)
+    }
+
+    /// Plotting range covering every point. Samples need not be evenly
+    /// spaced, so this spans the actual first and last values; a single
+    /// sample gets a window of one unit.
+    fn range(&self) -> std::ops::Range<f64> {
+        let start = self.values.iter().copied().fold(f64::INFINITY, f64::min);
+        let end = self.values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if end > start { start..end } else { start..start + 1.0 }
+    }
+}
+
+pub fn create_offset_plot(options: &PlotOptions, axis: &TimeAxis, data: &[f64], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let y_desc = format!("Value ({})", options.unit.label());
+    let style = LineStyle { color: options.colors.offset, smooth: options.smooth, offset_axis: true, highlight_sigma: options.highlight_sigma };
+    create_plot(options, axis, &options.unit.apply(data), device_name, "Offset", &y_desc, style)
+}
+
+/// The offset's first difference per `per` ("s" or "sample"), one point
+/// per sample after the first.
+pub fn create_offset_rate_plot(options: &PlotOptions, axis: &TimeAxis, rates: &[f64], per: &str, device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if rates.is_empty() {
+        return Ok(());
+    }
+
+    let y_desc = format!("Rate ({}/{})", options.unit.label(), per);
+    let style = LineStyle { color: options.colors.offset, smooth: None, offset_axis: false, highlight_sigma: None };
+    create_plot(options, &axis.skip(1), &options.unit.apply(rates), device_name, "Offset Rate", &y_desc, style)
+}
+
+/// The offset with `--detrend`'s trend removed, on the offset axis.
+pub fn create_detrended_plot(options: &PlotOptions, axis: &TimeAxis, data: &[f64], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let y_desc = format!("Value ({})", options.unit.label());
+    let style = LineStyle { color: options.colors.offset, smooth: options.smooth, offset_axis: true, highlight_sigma: None };
+    create_plot(options, axis, &options.unit.apply(data), device_name, "Detrended Offset", &y_desc, style)
+}
+
+pub fn create_delay_plot(options: &PlotOptions, axis: &TimeAxis, data: &[f64], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let y_desc = format!("Value ({})", options.unit.label());
+    let style = LineStyle { color: options.colors.delay, smooth: options.smooth, offset_axis: false, highlight_sigma: None };
+    create_plot(options, axis, &options.unit.apply(data), device_name, "Delay", &y_desc, style)
+}
+
+pub fn create_freq_plot(options: &PlotOptions, axis: &TimeAxis, data: &[f64], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let style = LineStyle { color: options.colors.freq, smooth: options.smooth, offset_axis: false, highlight_sigma: None };
+    create_plot(options, axis, data, device_name, "Freq", "Adjustment (ppb)", style)
+}
+
+/// Plots the offset standard deviation over a sliding window of `window`
+/// samples, each value placed at the time of the window's last sample.
+pub fn create_rolling_stddev_plot(options: &PlotOptions, axis: &TimeAxis, data: &[f64], window: usize, device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let rolling = stats::rolling_std_dev(&options.unit.apply(data), window);
+    if rolling.is_empty() {
+        return Ok(());
+    }
+
+    let y_desc = format!("Offset Std Dev ({})", options.unit.label());
+    let style = LineStyle { color: options.colors.offset, smooth: None, offset_axis: false, highlight_sigma: None };
+    create_plot(options, &axis.skip(window - 1), &rolling, device_name, "Rolling StdDev", &y_desc, style)
+}
+
+/// Plots `data` against `axis` in `style`.
+pub fn create_plot(options: &PlotOptions, axis: &TimeAxis, data: &[f64], device_name: &str, plot_type: &str, y_desc: &str, style: LineStyle) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = options.path(device_name, plot_type);
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let Range { start: min_value, end: max_value } = match style.offset_axis {
+            true => options.offset_range(data.min(), data.max()),
+            false => padded(data.min(), data.max()),
+        };
+
+        let mut chart = chart_builder(&root, options, device_name, plot_type)?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(axis.range(), min_value..max_value)?;
+
+        style_mesh(&mut chart.configure_mesh(), options)
+            .x_desc(axis.desc())
+            .x_labels(axis.label_count())
+            .x_label_formatter(&|x| axis.label(*x))
+            .y_desc(y_desc)
+            .draw()?;
+
+        chart.draw_series(axis.gaps.iter().map(|(start, end)| {
+            Rectangle::new([(*start, min_value), (*end, max_value)], BLACK.mix(0.15).filled())
+        }))?;
+
+        let color = style.color;
+        let raw = chart.draw_series(LineSeries::new(
+            downsample(&axis.values, data, options.max_points()),
+            &color,
+        ))?;
+
+        let trend = style.smooth.map(|window| (window, stats::moving_average(data, window))).filter(|(_, averages)| !averages.is_empty());
+        let highlighted = style.highlight_sigma.map(|sigma| (sigma, stats::beyond_sigma(data, sigma)));
+        let legend = trend.is_some() || highlighted.is_some();
+        if legend {
+            raw.label(plot_type)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+        if let Some((sigma, indices)) = &highlighted {
+            let dot = ShapeStyle::from(&RED).filled();
+            chart
+                .draw_series(indices.iter().map(|&i| Circle::new((axis.values[i], data[i]), 3, dot)))?
+                .label(format!("Beyond {} sigma ({})", sigma, indices.len()))
+                .legend(move |(x, y)| Circle::new((x + 10, y), 3, dot));
+        }
+        if let Some((window, averages)) = trend {
+            let trend_axis = axis.skip(window - 1);
+            chart
+                .draw_series(LineSeries::new(
+                    downsample(&trend_axis.values, &averages, options.max_points()),
+                    BLACK.stroke_width(2),
+                ))?
+                .label(format!("{}-sample moving average", window))
+                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+        }
+        if legend {
+            chart
+                .configure_series_labels()
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+        chart.draw_series(axis.marks.iter().map(|x| DashedPathElement::new(vec![(*x, min_value), (*x, max_value)], 6, 4, BLACK.stroke_width(2))))?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// The offset of device `names.0` minus that of `names.1`, e.g. a
+/// candidate against a known-good reference, over time. The two are
+/// aligned on [`correlation::aligned_series`] buckets as wide as the
+/// coarser logging interval, and buckets missing either are left out.
+pub fn create_diff_plot(options: &PlotOptions, a: &[Record], b: &[Record], names: (&str, &str)) -> Result<(), Box<dyn std::error::Error>> {
+    let timed = [correlation::timed_offsets(a), correlation::timed_offsets(b)];
+    let bucket = correlation::bucket_width(&[&timed[0], &timed[1]]);
+    let series = [(names.0.to_string(), a.to_vec()), (names.1.to_string(), b.to_vec())];
+    let (seconds, diffs): (Vec<f64>, Vec<f64>) = correlation::aligned_series(&series, Duration::from_secs_f64(bucket))
+        .into_iter()
+        .filter_map(|(start, row)| Some((start as f64 / 1e9, row[0]? - row[1]?)))
+        .unzip();
+    let Some(utc_offset) = a.iter().find_map(|record| record.timestamp).map(|t| *t.offset()).filter(|_| !diffs.is_empty()) else {
+        return Err(format!("{} and {} have no timestamped samples in common", names.0, names.1).into());
+    };
+
+    let y_desc = format!("Difference ({})", options.unit.label());
+    let style = LineStyle { color: options.colors.offset, smooth: options.smooth, offset_axis: false, highlight_sigma: None };
+    let device_name = format!("{} vs {}", names.0, names.1);
+    create_plot(options, &TimeAxis::wall_clock(seconds, utc_offset), &options.unit.apply(&diffs), &device_name, "Offset Difference", &y_desc, style)
+}
+
+/// Offset on the left axis and path delay on the right, against the same
+/// time axis. Offset excursions that track delay changes point at the
+/// network (congestion, asymmetry) rather than the clock.
+pub fn create_combined_plot(options: &PlotOptions, axis: &TimeAxis, offsets: &[f64], delays: &[f64], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = options.path(device_name, "combined");
+    let offsets = &options.unit.apply(offsets)[..];
+    let delays = &options.unit.apply(delays)[..];
+    let PlotColors { offset: offset_color, delay: delay_color, .. } = options.colors;
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let offset_range = options.offset_range(offsets.min(), offsets.max());
+        let mut chart = chart_builder(&root, options, device_name, "Offset and Delay")?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .right_y_label_area_size(70)
+            .build_cartesian_2d(axis.range(), offset_range.clone())?
+            .set_secondary_coord(axis.range(), padded(delays.min(), delays.max()));
+
+        style_mesh(&mut chart.configure_mesh(), options)
+            .x_desc(axis.desc())
+            .x_labels(axis.label_count())
+            .x_label_formatter(&|x| axis.label(*x))
+            .y_desc(format!("Offset ({})", options.unit.label()))
+            .draw()?;
+        let mut secondary = chart.configure_secondary_axes();
+        if let Some(size) = options.label_size {
+            secondary.label_style(("sans-serif", size)).axis_desc_style(("sans-serif", size));
+        }
+        secondary.y_desc(format!("Delay ({})", options.unit.label())).draw()?;
+
+        chart.draw_series(axis.gaps.iter().map(|(start, end)| {
+            Rectangle::new([(*start, offset_range.start), (*end, offset_range.end)], BLACK.mix(0.15).filled())
+        }))?;
+
+        chart
+            .draw_series(LineSeries::new(downsample(&axis.values, offsets, options.max_points()), &offset_color))?
+            .label("Offset")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], offset_color));
+        chart
+            .draw_secondary_series(LineSeries::new(downsample(&axis.values, delays, options.max_points()), &delay_color))?
+            .label("Delay")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], delay_color));
+
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Plots each sample's offset against its path delay. Offset error driven
+/// by delay changes shows up as a diagonal smear; a healthy servo gives a
+/// flat band whatever the delay. Longer series are thinned to every n-th
+/// sample to stay within `--max-points`. Samples without a delay are left
+/// out.
+pub fn create_scatter_plot(options: &PlotOptions, records: &[Record], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = options.path(device_name, "scatter");
+    let scale = options.unit.scale();
+    let pairs: Vec<(f64, f64)> = records.iter().filter_map(|r| Some((r.delay? / scale, r.offset / scale))).collect();
+    let step = pairs.len().div_ceil(options.max_points().max(1)).max(1);
+    let points: Vec<(f64, f64)> = pairs.into_iter().step_by(step).collect();
+    let label = options.unit.label();
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let delays = || points.iter().map(|(delay, _)| *delay);
+        let offsets = || points.iter().map(|(_, offset)| *offset);
+        let delay_range = padded(Statistics::min(delays()), Statistics::max(delays()));
+        let offset_range = options.offset_range(Statistics::min(offsets()), Statistics::max(offsets()));
+        let mut chart = chart_builder(&root, options, device_name, "Delay vs Offset")?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(delay_range, offset_range)?;
+
+        style_mesh(&mut chart.configure_mesh(), options)
+            .x_desc(format!("Delay ({})", label))
+            .y_desc(format!("Offset ({})", label))
+            .draw()?;
+
+        let color = options.colors.offset.mix(0.5);
+        chart.draw_series(points.iter().map(|point| Circle::new(*point, 2, color.filled())))?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Writes `{device}.html` with interactive offset and (when given) delay
+/// and freq charts against `axis`, for `--format html`.
+pub fn create_html_plot(options: &PlotOptions, axis: &TimeAxis, offsets: &[f64], delays: Option<&[f64]>, freqs: Option<&[f64]>, device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let unit = options.unit.label();
+    let points = |data: &[f64]| downsample(&axis.values, data, options.max_points());
+    let mut charts = vec![html::Chart {
+        title: "Offset",
+        unit,
+        color: hex_color(options.colors.offset),
+        points: points(&options.unit.apply(offsets)),
+    }];
+    if let Some(delays) = delays {
+        charts.push(html::Chart { title: "Delay", unit, color: hex_color(options.colors.delay), points: points(&options.unit.apply(delays)) });
+    }
+    if let Some(freqs) = freqs {
+        charts.push(html::Chart { title: "Freq", unit: "ppb", color: hex_color(options.colors.freq), points: points(freqs) });
+    }
+
+    html::write_page(&options.path(device_name, ""), device_name, axis.html_axis(), &charts)?;
+    Ok(())
+}
+
+/// The palette index of a device's comparison line, from an FNV-1a hash
+/// of its name: a device keeps its color from run to run however many
+/// others are plotted with it. The hash is spelled out because std's
+/// hasher may change between Rust releases.
+pub fn palette_index(device_name: &str) -> usize {
+    let hash = device_name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    (hash % Palette99::COLORS.len() as u64) as usize
+}
+
+/// Overlays every device's series on one chart, one palette color (see
+/// [`palette_index`]) and legend entry per device, so an outlying node
+/// stands out at a glance.
+pub fn create_comparison_plot(options: &PlotOptions, datasets: &[(String, Vec<f64>)], plot_type: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = options.path("comparison", plot_type);
+    let datasets: Vec<(&String, Vec<f64>)> = datasets.iter().map(|(name, data)| (name, options.unit.apply(data))).collect();
+    let y_desc = format!("Value ({})", options.unit.label());
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let max_len = datasets.iter().map(|(_, data)| data.len()).max().unwrap_or(0);
+        let max_value = Statistics::max(datasets.iter().flat_map(|(_, data)| data));
+        let min_value = Statistics::min(datasets.iter().flat_map(|(_, data)| data));
+
+        let mut chart = chart_builder(&root, options, "", &format!("{} Comparison", plot_type))?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(0..max_len, options.offset_range(min_value, max_value))?;
+
+        style_mesh(&mut chart.configure_mesh(), options).x_desc("Sample Number").y_desc(&y_desc).draw()?;
+
+        for (device_name, data) in &datasets {
+            let color = Palette99::pick(palette_index(device_name)).to_rgba();
+            let indices: Vec<f64> = (0..data.len()).map(|x| x as f64).collect();
+            chart
+                .draw_series(LineSeries::new(
+                    downsample(&indices, data, options.max_points()).into_iter().map(|(x, y)| (x as usize, y)),
+                    color.mix(options.opacity),
+                ))?
+                .label(device_name.as_str())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Pixel size of each device's tile on the contact sheet.
+const TILE_SIZE: (u32, u32) = (320, 200);
+
+/// Draws every device's offset series as a small labeled tile, `columns`
+/// to a row, in one `contact-sheet` image for scanning a whole fleet at
+/// once. Tiles share the `--y-min`/`--y-max` limits but otherwise scale
+/// to their own data.
+pub fn create_contact_sheet(options: &PlotOptions, datasets: &[(String, Vec<f64>)], columns: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let columns = columns.clamp(1, datasets.len().max(1));
+    let rows = datasets.len().div_ceil(columns).max(1);
+    let sheet = PlotOptions { width: TILE_SIZE.0 * columns as u32, height: TILE_SIZE.1 * rows as u32, ..options.clone() };
+    let filename = options.path("contact", "sheet");
+    render!(sheet, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let color = options.colors.offset;
+        for (area, (device_name, data)) in root.split_evenly((rows, columns)).iter().zip(datasets) {
+            let data = &options.unit.apply(data)[..];
+            let mut chart = ChartBuilder::on(area)
+                .caption(device_name, ("sans-serif", 16))
+                .margin(8)
+                .x_label_area_size(20)
+                .y_label_area_size(50)
+                .build_cartesian_2d(0.0..data.len().saturating_sub(1).max(1) as f64, options.offset_range(data.min(), data.max()))?;
+            style_mesh(&mut chart.configure_mesh(), options).x_labels(4).y_labels(5).label_style(("sans-serif", 10)).draw()?;
+
+            let indices: Vec<f64> = (0..data.len()).map(|x| x as f64).collect();
+            chart.draw_series(LineSeries::new(downsample(&indices, data, 2 * TILE_SIZE.0 as usize), &color))?;
+        }
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Quartiles, whiskers and outliers of one device's offsets, as drawn on
+/// the box plot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxStats {
+    pub q1: f64,
+    pub median: f64,
+    pub q3: f64,
+    /// The furthest samples within 1.5 interquartile ranges of the box.
+    pub low: f64,
+    pub high: f64,
+    /// Every distinct sample beyond the whiskers, in ascending order.
+    pub outliers: Vec<f64>,
+}
+
+impl BoxStats {
+    /// `None` without any samples.
+    pub fn from_values(values: &[f64]) -> Option<BoxStats> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut data = Data::new(values.to_vec());
+        let (q1, median, q3) = (data.lower_quartile(), data.median(), data.upper_quartile());
+        let reach = 1.5 * (q3 - q1);
+        let (low_fence, high_fence) = (q1 - reach, q3 + reach);
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let inside = sorted.iter().filter(|v| (low_fence..=high_fence).contains(*v));
+        let (low, high) = (Statistics::min(inside.clone()), Statistics::max(inside));
+        let mut outliers: Vec<f64> = sorted.into_iter().filter(|v| !(low_fence..=high_fence).contains(v)).collect();
+        outliers.dedup();
+        Some(BoxStats { q1, median, q3, low, high, outliers })
+    }
+}
+
+/// Draws one box and whiskers per device, side by side on a shared offset
+/// axis, in the devices' palette colors: the densest way to compare the
+/// distributions of a whole fleet on one figure.
+pub fn create_box_plot(options: &PlotOptions, datasets: &[(String, Vec<f64>)]) -> Result<(), Box<dyn std::error::Error>> {
+    let boxes: Vec<(&String, BoxStats)> = datasets
+        .iter()
+        .filter_map(|(name, data)| BoxStats::from_values(&options.unit.apply(data)).map(|stats| (name, stats)))
+        .collect();
+    if boxes.is_empty() {
+        return Ok(());
+    }
+
+    let filename = options.path("comparison", "Box Plot");
+    let y_desc = format!("Offset ({})", options.unit.label());
+    let label = |x: &f64| match boxes.get(x.round() as usize) {
+        Some((name, _)) if x.fract() == 0.0 && *x >= 0.0 => name.to_string(),
+        _ => String::new(),
+    };
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let min_value = Statistics::min(boxes.iter().map(|(_, stats)| stats.outliers.first().map_or(stats.low, |v| v.min(stats.low))));
+        let max_value = Statistics::max(boxes.iter().map(|(_, stats)| stats.outliers.last().map_or(stats.high, |v| v.max(stats.high))));
+        let mut chart = chart_builder(&root, options, "", "Offset Box Plot")?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(-0.5..boxes.len() as f64 - 0.5, options.offset_range(min_value, max_value))?;
+
+        style_mesh(&mut chart.configure_mesh(), options)
+            .disable_x_mesh()
+            .x_labels(boxes.len() + 1)
+            .x_label_formatter(&label)
+            .y_desc(&y_desc)
+            .draw()?;
+
+        for (i, (name, stats)) in boxes.iter().enumerate() {
+            let color = Palette99::pick(palette_index(name)).to_rgba();
+            let x = i as f64;
+            let whisker = |y: f64| PathElement::new(vec![(x - 0.15, y), (x + 0.15, y)], color);
+            chart.draw_series([
+                Rectangle::new([(x - 0.3, stats.q1), (x + 0.3, stats.q3)], color.mix(0.3).filled()),
+                Rectangle::new([(x - 0.3, stats.q1), (x + 0.3, stats.q3)], color.stroke_width(1)),
+            ])?;
+            chart.draw_series([
+                PathElement::new(vec![(x - 0.3, stats.median), (x + 0.3, stats.median)], color.stroke_width(2)),
+                PathElement::new(vec![(x, stats.q3), (x, stats.high)], color.stroke_width(1)),
+                PathElement::new(vec![(x, stats.q1), (x, stats.low)], color.stroke_width(1)),
+                whisker(stats.high),
+                whisker(stats.low),
+            ])?;
+            chart.draw_series(stats.outliers.iter().map(|y| Circle::new((x, *y), 2, color)))?;
+        }
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Braille cells across and down the `--format ascii` chart, which with
+/// its axis labels and a tab of indent fits an 80-column terminal. Each
+/// cell is 2 dots wide and 4 high.
+const ASCII_SIZE: (usize, usize) = (60, 16);
+
+/// Bit of the braille pattern for the dot at `(column, row)` of a cell.
+const BRAILLE_DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Draws the offset series in braille characters, for a look at its shape
+/// on a headless server. Returns the chart's lines: a heading, the plot
+/// with the Y range at its corners, and the first and last X labels.
+pub fn ascii_offset_plot(options: &PlotOptions, axis: &TimeAxis, data: &[f64]) -> Vec<String> {
+    let data = &options.unit.apply(data)[..];
+    let (columns, rows) = ASCII_SIZE;
+    let (width, height) = (2 * columns, 4 * rows);
+    let x = axis.range();
+    let y = options.offset_range(data.min(), data.max());
+    let to_dot = |(px, py): (f64, f64)| {
+        let dx = (px - x.start) / (x.end - x.start) * (width - 1) as f64;
+        let dy = (y.end - py) / (y.end - y.start) * (height - 1) as f64;
+        (dx.round().clamp(0.0, (width - 1) as f64) as usize, dy.round().clamp(0.0, (height - 1) as f64) as usize)
+    };
+
+    let mut cells = vec![vec![0u8; columns]; rows];
+    let mut plot = |(dx, dy): (usize, usize)| cells[dy / 4][dx / 2] |= BRAILLE_DOTS[dx % 2][dy % 4];
+    let dots: Vec<(usize, usize)> = downsample(&axis.values, data, 2 * width).into_iter().map(to_dot).collect();
+    if let Some(&first) = dots.first() {
+        plot(first);
+    }
+    // Joins consecutive points so steep swings stay connected.
+    for pair in dots.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        let steps = x0.abs_diff(x1).max(y0.abs_diff(y1)).max(1);
+        for step in 1..=steps {
+            let t = step as f64 / steps as f64;
+            let at = |a: usize, b: usize| (a as f64 + (b as f64 - a as f64) * t).round() as usize;
+            plot((at(x0, x1), at(y0, y1)));
+        }
+    }
+
+    const LABEL_WIDTH: usize = 10;
+    let mut lines = vec![format!("{:>LABEL_WIDTH$} Offset ({})", "", options.unit.label())];
+    for (row, bits) in cells.iter().enumerate() {
+        let label = match row {
+            0 => format!("{:.2}", y.end),
+            _ if row == rows - 1 => format!("{:.2}", y.start),
+            _ => String::new(),
+        };
+        let tick = if label.is_empty() { '│' } else { '┤' };
+        let line: String = bits.iter().map(|bits| char::from_u32(0x2800 + u32::from(*bits)).unwrap_or(' ')).collect();
+        lines.push(format!("{:>LABEL_WIDTH$} {}{}", label, tick, line));
+    }
+    lines.push(format!("{:>LABEL_WIDTH$} └{}", "", "─".repeat(columns)));
+    let (first, last) = (axis.label(x.start), axis.label(x.end));
+    lines.push(format!("{:>LABEL_WIDTH$}  {}{:>pad$}", "", first, last, pad = (columns + 1).saturating_sub(first.len())));
+    lines
+}
+
+/// Reduces a series to at most `max_points` points by splitting it into
+/// buckets and keeping each bucket's minimum and maximum, in time order, so
+/// spikes survive where averaging would flatten them.
+fn downsample(xs: &[f64], ys: &[f64], max_points: usize) -> Vec<(f64, f64)> {
+    let points = xs.iter().copied().zip(ys.iter().copied());
+    let buckets = max_points / 2;
+    if ys.len() <= max_points || buckets == 0 {
+        return points.collect();
+    }
+
+    let points: Vec<(f64, f64)> = points.collect();
+    let mut reduced = Vec::with_capacity(2 * buckets);
+    for bucket in 0..buckets {
+        let start = bucket * points.len() / buckets;
+        let end = (bucket + 1) * points.len() / buckets;
+        let slice = &points[start..end];
+        let Some(low) = slice.iter().min_by(|a, b| a.1.total_cmp(&b.1)) else {
+            continue;
+        };
+        let high = slice.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap_or(low);
+        if low.0 <= high.0 {
+            reduced.extend([*low, *high]);
+        } else {
+            reduced.extend([*high, *low]);
+        }
+    }
+    reduced
+}
+
+/// Bins `data` into `bins` equal-width buckets and draws them as a bar
+/// chart, to show whether the distribution is tight, wide or bimodal. With
+/// `fit_normal` the normal curve of the data's mean and std dev is drawn
+/// over the bars, scaled to their area, so skew and heavy tails stand out.
+pub fn create_histogram(options: &PlotOptions, data: &[f64], device_name: &str, label: &str, bins: usize, fit_normal: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = options.path(device_name, &format!("{}-hist", label));
+    let data = &options.unit.apply(data)[..];
+    let x_desc = format!("Value ({})", options.unit.label());
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let bins = bins.max(1);
+        let min_value = data.min();
+        let mut max_value = data.max();
+        if max_value <= min_value {
+            max_value = min_value + 1.0;
+        }
+        let bin_width = (max_value - min_value) / bins as f64;
+
+        let mut counts = vec![0usize; bins];
+        for value in data {
+            let bin = (((value - min_value) / bin_width) as usize).min(bins - 1);
+            counts[bin] += 1;
+        }
+        let curve = fit_normal.then(|| normal_curve(data, min_value..max_value, data.len() as f64 * bin_width)).flatten();
+        let max_count = counts.iter().copied().max().unwrap_or(0) as f64;
+        let max_density = curve.iter().flatten().map(|(_, count)| *count).fold(max_count, f64::max);
+
+        let mut chart = chart_builder(&root, options, device_name, &format!("{} Histogram", label))?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(min_value..max_value, 0.0..max_density + 1.0)?;
+
+        style_mesh(&mut chart.configure_mesh(), options).x_desc(&x_desc).y_desc("Samples").y_label_formatter(&|count| format!("{:.0}", count)).draw()?;
+
+        chart.draw_series(counts.iter().enumerate().map(|(bin, count)| {
+            let left = min_value + bin as f64 * bin_width;
+            Rectangle::new([(left, 0.0), (left + bin_width, *count as f64)], options.colors.offset.filled())
+        }))?;
+
+        if let Some(curve) = curve {
+            chart
+                .draw_series(LineSeries::new(curve, BLACK.stroke_width(2)))?
+                .label("Normal fit")
+                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+            chart
+                .configure_series_labels()
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Points drawn along the `--fit-normal` curve.
+const NORMAL_CURVE_POINTS: usize = 200;
+
+/// The normal PDF of `data`'s mean and std dev over `range`, scaled by
+/// `area` (samples times bin width) to match histogram counts. `None` for
+/// a constant series, which has no curve.
+fn normal_curve(data: &[f64], range: Range<f64>, area: f64) -> Option<Vec<(f64, f64)>> {
+    let std_dev = data.std_dev();
+    if std_dev.is_nan() || std_dev <= 0.0 {
+        return None;
+    }
+    let normal = Normal::new(data.mean(), std_dev).ok()?;
+    let step = (range.end - range.start) / (NORMAL_CURVE_POINTS - 1) as f64;
+    Some((0..NORMAL_CURVE_POINTS).map(|i| range.start + i as f64 * step).map(|x| (x, area * normal.pdf(x))).collect())
+}
+
+/// Percentiles marked on the CDF plot.
+const CDF_MARKS: [usize; 3] = [50, 95, 99];
+
+/// Empirical CDF of `data`: the fraction of samples at or below each value,
+/// so the bound covering e.g. 99% of samples can be read off directly. The
+/// p50, p95 and p99 points are marked.
+pub fn create_cdf_plot(options: &PlotOptions, data: &[f64], device_name: &str, label: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let filename = options.path(device_name, &format!("{}-cdf", label));
+    let mut sorted = options.unit.apply(data);
+    sorted.sort_by(f64::total_cmp);
+    let n = sorted.len();
+    let fractions: Vec<f64> = (1..=n).map(|rank| rank as f64 / n as f64).collect();
+    let marks: Vec<(usize, f64, f64)> = CDF_MARKS
+        .iter()
+        .map(|&p| {
+            let index = (p * n).div_ceil(100).clamp(1, n) - 1;
+            (p, sorted[index], fractions[index])
+        })
+        .collect();
+    let x_desc = format!("Value ({})", options.unit.label());
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let min_value = sorted[0];
+        let mut max_value = sorted[n - 1];
+        if max_value <= min_value {
+            max_value = min_value + 1.0;
+        }
+
+        let mut chart = chart_builder(&root, options, device_name, &format!("{} CDF", label))?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(min_value..max_value, 0.0..1.05)?;
+
+        style_mesh(&mut chart.configure_mesh(), options).x_desc(&x_desc).y_desc("Fraction of samples").draw()?;
+
+        chart.draw_series(LineSeries::new(
+            downsample(&sorted, &fractions, options.max_points()),
+            &options.colors.offset,
+        ))?;
+        chart.draw_series(marks.iter().map(|&(p, value, fraction)| {
+            EmptyElement::at((value, fraction))
+                + Circle::new((0, 0), 4, BLACK.filled())
+                + Text::new(format!("p{} {:.2}", p, value), (-70, -6), ("sans-serif", 14))
+        }))?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Log-log plot of Allan deviation against averaging time.
+pub fn create_adev_plot(options: &PlotOptions, adev: &[(f64, f64)], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // A zero deviation (e.g. a perfectly constant series) has no place on a log axis.
+    let points: Vec<(f64, f64)> = adev.iter().copied().filter(|(_, dev)| *dev > 0.0).collect();
+    if points.is_empty() {
+        return Ok(());
+    }
+
+    let filename = options.path(device_name, "adev");
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let min_tau = Statistics::min(points.iter().map(|(tau, _)| *tau));
+        let max_tau = Statistics::max(points.iter().map(|(tau, _)| *tau));
+        let min_dev = Statistics::min(points.iter().map(|(_, dev)| *dev));
+        let max_dev = Statistics::max(points.iter().map(|(_, dev)| *dev));
+
+        let mut chart = chart_builder(&root, options, device_name, "Allan Deviation")?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(
+                (min_tau * 0.9..max_tau * 1.1).log_scale(),
+                (min_dev * 0.9..max_dev * 1.1).log_scale(),
+            )?;
+
+        style_mesh(&mut chart.configure_mesh(), options)
+            .x_desc("Averaging Time (s)")
+            .y_desc("ADEV")
+            .y_label_formatter(&|v| format!("{:.0e}", v))
+            .draw()?;
+
+        chart.draw_series(LineSeries::new(points.iter().copied(), &options.colors.offset))?;
+        chart.draw_series(points.iter().map(|point| Circle::new(*point, 3, options.colors.offset.filled())))?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// The offset correlogram against lag in seconds, with a guide at 1/e: a
+/// slow decay means a sluggish servo, a swing below zero an under-damped
+/// one.
+pub fn create_autocorrelation_plot(options: &PlotOptions, autocorrelation: &[f64], interval: f64, device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if autocorrelation.len() < 2 {
+        return Ok(());
+    }
+
+    let filename = options.path(device_name, "acf");
+    let points: Vec<(f64, f64)> = autocorrelation.iter().enumerate().map(|(lag, r)| (lag as f64 * interval, *r)).collect();
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let max_lag = points[points.len() - 1].0;
+        let mut chart = chart_builder(&root, options, device_name, "Autocorrelation")?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(0.0..max_lag, -1.0..1.0)?;
+
+        style_mesh(&mut chart.configure_mesh(), options).x_desc("Lag (s)").y_desc("Autocorrelation").draw()?;
+
+        let threshold = (-1.0_f64).exp();
+        chart.draw_series(LineSeries::new([(0.0, threshold), (max_lag, threshold)], BLACK.mix(0.4)))?;
+        chart.draw_series(LineSeries::new([(0.0, 0.0), (max_lag, 0.0)], BLACK.mix(0.4)))?;
+        chart.draw_series(LineSeries::new(points.iter().copied(), &options.colors.offset))?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// MTIE against observation window length, windows on a log axis.
+pub fn create_mtie_plot(options: &PlotOptions, mtie: &[(usize, f64)], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let points: Vec<(f64, f64)> = mtie
+        .iter()
+        .map(|(window, value)| (*window as f64, value / options.unit.scale()))
+        .collect();
+    if points.is_empty() {
+        return Ok(());
+    }
+
+    let filename = options.path(device_name, "mtie");
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let min_window = Statistics::min(points.iter().map(|(window, _)| *window));
+        let max_window = Statistics::max(points.iter().map(|(window, _)| *window));
+        let max_value = Statistics::max(points.iter().map(|(_, value)| *value));
+
+        // MTIE often spans less than a decade, where a log Y axis would
+        // have no labels at all.
+        let mut chart = chart_builder(&root, options, device_name, "MTIE")?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d((min_window * 0.9..max_window * 1.1).log_scale(), 0.0..max_value * 1.1)?;
+
+        style_mesh(&mut chart.configure_mesh(), options)
+            .x_desc("Window (samples)")
+            .y_desc(format!("MTIE ({})", options.unit.label()))
+            .draw()?;
+
+        chart.draw_series(LineSeries::new(points.iter().copied(), &options.colors.offset))?;
+        chart.draw_series(points.iter().map(|point| Circle::new(*point, 3, options.colors.offset.filled())))?;
+
+        root.present()?;
+        Ok(())
+    })
+}
+
+/// Plots the offset's amplitude spectrum from
+/// [`spectrum::amplitude_spectrum`], marking the strongest peak with its
+/// period.
+pub fn create_spectrum_plot(options: &PlotOptions, spectrum: &[(f64, f64)], device_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((peak_freq, peak_amplitude)) = spectrum::peak(spectrum) else {
+        return Ok(());
+    };
+
+    let scale = options.unit.scale();
+    let freqs: Vec<f64> = spectrum.iter().map(|(freq, _)| *freq).collect();
+    let amplitudes: Vec<f64> = spectrum.iter().map(|(_, amplitude)| amplitude / scale).collect();
+    let peak = (peak_freq, peak_amplitude / scale);
+    let filename = options.path(device_name, "spectrum");
+    render!(options, filename, |root| {
+        root.fill(&WHITE)?;
+
+        let max_freq = freqs[freqs.len() - 1];
+        let max_amplitude = if peak.1 > 0.0 { peak.1 * 1.1 } else { 1.0 };
+
+        let mut chart = chart_builder(&root, options, device_name, "Offset Spectrum")?
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(0.0..max_freq, 0.0..max_amplitude)?;
+
+        style_mesh(&mut chart.configure_mesh(), options)
+            .x_desc("Frequency (Hz)")
+            .y_desc(format!("Amplitude ({})", options.unit.label()))
+            .draw()?;
+
+        chart.draw_series(LineSeries::new(
+            downsample(&freqs, &amplitudes, options.max_points()),
+            &options.colors.offset,
+        ))?;
+        chart.draw_series(std::iter::once(
+            EmptyElement::at(peak)
+                + Circle::new((0, 0), 4, BLACK.filled())
+                + Text::new(format!("period {:.1} s", 1.0 / peak.0), (8, -6), ("sans-serif", 14)),
+        ))?;
+
+        root.present()?;
+        Ok(())
+    })
+}
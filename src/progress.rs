@@ -0,0 +1,122 @@
+//! A one-line progress display on stderr while logs are parsed, so a long
+//! run over big logs doesn't look hung. It is only drawn when stderr is a
+//! terminal; everywhere else the counters are kept but nothing is printed.
+
+use crate::cli::Machine;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often the progress line is redrawn.
+pub const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+pub struct Progress {
+    enabled: bool,
+    total_files: usize,
+    /// Combined on-disk size of the logs; 0 when unknown (e.g. stdin).
+    total_bytes: u64,
+    files_done: AtomicUsize,
+    bytes_read: AtomicU64,
+    /// The device most recently started, shown as the one in progress.
+    current: Mutex<String>,
+}
+
+impl Progress {
+    /// Progress over `machines`' logs, drawn if `show` is set and stderr is
+    /// a terminal.
+    pub fn new(machines: &[Machine], show: bool) -> Progress {
+        let total_bytes = machines
+            .iter()
+            .flat_map(|machine| &machine.paths)
+            .filter_map(|path| fs::metadata(path).ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        Progress {
+            enabled: show && io::stderr().is_terminal(),
+            total_files: machines.len(),
+            total_bytes,
+            files_done: AtomicUsize::new(0),
+            bytes_read: AtomicU64::new(0),
+            current: Mutex::new(String::new()),
+        }
+    }
+
+    /// Progress that is never drawn.
+    pub fn hidden() -> Progress {
+        Progress::new(&[], false)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn start_file(&self, name: &str) {
+        *self.current.lock().unwrap() = name.to_string();
+    }
+
+    pub fn finish_file(&self) {
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.files_done.load(Ordering::Relaxed) >= self.total_files
+    }
+
+    /// Wraps raw (still compressed, for `.gz`) file input so the bytes it
+    /// yields are counted.
+    pub fn track<R: Read>(&self, inner: R) -> Tracked<'_, R> {
+        Tracked { inner, progress: self }
+    }
+
+    /// Redraws the progress line in place.
+    pub fn draw(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        let read = self.bytes_read.load(Ordering::Relaxed);
+        let amount = if self.total_bytes > 0 {
+            let percent = (read as f64 / self.total_bytes as f64 * 100.0).min(100.0);
+            format!("{} of {} ({:.0}%)", megabytes(read), megabytes(self.total_bytes), percent)
+        } else {
+            megabytes(read)
+        };
+        let current = self.current.lock().unwrap().clone();
+        let done = self.files_done.load(Ordering::Relaxed);
+
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[KParsing [{}/{}] {}: {}", done, self.total_files, current, amount);
+        let _ = stderr.flush();
+    }
+
+    /// Erases the progress line so later output starts on a clean line.
+    pub fn clear(&self) {
+        if self.enabled {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1e6)
+}
+
+/// A reader that adds every byte it reads to a [`Progress`].
+pub struct Tracked<'a, R> {
+    inner: R,
+    progress: &'a Progress,
+}
+
+impl<R: Read> Read for Tracked<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
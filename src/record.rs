@@ -0,0 +1,101 @@
+use chrono::{DateTime, FixedOffset};
+use crate::json;
+use std::fmt;
+
+/// The linuxptp daemon that logged a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Ptp4l,
+    /// ptp4l's `rms ... max ...` lines, logged instead of every sample
+    /// when `summary_interval` is set.
+    Ptp4lSummary,
+    Phc2sys,
+    /// ts2phc, which has no path delay: its records carry none.
+    Ts2phc,
+    /// Matched by a user-supplied `--pattern`.
+    Custom,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Ptp4l => write!(f, "ptp4l"),
+            Source::Ptp4lSummary => write!(f, "ptp4l-summary"),
+            Source::Phc2sys => write!(f, "phc2sys"),
+            Source::Ts2phc => write!(f, "ts2phc"),
+            Source::Custom => write!(f, "custom"),
+        }
+    }
+}
+
+/// One offset sample from a ptp4l, phc2sys or ts2phc log line. A ptp4l
+/// summary line is a pre-aggregated record: `offset` is the RMS offset over
+/// the summary interval and `max_offset` its largest |offset|.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub source: Source,
+    /// Syslog timestamp, including the logged UTC offset. Custom patterns
+    /// without a `timestamp` group leave this unset.
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    /// The daemon's own monotonic timestamp in seconds.
+    pub internal_ts: Option<f64>,
+    pub offset: f64,
+    /// Largest |offset| of a pre-aggregated record's interval.
+    pub max_offset: Option<f64>,
+    /// Servo state token: 0 unlocked, 1 clock step, 2 locked.
+    pub servo_state: Option<u8>,
+    pub freq: Option<f64>,
+    /// Path delay; `None` for ts2phc, which measures none.
+    pub delay: Option<f64>,
+}
+
+impl Record {
+    /// The largest |offset| the record stands for: its own, or the
+    /// interval max of a pre-aggregated record.
+    pub fn worst_offset(&self) -> f64 {
+        self.max_offset.unwrap_or(0.0).max(self.offset.abs())
+    }
+
+    /// One JSON object per record, tagged with the device it came from,
+    /// for `--jsonl`. Fields a log format doesn't carry are `null`.
+    pub fn to_json(&self, device: &str) -> String {
+        let optional = |value: Option<f64>| value.map_or("null".to_string(), json::number);
+        json::Object::new()
+            .string("device", device)
+            .string("source", &self.source.to_string())
+            .raw("timestamp", self.timestamp.map_or("null".to_string(), |t| json::string(&t.to_rfc3339())))
+            .raw("internal_ts", optional(self.internal_ts))
+            .number("offset", self.offset)
+            .raw("max_offset", optional(self.max_offset))
+            .raw("servo_state", self.servo_state.map_or("null".to_string(), |state| state.to_string()))
+            .raw("freq", optional(self.freq))
+            .raw("delay", optional(self.delay))
+            .finish()
+    }
+
+    /// One InfluxDB line-protocol point in measurement `ptp`, tagged with
+    /// the device and source, for `--influx`. `None` without a timestamp,
+    /// since untimed points would all land on the time they are written.
+    pub fn to_influx(&self, device: &str) -> Option<String> {
+        let timestamp = self.timestamp?.timestamp_nanos_opt()?;
+        let mut fields = format!("offset={}", self.offset);
+        if let Some(delay) = self.delay {
+            fields.push_str(&format!(",delay={}", delay));
+        }
+        if let Some(max_offset) = self.max_offset {
+            fields.push_str(&format!(",max_offset={}", max_offset));
+        }
+        if let Some(freq) = self.freq {
+            fields.push_str(&format!(",freq={}", freq));
+        }
+        if let Some(state) = self.servo_state {
+            fields.push_str(&format!(",servo_state={}i", state));
+        }
+        Some(format!("ptp,device={},source={} {} {}", escape_tag(device), self.source, fields, timestamp))
+    }
+}
+
+/// Escapes the characters that end an InfluxDB tag value.
+fn escape_tag(value: &str) -> String {
+    value.replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
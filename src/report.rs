@@ -0,0 +1,54 @@
+use crate::plot::{ImageFormat, PlotOptions};
+use crate::summary::DeviceSummary;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writes a Markdown report with one summary table row per device followed
+/// by each device's offset and delay plots (or a link to its HTML page),
+/// unless no plots were drawn. Values have `precision` fractional digits.
+/// Image links use the plot directory exactly as given on the command line.
+pub fn write_markdown(path: &Path, summaries: &[DeviceSummary], plots: &PlotOptions, precision: usize, with_plots: bool) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    writeln!(out, "# PTP Statistics Report")?;
+    writeln!(out)?;
+    let unit = plots.unit.label();
+    writeln!(
+        out,
+        "| Device | Samples | Mean Offset ({unit}) | Max \\|Offset\\| ({unit}) | RMS Offset ({unit}) | Mean Delay ({unit}) | Max Delay ({unit}) |"
+    )?;
+    writeln!(out, "|---|---:|---:|---:|---:|---:|---:|")?;
+    let scale = plots.unit.scale();
+    let delay = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{:.precision$}", value / scale));
+    for summary in summaries {
+        writeln!(
+            out,
+            "| {} | {} | {:.precision$} | {:.precision$} | {:.precision$} | {} | {} |",
+            summary.device,
+            summary.samples,
+            summary.offset.mean / scale,
+            summary.abs_offset.max / scale,
+            summary.offset.rms / scale,
+            delay(summary.delay.as_ref().map(|delay| delay.mean)),
+            delay(summary.delay.as_ref().map(|delay| delay.max)),
+        )?;
+    }
+
+    for summary in summaries.iter().filter(|_| with_plots) {
+        writeln!(out)?;
+        writeln!(out, "## {}", summary.device)?;
+        writeln!(out)?;
+        if plots.format == ImageFormat::Html {
+            let page = plots.path(&summary.device, "");
+            writeln!(out, "[{} interactive plots]({})", summary.device, page.display())?;
+            continue;
+        }
+        for plot_type in ["Offset", "Delay"] {
+            let image = plots.path(&summary.device, plot_type);
+            writeln!(out, "![{} {}]({})", summary.device, plot_type, image.display())?;
+        }
+    }
+
+    out.flush()
+}
@@ -0,0 +1,98 @@
+use crate::record::Record;
+use chrono::{DateTime, FixedOffset};
+
+/// The servo state ptp4l reports once the clock is locked.
+pub const LOCKED: u8 = 2;
+
+/// A change of servo state between two consecutive samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// Index of the first sample in the new state.
+    pub index: usize,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    pub from: u8,
+    pub to: u8,
+}
+
+/// Returns every point where the servo state differs from the previous
+/// sample, stamped with the time of the first sample in the new state.
+/// Samples without a state (from custom patterns) are ignored.
+pub fn state_transitions(records: &[Record]) -> Vec<Transition> {
+    records
+        .windows(2)
+        .enumerate()
+        .filter_map(|(index, pair)| match (pair[0].servo_state, pair[1].servo_state) {
+            (Some(from), Some(to)) if from != to => Some(Transition {
+                index: index + 1,
+                timestamp: pair[1].timestamp,
+                from,
+                to,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A stretch of consecutive samples in one servo state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateRun {
+    pub state: u8,
+    /// Index of the run's first sample.
+    pub index: usize,
+    pub samples: usize,
+    /// Seconds from the run's first sample to the first of the next run,
+    /// or to its own last sample for the final run; `None` without
+    /// timestamps.
+    pub duration: Option<f64>,
+}
+
+/// Run-length encodes the servo state sequence, telling a clock that
+/// locked once and stayed from one that kept dropping in and out of lock.
+/// Samples without a state are ignored.
+pub fn state_runs(records: &[Record]) -> Vec<StateRun> {
+    let stated: Vec<(usize, &Record, u8)> = records
+        .iter()
+        .enumerate()
+        .filter_map(|(index, r)| Some((index, r, r.servo_state?)))
+        .collect();
+
+    let mut runs = Vec::new();
+    let mut start = 0;
+    while start < stated.len() {
+        let state = stated[start].2;
+        let len = stated[start..].iter().take_while(|(_, _, s)| *s == state).count();
+        let end = stated.get(start + len).unwrap_or(&stated[start + len - 1]).1;
+        runs.push(StateRun {
+            state,
+            index: stated[start].0,
+            samples: len,
+            duration: end.timestamp.zip(stated[start].1.timestamp).map(|(end, start)| (end - start).as_seconds_f64()),
+        });
+        start += len;
+    }
+    runs
+}
+
+/// Fraction of samples in the [`LOCKED`] state, out of those carrying a
+/// state; `None` when no sample does.
+pub fn locked_fraction(records: &[Record]) -> Option<f64> {
+    let states: Vec<u8> = records.iter().filter_map(|r| r.servo_state).collect();
+    if states.is_empty() {
+        return None;
+    }
+    let locked = states.iter().filter(|state| **state == LOCKED).count();
+    Some(locked as f64 / states.len() as f64)
+}
+
+/// Counts samples per servo state, ordered by state.
+pub fn state_counts(records: &[Record]) -> Vec<(u8, usize)> {
+    let mut counts: Vec<(u8, usize)> = Vec::new();
+    for state in records.iter().filter_map(|r| r.servo_state) {
+        match counts.iter_mut().find(|(s, _)| *s == state) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((state, 1)),
+        }
+    }
+    counts.sort();
+    counts
+}
@@ -0,0 +1,31 @@
+//! Unicode block sparklines, for a glance at a series' shape in the
+//! `--quiet` summary table.
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Draws `data` as at most `width` block characters, each the mean of an
+/// equal share of the samples, scaled from the lowest to the highest. A
+/// flat series is drawn along the bottom.
+pub fn sparkline(data: &[f64], width: usize) -> String {
+    if data.is_empty() || width == 0 {
+        return String::new();
+    }
+
+    let columns = width.min(data.len());
+    let means: Vec<f64> = (0..columns)
+        .map(|column| {
+            let bucket = &data[column * data.len() / columns..(column + 1) * data.len() / columns];
+            bucket.iter().sum::<f64>() / bucket.len() as f64
+        })
+        .collect();
+
+    let low = means.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = means.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    means
+        .iter()
+        .map(|mean| {
+            let level = if high > low { (mean - low) / (high - low) } else { 0.0 };
+            BARS[((level * (BARS.len() - 1) as f64).round() as usize).min(BARS.len() - 1)]
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+//! Amplitude spectrum of the offset series, for finding the period of a
+//! cyclic disturbance such as a thermal cycle or a fight with NTP.
+
+use std::f64::consts::PI;
+
+/// Returns `(frequency in Hz, amplitude)` for each positive frequency of
+/// `data` sampled every `interval` seconds, lowest first. The mean is
+/// removed and the series zero-padded to a power of two, so a sine of
+/// amplitude `a` shows up as a peak of roughly `a` at its frequency.
+pub fn amplitude_spectrum(data: &[f64], interval: f64) -> Vec<(f64, f64)> {
+    let n = data.len();
+    if n < 2 || !interval.is_finite() || interval <= 0.0 {
+        return Vec::new();
+    }
+
+    let mean = data.iter().sum::<f64>() / n as f64;
+    let size = n.next_power_of_two();
+    let mut re: Vec<f64> = data.iter().map(|x| x - mean).collect();
+    re.resize(size, 0.0);
+    let mut im = vec![0.0; size];
+    fft(&mut re, &mut im);
+
+    let resolution = 1.0 / (size as f64 * interval);
+    (1..=size / 2)
+        .map(|k| (k as f64 * resolution, 2.0 * re[k].hypot(im[k]) / n as f64))
+        .collect()
+}
+
+/// The strongest `(frequency, amplitude)` of a spectrum.
+pub fn peak(spectrum: &[(f64, f64)]) -> Option<(f64, f64)> {
+    spectrum.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// In-place iterative radix-2 FFT; the length must be a power of two.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
@@ -0,0 +1,536 @@
+use statrs::distribution::{ContinuousCDF, StudentsT};
+use statrs::statistics::{Data, Distribution};
+use std::collections::VecDeque;
+
+/// Median of unsorted samples; NaN when empty.
+pub fn median(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Root mean square of the samples, the usual PTP accuracy figure since
+/// positive and negative offsets don't cancel out as they do in the mean.
+pub fn rms(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let sum_of_squares: f64 = data.iter().map(|x| x * x).sum();
+    (sum_of_squares / data.len() as f64).sqrt()
+}
+
+/// Sample standard deviation over each `window`-sized run of consecutive
+/// samples. The result has one value per window end, so it is empty when
+/// there are fewer samples than the window.
+pub fn rolling_std_dev(data: &[f64], window: usize) -> Vec<f64> {
+    if window < 2 || data.len() < window {
+        return Vec::new();
+    }
+
+    data.windows(window)
+        .map(|values| {
+            let mean = values.iter().sum::<f64>() / window as f64;
+            let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (window - 1) as f64;
+            variance.sqrt()
+        })
+        .collect()
+}
+
+/// Mean of each `window`-sized run of consecutive samples, one value per
+/// window end like [`rolling_std_dev`]; empty when there are fewer samples
+/// than the window.
+pub fn moving_average(data: &[f64], window: usize) -> Vec<f64> {
+    if window == 0 || data.len() < window {
+        return Vec::new();
+    }
+
+    let mut sum: f64 = data[..window].iter().sum();
+    let mut averages = Vec::with_capacity(data.len() - window + 1);
+    averages.push(sum / window as f64);
+    for i in window..data.len() {
+        sum += data[i] - data[i - window];
+        averages.push(sum / window as f64);
+    }
+    averages
+}
+
+/// Normalized autocorrelation of `data` at lags `0..=max_lag`, or up to
+/// the series' length: 1 at lag 0, then how much each sample still
+/// resembles the ones `lag` before it. Empty for a constant series.
+pub fn autocorrelation(data: &[f64], max_lag: usize) -> Vec<f64> {
+    let n = data.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    let mean = data.iter().sum::<f64>() / n as f64;
+    let deviations: Vec<f64> = data.iter().map(|x| x - mean).collect();
+    let variance: f64 = deviations.iter().map(|d| d * d).sum();
+    if variance == 0.0 {
+        return Vec::new();
+    }
+
+    (0..=max_lag.min(n - 1))
+        .map(|lag| deviations[..n - lag].iter().zip(&deviations[lag..]).map(|(a, b)| a * b).sum::<f64>() / variance)
+        .collect()
+}
+
+/// The first lag at which an autocorrelation falls below 1/e, the
+/// series' time constant in samples; `None` if it stays above.
+pub fn decorrelation_lag(autocorrelation: &[f64]) -> Option<usize> {
+    autocorrelation.iter().position(|r| *r < (-1.0_f64).exp())
+}
+
+/// Overlapping Allan deviation of a time-error series sampled every `tau0`
+/// seconds, evaluated at octave-spaced averaging times. Offsets are taken
+/// in nanoseconds, so the returned deviation is a fractional frequency.
+pub fn allan_deviation(offsets: &[f64], tau0: f64) -> Vec<(f64, f64)> {
+    let phase: Vec<f64> = offsets.iter().map(|x| x * 1e-9).collect();
+    let n = phase.len();
+    let mut result = Vec::new();
+
+    let mut m = 1;
+    while 2 * m < n {
+        let tau = m as f64 * tau0;
+        let terms = n - 2 * m;
+        let sum: f64 = (0..terms)
+            .map(|i| (phase[i + 2 * m] - 2.0 * phase[i + m] + phase[i]).powi(2))
+            .sum();
+        let variance = sum / (2.0 * tau * tau * terms as f64);
+        result.push((tau, variance.sqrt()));
+        m *= 2;
+    }
+
+    result
+}
+
+/// Maximum Time Interval Error: for each window of `n` consecutive
+/// samples, the largest peak-to-peak offset seen in any such window.
+/// Windows longer than the series are skipped.
+pub fn mtie(offsets: &[f64], windows: &[usize]) -> Vec<(usize, f64)> {
+    windows
+        .iter()
+        .filter(|&&n| n >= 1 && n <= offsets.len())
+        .map(|&n| (n, max_window_range(offsets, n)))
+        .collect()
+}
+
+/// Largest `max - min` over every `n`-sample window, using monotonic
+/// deques of indices so each window size is a single pass.
+fn max_window_range(data: &[f64], n: usize) -> f64 {
+    let mut maxima: VecDeque<usize> = VecDeque::new();
+    let mut minima: VecDeque<usize> = VecDeque::new();
+    let mut worst = 0.0_f64;
+
+    for (i, &x) in data.iter().enumerate() {
+        while maxima.back().is_some_and(|&j| data[j] <= x) {
+            maxima.pop_back();
+        }
+        maxima.push_back(i);
+        while minima.back().is_some_and(|&j| data[j] >= x) {
+            minima.pop_back();
+        }
+        minima.push_back(i);
+
+        if i + 1 >= n {
+            let start = i + 1 - n;
+            while maxima.front().is_some_and(|&j| j < start) {
+                maxima.pop_front();
+            }
+            while minima.front().is_some_and(|&j| j < start) {
+                minima.pop_front();
+            }
+            worst = worst.max(data[maxima[0]] - data[minima[0]]);
+        }
+    }
+
+    worst
+}
+
+/// Window sizes 2, 4, 8, ... up to the series length, the default MTIE
+/// observation windows.
+pub fn octave_windows(len: usize) -> Vec<usize> {
+    std::iter::successors(Some(2_usize), |n| n.checked_mul(2))
+        .take_while(|&n| n <= len)
+        .collect()
+}
+
+/// Fraction of samples with |value| at most `bound`, e.g. the share of
+/// time a clock stayed within spec. NaN when empty.
+pub fn fraction_within(data: &[f64], bound: f64) -> f64 {
+    let within = data.iter().filter(|x| x.abs() <= bound).count();
+    within as f64 / data.len() as f64
+}
+
+/// Indices of the samples more than `sigma` standard deviations from the
+/// mean, both as printed in the stats block. Empty when fewer than two
+/// samples give no spread.
+pub fn beyond_sigma(data: &[f64], sigma: f64) -> Vec<usize> {
+    let stats = Data::new(data.to_vec());
+    let (Some(mean), Some(std_dev)) = (stats.mean(), stats.std_dev()) else {
+        return Vec::new();
+    };
+    (0..data.len()).filter(|&i| (data[i] - mean).abs() > sigma * std_dev).collect()
+}
+
+pub fn abs_values(data: &[f64]) -> Vec<f64> {
+    data.iter().map(|x| x.abs()).collect()
+}
+
+/// Mean and standard deviation of `data` with each value counted in
+/// proportion to its weight, as `(mean, std_dev)`. Both are NaN when the
+/// weights sum to zero.
+pub fn weighted_mean_std_dev(data: &[f64], weights: &[f64]) -> (f64, f64) {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return (f64::NAN, f64::NAN);
+    }
+
+    let mean = data.iter().zip(weights).map(|(x, w)| x * w).sum::<f64>() / total;
+    let variance = data.iter().zip(weights).map(|(x, w)| w * (x - mean).powi(2)).sum::<f64>() / total;
+    (mean, variance.sqrt())
+}
+
+/// Least-squares fit of `y = slope * x + intercept`, returning
+/// `(slope, intercept, r²)`. A perfectly flat `y` is a perfect fit (r² of
+/// 1); with fewer than two distinct `x` values every result is NaN.
+pub fn linear_regression(x: &[f64], y: &[f64]) -> (f64, f64, f64) {
+    let n = x.len().min(y.len());
+    let (x, y) = (&x[..n], &y[..n]);
+    let mean_x = x.iter().sum::<f64>() / n as f64;
+    let mean_y = y.iter().sum::<f64>() / n as f64;
+
+    let mut sxx = 0.0;
+    let mut sxy = 0.0;
+    let mut syy = 0.0;
+    for (xi, yi) in x.iter().zip(y) {
+        sxx += (xi - mean_x) * (xi - mean_x);
+        sxy += (xi - mean_x) * (yi - mean_y);
+        syy += (yi - mean_y) * (yi - mean_y);
+    }
+
+    if n < 2 || sxx == 0.0 {
+        return (f64::NAN, f64::NAN, f64::NAN);
+    }
+
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let r_squared = if syy == 0.0 { 1.0 } else { (sxy * sxy / (sxx * syy)).min(1.0) };
+    (slope, intercept, r_squared)
+}
+
+/// Whether a least-squares slope fitted with this r² over `n` points is
+/// nonzero at confidence `level`, by the t-test on the correlation with
+/// n − 2 degrees of freedom.
+pub fn slope_is_significant(r_squared: f64, n: usize, level: f64) -> bool {
+    if n < 3 || r_squared.is_nan() {
+        return false;
+    }
+    if r_squared >= 1.0 {
+        return true;
+    }
+    let Ok(t) = StudentsT::new(0.0, 1.0, (n - 2) as f64) else {
+        return false;
+    };
+    let statistic = (r_squared * (n - 2) as f64 / (1.0 - r_squared)).sqrt();
+    statistic > t.inverse_cdf(1.0 - (1.0 - level) / 2.0)
+}
+
+/// Pearson correlation of the sample-to-sample changes of two series,
+/// which ignores slow common trends and picks out steps taken together.
+pub fn delta_correlation(x: &[f64], y: &[f64]) -> f64 {
+    let deltas = |data: &[f64]| -> Vec<f64> { data.windows(2).map(|pair| pair[1] - pair[0]).collect() };
+    pearson(&deltas(x), &deltas(y))
+}
+
+/// Two-sided Student's t confidence interval for the mean at `level`
+/// (e.g. 0.95), as `(low, high)`. Both ends are NaN with fewer than two
+/// samples.
+pub fn confidence_interval(data: &Data<Vec<f64>>, level: f64) -> (f64, f64) {
+    let n = data.len();
+    t_interval(data.mean().unwrap_or(f64::NAN), data.std_dev().unwrap_or(f64::NAN), n, level)
+}
+
+/// [`confidence_interval`] from a sample mean, sample standard deviation
+/// and count, for statistics accumulated without keeping the samples.
+pub fn t_interval(mean: f64, std_dev: f64, n: usize, level: f64) -> (f64, f64) {
+    let Ok(t) = StudentsT::new(0.0, 1.0, n.saturating_sub(1) as f64) else {
+        return (f64::NAN, f64::NAN);
+    };
+    let half_width = t.inverse_cdf(1.0 - (1.0 - level) / 2.0) * std_dev / (n as f64).sqrt();
+    (mean - half_width, mean + half_width)
+}
+
+/// Central moments `(m2, m3, m4)` as sums of powered deviations.
+fn central_moment_sums(data: &[f64]) -> (f64, f64, f64) {
+    let mean = data.iter().sum::<f64>() / data.len() as f64;
+    data.iter().fold((0.0, 0.0, 0.0), |(m2, m3, m4), x| {
+        let d = x - mean;
+        (m2 + d * d, m3 + d * d * d, m4 + d * d * d * d)
+    })
+}
+
+/// Skewness from moment sums; NaN for fewer than two distinct values.
+fn skewness_of(n: usize, m2: f64, m3: f64) -> f64 {
+    if n < 2 || m2 == 0.0 { f64::NAN } else { (n as f64).sqrt() * m3 / m2.powf(1.5) }
+}
+
+/// Excess kurtosis from moment sums; NaN for fewer than two distinct values.
+fn kurtosis_of(n: usize, m2: f64, m4: f64) -> f64 {
+    if n < 2 || m2 == 0.0 { f64::NAN } else { n as f64 * m4 / (m2 * m2) - 3.0 }
+}
+
+/// Sample skewness (g1): positive when the right tail is longer, as with
+/// path delay under queueing. NaN for fewer than two distinct values.
+pub fn skewness(data: &[f64]) -> f64 {
+    let (m2, m3, _) = central_moment_sums(data);
+    skewness_of(data.len(), m2, m3)
+}
+
+/// Sample excess kurtosis (g2): 0 for Gaussian noise, positive for heavy
+/// tails. NaN for fewer than two distinct values.
+pub fn kurtosis(data: &[f64]) -> f64 {
+    let (m2, _, m4) = central_moment_sums(data);
+    kurtosis_of(data.len(), m2, m4)
+}
+
+/// `data[i] - data[i - 1]` for every sample after the first.
+pub fn first_difference(data: &[f64]) -> Vec<f64> {
+    data.windows(2).map(|pair| pair[1] - pair[0]).collect()
+}
+
+/// Fewest samples on either side of a changepoint.
+pub const MIN_CHANGEPOINT_SEGMENT: usize = 10;
+
+/// Largest scaled CUSUM expected from noise alone: the 99.9th percentile
+/// of the Kolmogorov distribution, which a Brownian bridge's maximum
+/// follows.
+const CHANGEPOINT_CRITICAL: f64 = 1.95;
+
+/// Indices at which the mean of `data` shifts, each the first sample at
+/// the new level, in order. Binary segmentation on the CUSUM: a segment's
+/// largest cumulative deviation from its own mean marks a shift when noise
+/// as large as the segment's standard deviation would hardly reach it, and
+/// the two sides are then searched in turn. Measured against the whole
+/// segment's spread, slow wander is not taken for a series of steps.
+pub fn detect_changepoints(data: &[f64]) -> Vec<usize> {
+    let mut changepoints = Vec::new();
+    split_at_changepoints(data, 0, &mut changepoints);
+    changepoints
+}
+
+fn split_at_changepoints(data: &[f64], start: usize, changepoints: &mut Vec<usize>) {
+    let n = data.len();
+    if n < 2 * MIN_CHANGEPOINT_SEGMENT {
+        return;
+    }
+
+    let mean = data.iter().sum::<f64>() / n as f64;
+    let std_dev = (data.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / n as f64).sqrt();
+    let mut cusum = 0.0;
+    let (mut split, mut largest) = (0, 0.0);
+    for (i, value) in data[..n - MIN_CHANGEPOINT_SEGMENT].iter().enumerate() {
+        cusum += value - mean;
+        if i + 1 >= MIN_CHANGEPOINT_SEGMENT && f64::abs(cusum) > largest {
+            (split, largest) = (i + 1, f64::abs(cusum));
+        }
+    }
+    // NaN for a constant segment, which has no shift.
+    let score = largest / (std_dev * (n as f64).sqrt());
+    if score.is_nan() || score <= CHANGEPOINT_CRITICAL {
+        return;
+    }
+
+    split_at_changepoints(&data[..split], start, changepoints);
+    changepoints.push(start + split);
+    split_at_changepoints(&data[split..], start + split, changepoints);
+}
+
+/// Sign changes between consecutive samples. Zeros carry no sign, so
+/// `-1, 0, 1` is one crossing.
+pub fn zero_crossings(data: &[f64]) -> usize {
+    let mut crossings = 0;
+    let mut last_negative = None;
+    for value in data.iter().filter(|value| **value != 0.0) {
+        let negative = *value < 0.0;
+        if last_negative.is_some_and(|last| last != negative) {
+            crossings += 1;
+        }
+        last_negative = Some(negative);
+    }
+    crossings
+}
+
+/// Pearson correlation coefficient of paired samples; NaN with fewer than
+/// two pairs or when either series is constant.
+pub fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len().min(y.len());
+    let (x, y) = (&x[..n], &y[..n]);
+    let mean_x = x.iter().sum::<f64>() / n as f64;
+    let mean_y = y.iter().sum::<f64>() / n as f64;
+
+    let mut sxx = 0.0;
+    let mut sxy = 0.0;
+    let mut syy = 0.0;
+    for (xi, yi) in x.iter().zip(y) {
+        sxx += (xi - mean_x) * (xi - mean_x);
+        sxy += (xi - mean_x) * (yi - mean_y);
+        syy += (yi - mean_y) * (yi - mean_y);
+    }
+
+    if n < 2 || sxx == 0.0 || syy == 0.0 {
+        return f64::NAN;
+    }
+    (sxy / (sxx * syy).sqrt()).clamp(-1.0, 1.0)
+}
+
+/// Running mean, variance (Welford's method, extended to the third and
+/// fourth moments), RMS and extremes, for series too long to keep in memory.
+#[derive(Debug, Clone)]
+pub struct OnlineStats {
+    count: usize,
+    mean: f64,
+    m2: f64,
+    m3: f64,
+    m4: f64,
+    sum_of_squares: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for OnlineStats {
+    fn default() -> Self {
+        OnlineStats {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            m3: 0.0,
+            m4: 0.0,
+            sum_of_squares: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl OnlineStats {
+    pub fn push(&mut self, value: f64) {
+        let previous = self.count as f64;
+        self.count += 1;
+        let n = self.count as f64;
+        let delta = value - self.mean;
+        let delta_n = delta / n;
+        let term = delta * delta_n * previous;
+        self.mean += delta_n;
+        self.m4 += term * delta_n * delta_n * (n * n - 3.0 * n + 3.0) + 6.0 * delta_n * delta_n * self.m2
+            - 4.0 * delta_n * self.m3;
+        self.m3 += term * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term;
+        self.sum_of_squares += value * value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// NaN when empty, matching the batch statistics.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 { f64::NAN } else { self.mean }
+    }
+
+    pub fn rms(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { (self.sum_of_squares / self.count as f64).sqrt() }
+    }
+
+    /// Sample standard deviation, NaN with fewer than two values.
+    pub fn std_dev(&self) -> f64 {
+        if self.count < 2 { f64::NAN } else { (self.m2 / (self.count - 1) as f64).sqrt() }
+    }
+
+    pub fn skewness(&self) -> f64 {
+        skewness_of(self.count, self.m2, self.m3)
+    }
+
+    pub fn kurtosis(&self) -> f64 {
+        kurtosis_of(self.count, self.m2, self.m4)
+    }
+
+    pub fn min(&self) -> f64 {
+        if self.count == 0 { f64::NAN } else { self.min }
+    }
+
+    pub fn max(&self) -> f64 {
+        if self.count == 0 { f64::NAN } else { self.max }
+    }
+}
+
+/// A uniform random sample of at most `capacity` of the values pushed
+/// (Vitter's algorithm R), for order statistics of series too long to
+/// keep. The generator has a fixed seed, so a log always gives the same
+/// sample.
+#[derive(Debug, Clone)]
+pub struct Reservoir<T> {
+    capacity: usize,
+    seen: usize,
+    items: Vec<T>,
+    state: u64,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize) -> Reservoir<T> {
+        Reservoir { capacity, seen: 0, items: Vec::new(), state: 0x5eed }
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            self.items.push(item);
+            return;
+        }
+        let slot = (self.next_random() % self.seen as u64) as usize;
+        if slot < self.capacity {
+            self.items[slot] = item;
+        }
+    }
+
+    /// The sample, in no particular order.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// How many values were pushed.
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    /// Whether the sample holds fewer values than were pushed, so that
+    /// statistics of it are estimates.
+    pub fn is_sampled(&self) -> bool {
+        self.seen > self.items.len()
+    }
+
+    fn next_random(&mut self) -> u64 {
+        split_mix(&mut self.state)
+    }
+}
+
+/// SplitMix64: a small, fast generator whose output depends only on the
+/// seed `state` starts from, for results that are the same every run.
+pub(crate) fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
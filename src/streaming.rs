@@ -0,0 +1,128 @@
+//! `--streaming` mode: statistics folded in one record at a time, so a
+//! week-long capture never has to fit in memory.
+
+use crate::record::Record;
+use crate::servo;
+use crate::stats::{self, OnlineStats, Reservoir};
+use crate::summary::{DeviceSummary, Oscillation, Peak, StatBlock, CONFIDENCE_LEVEL};
+
+/// Running statistics for one device's samples.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceStats {
+    samples: usize,
+    offset: OnlineStats,
+    abs_offset: OnlineStats,
+    /// Largest max |offset| reported by pre-aggregated records.
+    reported_max: Option<f64>,
+    delay: OnlineStats,
+    freq: OnlineStats,
+    worst_offset: Option<Peak>,
+    worst_delay: Option<Peak>,
+    zero_crossings: usize,
+    /// Sign of the last nonzero offset.
+    last_negative: Option<bool>,
+    /// Samples carrying a servo state, and how many of those were locked.
+    with_state: usize,
+    locked: usize,
+    /// A random sample of `(offset, delay)` pairs for estimated order
+    /// statistics, for `--reservoir`.
+    reservoir: Option<Reservoir<(f64, Option<f64>)>>,
+}
+
+impl DeviceStats {
+    /// Running statistics that also keep a random sample of `reservoir`
+    /// records, if given, to estimate the median and percentiles from.
+    pub fn new(reservoir: Option<usize>) -> DeviceStats {
+        DeviceStats { reservoir: reservoir.map(Reservoir::new), ..DeviceStats::default() }
+    }
+
+    pub fn push(&mut self, record: &Record) {
+        if let Some(reservoir) = &mut self.reservoir {
+            reservoir.push((record.offset, record.delay));
+        }
+        Peak::keep_larger(&mut self.worst_offset, record.worst_offset(), self.samples, record);
+        if let Some(delay) = record.delay {
+            Peak::keep_larger(&mut self.worst_delay, delay, self.samples, record);
+            self.delay.push(delay);
+        }
+        self.samples += 1;
+        self.offset.push(record.offset);
+        self.abs_offset.push(record.offset.abs());
+        if let Some(max) = record.max_offset {
+            self.reported_max = Some(self.reported_max.map_or(max, |m| m.max(max)));
+        }
+        if record.offset != 0.0 {
+            let negative = record.offset < 0.0;
+            if self.last_negative.is_some_and(|last| last != negative) {
+                self.zero_crossings += 1;
+            }
+            self.last_negative = Some(negative);
+        }
+        if let Some(freq) = record.freq {
+            self.freq.push(freq);
+        }
+        if let Some(state) = record.servo_state {
+            self.with_state += 1;
+            if state == servo::LOCKED {
+                self.locked += 1;
+            }
+        }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// The offsets of the `--reservoir` sample; `None` without one.
+    pub fn sampled_offsets(&self) -> Option<Vec<f64>> {
+        self.reservoir.as_ref().map(|reservoir| reservoir.items().iter().map(|(offset, _)| *offset).collect())
+    }
+
+    pub fn summary(&self, device: &str) -> DeviceSummary {
+        let mut offset = StatBlock::from_online(&self.offset);
+        let mut abs_offset = StatBlock::from_online(&self.abs_offset);
+        if let Some(max) = self.reported_max {
+            abs_offset.max = abs_offset.max.max(max);
+        }
+        let mut delay = (self.delay.count() > 0).then(|| StatBlock::from_online(&self.delay));
+        if let Some(reservoir) = &self.reservoir {
+            let offsets: Vec<f64> = reservoir.items().iter().map(|(offset, _)| *offset).collect();
+            let delays: Vec<f64> = reservoir.items().iter().filter_map(|(_, delay)| *delay).collect();
+            offset = offset.with_order_statistics(&offsets);
+            abs_offset = abs_offset.with_order_statistics(&stats::abs_values(&offsets));
+            delay = delay.map(|delay| delay.with_order_statistics(&delays));
+        }
+
+        DeviceSummary {
+            device: device.to_string(),
+            grandmaster: None,
+            samples: self.samples,
+            sample_interval: None,
+            completeness_pct: None,
+            percentile_sample: self.reservoir.as_ref().filter(|reservoir| reservoir.is_sampled()).map(|reservoir| reservoir.items().len()),
+            offset,
+            offset_mean_ci: stats::t_interval(self.offset.mean(), self.offset.std_dev(), self.samples, CONFIDENCE_LEVEL),
+            abs_offset,
+            abs_offset_integral: None,
+            worst_offset: self.worst_offset.clone(),
+            time_in_spec: None,
+            settling: None,
+            cleaned_offset: None,
+            detrended_offset: None,
+            time_weighted_offset: None,
+            hourly: None,
+            mean_shifts: None,
+            locked_fraction: (self.with_state > 0).then(|| self.locked as f64 / self.with_state as f64),
+            oscillation: Oscillation::new(self.zero_crossings, self.samples, None),
+            offset_rate: None,
+            trend: None,
+            delay,
+            worst_delay: self.worst_delay.clone(),
+            negative_delays: None,
+            delay_offset_correlation: None,
+            offset_delay_level_correlation: None,
+            freq: (self.freq.count() == self.samples).then(|| StatBlock::from_online(&self.freq)),
+            freq_drift: None,
+        }
+    }
+}
@@ -0,0 +1,836 @@
+use chrono::{DateTime, FixedOffset, Timelike};
+use crate::detrend::Detrend;
+use crate::json;
+use crate::record::Record;
+use crate::stats::{self, OnlineStats};
+use crate::timing;
+use statrs::statistics::{Data, Distribution, Max, Median, Min, OrderStatistics};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Summary statistics for one series (offset, delay or freq).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatBlock {
+    pub mean: f64,
+    pub rms: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    /// Shape of the distribution, unchanged by scaling: skewness and
+    /// excess kurtosis (0 for Gaussian noise).
+    pub skewness: f64,
+    pub kurtosis: f64,
+}
+
+impl StatBlock {
+    pub fn from_values(values: &[f64]) -> StatBlock {
+        let mut data = Data::new(values.to_vec());
+
+        StatBlock {
+            mean: data.mean().unwrap_or(f64::NAN),
+            rms: stats::rms(values),
+            median: data.median(),
+            p95: data.percentile(95),
+            p99: data.percentile(99),
+            min: data.min(),
+            max: data.max(),
+            std_dev: data.std_dev().unwrap_or(f64::NAN),
+            skewness: stats::skewness(values),
+            kurtosis: stats::kurtosis(values),
+        }
+    }
+
+    /// Spread between the extremes, a quick stability indicator.
+    pub fn peak_to_peak(&self) -> f64 {
+        self.max - self.min
+    }
+
+    /// The block with the median and percentiles of `sample`, e.g. a
+    /// [`stats::Reservoir`]'s, in place of its own.
+    pub fn with_order_statistics(self, sample: &[f64]) -> StatBlock {
+        let sampled = StatBlock::from_values(sample);
+        StatBlock { median: sampled.median, p95: sampled.p95, p99: sampled.p99, ..self }
+    }
+
+    /// Statistics from a running accumulator. Order statistics need the
+    /// whole series, so the median and percentiles are NaN.
+    pub fn from_online(stats: &OnlineStats) -> StatBlock {
+        StatBlock {
+            mean: stats.mean(),
+            rms: stats.rms(),
+            median: f64::NAN,
+            p95: f64::NAN,
+            p99: f64::NAN,
+            min: stats.min(),
+            max: stats.max(),
+            std_dev: stats.std_dev(),
+            skewness: stats.skewness(),
+            kurtosis: stats.kurtosis(),
+        }
+    }
+
+    /// The same statistics with every value divided by `divisor`, for
+    /// display in a larger unit.
+    pub fn scaled(&self, divisor: f64) -> StatBlock {
+        StatBlock {
+            mean: self.mean / divisor,
+            rms: self.rms / divisor,
+            median: self.median / divisor,
+            p95: self.p95 / divisor,
+            p99: self.p99 / divisor,
+            min: self.min / divisor,
+            max: self.max / divisor,
+            std_dev: self.std_dev / divisor,
+            skewness: self.skewness,
+            kurtosis: self.kurtosis,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .number("mean", self.mean)
+            .number("rms", self.rms)
+            .number("median", self.median)
+            .number("p95", self.p95)
+            .number("p99", self.p99)
+            .number("min", self.min)
+            .number("max", self.max)
+            .number("std_dev", self.std_dev)
+            .number("skewness", self.skewness)
+            .number("kurtosis", self.kurtosis)
+            .finish()
+    }
+}
+
+/// One line of a printed stats block, selectable with `--metrics`.
+#[derive(Debug)]
+pub struct Metric {
+    /// Name on the command line, as in the JSON output.
+    pub name: &'static str,
+    /// Label in the stats block, with `{series}` replaced by the block's
+    /// series name.
+    pub label: &'static str,
+    pub value: fn(&StatBlock) -> f64,
+    /// Needs the whole series, so streamed blocks lack it.
+    pub order_statistic: bool,
+    /// Describes the distribution's shape rather than a value in the
+    /// block's unit, so `--precision` doesn't apply.
+    pub shape: bool,
+}
+
+impl Metric {
+    const fn new(name: &'static str, label: &'static str, value: fn(&StatBlock) -> f64) -> Metric {
+        Metric { name, label, value, order_statistic: false, shape: false }
+    }
+
+    /// The metric called `name`, if there is one.
+    pub fn find(name: &str) -> Option<&'static Metric> {
+        METRICS.iter().find(|metric| metric.name == name)
+    }
+}
+
+/// Every stats block metric, in the default printing order.
+pub const METRICS: &[Metric] = &[
+    Metric::new("mean", "Mean", |b| b.mean),
+    Metric::new("rms", "RMS {series}", |b| b.rms),
+    Metric { order_statistic: true, ..Metric::new("median", "Median", |b| b.median) },
+    Metric { order_statistic: true, ..Metric::new("p95", "p95", |b| b.p95) },
+    Metric { order_statistic: true, ..Metric::new("p99", "p99", |b| b.p99) },
+    Metric::new("min", "Min", |b| b.min),
+    Metric::new("max", "Max", |b| b.max),
+    Metric::new("peak_to_peak", "Peak-to-peak", StatBlock::peak_to_peak),
+    Metric::new("std_dev", "Std Dev", |b| b.std_dev),
+    Metric { shape: true, ..Metric::new("skewness", "Skewness", |b| b.skewness) },
+    Metric { shape: true, ..Metric::new("kurtosis", "Kurtosis (excess)", |b| b.kurtosis) },
+];
+
+/// Linear trend of the frequency adjustment over time. A steady slope while
+/// locked points at oscillator aging or temperature drift.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub ppb_per_hour: f64,
+    pub r_squared: f64,
+}
+
+impl Drift {
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .number("ppb_per_hour", self.ppb_per_hour)
+            .number("r_squared", self.r_squared)
+            .finish()
+    }
+}
+
+/// Offset statistics weighted by the time each sample stands for, for
+/// `--time-weighted`. Unlike the plain mean, a stretch logged at a lower
+/// rate counts for as long as it lasted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeWeighted {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl TimeWeighted {
+    /// `None` unless every record has a timestamp.
+    pub fn from_records(records: &[Record]) -> Option<TimeWeighted> {
+        let weights = timing::sample_weights(records)?;
+        let offsets: Vec<f64> = records.iter().map(|r| r.offset).collect();
+        let (mean, std_dev) = stats::weighted_mean_std_dev(&offsets, &weights);
+        (!mean.is_nan()).then_some(TimeWeighted { mean, std_dev })
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .number("mean", self.mean)
+            .number("std_dev", self.std_dev)
+            .finish()
+    }
+}
+
+/// Samples with a negative path delay, which no real network has: a sign
+/// of broken hardware timestamping or a misconfigured NIC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegativeDelays {
+    pub count: usize,
+    /// The first such sample, with its delay as the value.
+    pub first: Peak,
+}
+
+impl NegativeDelays {
+    /// `None` when no delay is below zero.
+    pub fn from_records(records: &[Record]) -> Option<NegativeDelays> {
+        let negative = |r: &Record| r.delay.filter(|delay| *delay < 0.0);
+        let (index, record, delay) = records.iter().enumerate().find_map(|(index, r)| Some((index, r, negative(r)?)))?;
+        let count = records[index..].iter().filter_map(negative).count();
+        Some(NegativeDelays { count, first: Peak { value: delay, index, timestamp: record.timestamp } })
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new().integer("count", self.count).raw("first", self.first.to_json()).finish()
+    }
+}
+
+/// A step in the offset's mean, found by [`stats::detect_changepoints`],
+/// e.g. after a config change or a grandmaster switch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeanShift {
+    /// The first sample at the new level.
+    pub index: usize,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    /// Mean offset from the previous shift, or the start, to this one.
+    pub before: f64,
+    /// Mean offset from this shift to the next one, or the end.
+    pub after: f64,
+}
+
+impl MeanShift {
+    /// Every shift in the offsets of `records`, in log order.
+    pub fn find(records: &[Record]) -> Vec<MeanShift> {
+        let offsets: Vec<f64> = records.iter().map(|r| r.offset).collect();
+        let changepoints = stats::detect_changepoints(&offsets);
+        let bounds: Vec<usize> = std::iter::once(0).chain(changepoints.iter().copied()).chain(std::iter::once(offsets.len())).collect();
+        let means: Vec<f64> = bounds.windows(2).map(|pair| offsets[pair[0]..pair[1]].iter().sum::<f64>() / (pair[1] - pair[0]) as f64).collect();
+        changepoints
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| MeanShift { index, timestamp: records[index].timestamp, before: means[i], after: means[i + 1] })
+            .collect()
+    }
+
+    /// `at 2024-05-01T12:00:00-04:00`, or `at sample N` without a timestamp.
+    pub fn location(&self) -> String {
+        match self.timestamp {
+            Some(timestamp) => format!("at {}", timestamp.to_rfc3339()),
+            None => format!("at sample {}", self.index),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .integer("index", self.index)
+            .raw("timestamp", self.timestamp.map_or("null".to_string(), |t| json::string(&t.to_rfc3339())))
+            .number("before", self.before)
+            .number("after", self.after)
+            .finish()
+    }
+}
+
+/// Offset statistics of one clock hour, for `--hourly`, to show
+/// time-of-day patterns such as sync worsening under business-hours load.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourlyStats {
+    /// The start of the hour, in the UTC offset of its first sample.
+    pub start: DateTime<FixedOffset>,
+    pub samples: usize,
+    pub mean: f64,
+    pub max_abs: f64,
+}
+
+impl HourlyStats {
+    /// One bucket per hour that has samples, in time order. Records
+    /// without a timestamp are left out.
+    pub fn buckets(records: &[Record]) -> Vec<HourlyStats> {
+        let mut hours: BTreeMap<DateTime<FixedOffset>, (usize, f64, f64)> = BTreeMap::new();
+        for record in records {
+            let Some(start) = record.timestamp.and_then(|t| t.with_minute(0)?.with_second(0)?.with_nanosecond(0)) else {
+                continue;
+            };
+            let (samples, sum, max_abs) = hours.entry(start).or_insert((0, 0.0, 0.0));
+            *samples += 1;
+            *sum += record.offset;
+            *max_abs = max_abs.max(record.offset.abs());
+        }
+        hours
+            .into_iter()
+            .map(|(start, (samples, sum, max_abs))| HourlyStats { start, samples, mean: sum / samples as f64, max_abs })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .string("start", &self.start.to_rfc3339())
+            .integer("samples", self.samples)
+            .number("mean", self.mean)
+            .number("max_abs", self.max_abs)
+            .finish()
+    }
+}
+
+/// How long the offset took to settle within `--settle-ns` after the log
+/// started, as in ptp4l converging after a restart or failover.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settling {
+    pub bound: f64,
+    /// `None` when the offset was still outside the bound at the end.
+    pub seconds: Option<f64>,
+}
+
+impl Settling {
+    /// `None` unless every record has a timestamp.
+    pub fn from_records(records: &[Record], bound: f64) -> Option<Settling> {
+        if records.is_empty() || records.iter().any(|r| r.timestamp.is_none()) {
+            return None;
+        }
+        Some(Settling { bound, seconds: timing::settling_time(records, bound) })
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .number("bound_ns", self.bound)
+            .raw("seconds", self.seconds.map_or("null".to_string(), json::number))
+            .finish()
+    }
+}
+
+/// Where a series peaked, so the worst sample can be found in other logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peak {
+    pub value: f64,
+    /// Position of the sample in the device's records.
+    pub index: usize,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+}
+
+impl Peak {
+    /// The record with the largest `value`, the first one on ties.
+    pub fn largest(records: &[Record], value: impl Fn(&Record) -> Option<f64>) -> Option<Peak> {
+        let mut peak = None;
+        for (index, record) in records.iter().enumerate() {
+            if let Some(value) = value(record) {
+                Peak::keep_larger(&mut peak, value, index, record);
+            }
+        }
+        peak
+    }
+
+    /// The `n` records with the largest `value`, largest first and in log
+    /// order on ties. Records without a value are passed over.
+    pub fn largest_n(records: &[Record], value: impl Fn(&Record) -> Option<f64>, n: usize) -> Vec<Peak> {
+        let mut peaks: Vec<Peak> = records
+            .iter()
+            .enumerate()
+            .filter_map(|(index, record)| Some(Peak { value: value(record)?, index, timestamp: record.timestamp }))
+            .collect();
+        peaks.sort_by(|a, b| b.value.total_cmp(&a.value));
+        peaks.truncate(n);
+        peaks
+    }
+
+    /// Replaces `peak` with the sample at `index` if that is larger.
+    pub fn keep_larger(peak: &mut Option<Peak>, value: f64, index: usize, record: &Record) {
+        if peak.as_ref().is_none_or(|peak| value > peak.value) {
+            *peak = Some(Peak { value, index, timestamp: record.timestamp });
+        }
+    }
+
+    /// `at 2024-05-01T12:00:00-04:00`, or `at sample N` without a timestamp.
+    pub fn location(&self) -> String {
+        match self.timestamp {
+            Some(timestamp) => format!("at {}", timestamp.to_rfc3339()),
+            None => format!("at sample {}", self.index),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .number("value", self.value)
+            .integer("index", self.index)
+            .raw("timestamp", self.timestamp.map_or("null".to_string(), |t| json::string(&t.to_rfc3339())))
+            .finish()
+    }
+}
+
+/// Fraction of sample-to-sample steps that must change the offset's sign
+/// before a device counts as oscillating. Noise around a locked servo
+/// flips sign on about half of them; a hunting servo on nearly all.
+pub const OSCILLATION_FRACTION: f64 = 0.8;
+
+/// Fewest samples for which the sign-change fraction is worth judging.
+const OSCILLATION_MIN_SAMPLES: usize = 20;
+
+/// How often the offset changes sign: a servo that is hunting swings
+/// across zero on almost every sample while its mean stays small.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Oscillation {
+    pub zero_crossings: usize,
+    /// `None` without a sample interval to measure time by.
+    pub per_minute: Option<f64>,
+    pub oscillating: bool,
+}
+
+impl Oscillation {
+    pub fn new(zero_crossings: usize, samples: usize, sample_interval: Option<f64>) -> Oscillation {
+        let steps = samples.saturating_sub(1);
+        let per_minute = sample_interval
+            .filter(|interval| *interval > 0.0 && steps > 0)
+            .map(|interval| zero_crossings as f64 / (interval * steps as f64 / 60.0));
+
+        Oscillation {
+            zero_crossings,
+            per_minute,
+            oscillating: samples >= OSCILLATION_MIN_SAMPLES && zero_crossings as f64 > OSCILLATION_FRACTION * steps as f64,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .integer("zero_crossings", self.zero_crossings)
+            .raw("per_minute", self.per_minute.map_or("null".to_string(), json::number))
+            .raw("oscillating", self.oscillating.to_string())
+            .finish()
+    }
+}
+
+/// Statistics of the offset's first difference, how hard the servo is
+/// correcting: a disturbance shows as a spike at the exact sample it hit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetRate {
+    /// In ns/s when the log has a sample interval, else in ns/sample.
+    pub stats: StatBlock,
+    pub per_second: bool,
+    /// The largest |change|, at the sample it led to.
+    pub largest: Option<Peak>,
+}
+
+impl OffsetRate {
+    /// `None` with fewer than two records.
+    pub fn from_records(records: &[Record], sample_interval: Option<f64>) -> Option<OffsetRate> {
+        let offsets: Vec<f64> = records.iter().map(|r| r.offset).collect();
+        let interval = sample_interval.filter(|interval| *interval > 0.0);
+        let rates: Vec<f64> = stats::first_difference(&offsets).iter().map(|step| step / interval.unwrap_or(1.0)).collect();
+        if rates.is_empty() {
+            return None;
+        }
+
+        let mut largest = None;
+        for (index, rate) in rates.iter().enumerate() {
+            Peak::keep_larger(&mut largest, rate.abs(), index + 1, &records[index + 1]);
+        }
+        Some(OffsetRate { stats: StatBlock::from_values(&rates), per_second: interval.is_some(), largest })
+    }
+
+    /// "s" or "sample", what the rate is per.
+    pub fn per(&self) -> &'static str {
+        if self.per_second { "s" } else { "sample" }
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .string("per", self.per())
+            .raw("stats", self.stats.to_json())
+            .raw("largest", self.largest.as_ref().map_or("null".to_string(), Peak::to_json))
+            .finish()
+    }
+}
+
+/// Which way |offset| went over the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrendDirection {
+    Improving,
+    Stable,
+    Degrading,
+}
+
+impl TrendDirection {
+    pub fn label(self) -> &'static str {
+        match self {
+            TrendDirection::Improving => "improving",
+            TrendDirection::Stable => "stable",
+            TrendDirection::Degrading => "degrading",
+        }
+    }
+}
+
+/// Linear trend of |offset| over time, as a one-word verdict: a device
+/// can be within its limits and still be getting worse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trend {
+    pub ns_per_hour: f64,
+    pub r_squared: f64,
+    pub direction: TrendDirection,
+}
+
+impl Trend {
+    /// Fits |offset| against hours since the first timestamped sample. The
+    /// slope counts as a trend when it is at least `min_ns_per_hour` steep
+    /// and nonzero at [`CONFIDENCE_LEVEL`]; a long log makes even a tiny
+    /// slope significant, so the steepness is what decides. `None` with
+    /// fewer than three timestamped samples or all at one time.
+    pub fn from_records(records: &[Record], min_ns_per_hour: f64) -> Option<Trend> {
+        let first = records.iter().find_map(|r| r.timestamp)?;
+        let (hours, abs_offsets): (Vec<f64>, Vec<f64>) = records
+            .iter()
+            .filter_map(|r| Some(((r.timestamp? - first).as_seconds_f64() / 3600.0, r.offset.abs())))
+            .unzip();
+
+        let (slope, _, r_squared) = stats::linear_regression(&hours, &abs_offsets);
+        if hours.len() < 3 || slope.is_nan() {
+            return None;
+        }
+        let direction = match slope.abs() >= min_ns_per_hour && stats::slope_is_significant(r_squared, hours.len(), CONFIDENCE_LEVEL) {
+            false => TrendDirection::Stable,
+            true if slope < 0.0 => TrendDirection::Improving,
+            true => TrendDirection::Degrading,
+        };
+        Some(Trend { ns_per_hour: slope, r_squared, direction })
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .string("direction", self.direction.label())
+            .number("ns_per_hour", self.ns_per_hour)
+            .number("r_squared", self.r_squared)
+            .finish()
+    }
+}
+
+/// What `--top` ranks devices by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankBy {
+    #[default]
+    Rms,
+    MaxOffset,
+    DelayJitter,
+    /// Share of samples locked, lowest first.
+    Lock,
+}
+
+impl RankBy {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RankBy::Rms => "RMS offset",
+            RankBy::MaxOffset => "max |offset|",
+            RankBy::DelayJitter => "delay jitter",
+            RankBy::Lock => "lock percentage",
+        }
+    }
+
+    /// How bad the device looks by this key, higher being worse; `None`
+    /// when the device lacks the value (no servo states for `Lock`, no
+    /// delays or a single sample for `DelayJitter`).
+    fn badness(&self, summary: &DeviceSummary) -> Option<f64> {
+        match self {
+            RankBy::Rms => Some(summary.offset.rms),
+            RankBy::MaxOffset => Some(summary.abs_offset.max),
+            RankBy::DelayJitter => summary.delay_jitter().filter(|jitter| !jitter.is_nan()),
+            RankBy::Lock => summary.locked_fraction.map(|fraction| 1.0 - fraction),
+        }
+    }
+
+    /// The `n` worst devices, worst first. Devices without the value rank
+    /// after every device with it.
+    pub fn worst<'a>(&self, summaries: &'a [DeviceSummary], n: usize) -> Vec<&'a DeviceSummary> {
+        let mut ranked: Vec<&DeviceSummary> = summaries.iter().collect();
+        ranked.sort_by(|a, b| match (self.badness(a), self.badness(b)) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        ranked.truncate(n);
+        ranked
+    }
+}
+
+impl FromStr for RankBy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rms" => Ok(RankBy::Rms),
+            "max" => Ok(RankBy::MaxOffset),
+            "jitter" => Ok(RankBy::DelayJitter),
+            "lock" => Ok(RankBy::Lock),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Confidence level of [`DeviceSummary::offset_mean_ci`].
+pub const CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Correlation of delay and offset steps at or above which the device is
+/// flagged for path asymmetry.
+pub const ASYMMETRY_CORRELATION: f64 = 0.7;
+
+/// Fewest samples [`DeviceSummary::offset_delay_level_correlation`] is
+/// computed from; any two points correlate perfectly.
+pub const MIN_CORRELATION_SAMPLES: usize = 10;
+
+/// Below this [`DeviceSummary::completeness_pct`] enough log lines are
+/// missing to bias the statistics.
+pub const LOW_COMPLETENESS_PCT: f64 = 95.0;
+
+/// Everything computed for one device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSummary {
+    pub device: String,
+    /// Clock identity of the grandmaster the device followed: the last one
+    /// its log shows selected, or the config file's.
+    pub grandmaster: Option<String>,
+    pub samples: usize,
+    /// Median seconds between samples, when the log has timestamps.
+    pub sample_interval: Option<f64>,
+    /// Samples logged as a percentage of those expected over the
+    /// timestamp span (see [`timing::completeness_pct`]); `None` without
+    /// timestamps or in `--streaming` mode.
+    pub completeness_pct: Option<f64>,
+    /// With `--reservoir`, the size of the random sample the medians and
+    /// percentiles were estimated from; `None` when they are exact.
+    pub percentile_sample: Option<usize>,
+    pub offset: StatBlock,
+    /// [`CONFIDENCE_LEVEL`] confidence interval of the mean offset as
+    /// `(low, high)`; NaN with fewer than two samples.
+    pub offset_mean_ci: (f64, f64),
+    /// Statistics of |offset|, the worst-case error regardless of sign.
+    pub abs_offset: StatBlock,
+    /// Time integral of |offset| in ns·s (see [`timing::integrate_abs`]);
+    /// `None` without timestamps or in `--streaming` mode.
+    pub abs_offset_integral: Option<f64>,
+    /// The sample with the largest |offset|; `None` without samples.
+    pub worst_offset: Option<Peak>,
+    /// Fraction of samples with |offset| within `--max-offset-ns`, when set.
+    pub time_in_spec: Option<f64>,
+    /// Settling time within `--settle-ns`, when set and the log has
+    /// timestamps.
+    pub settling: Option<Settling>,
+    /// Offset statistics after `--drop-outliers`, with the number removed.
+    pub cleaned_offset: Option<(StatBlock, usize)>,
+    /// Offset statistics after `--detrend`, with what was subtracted.
+    pub detrended_offset: Option<(StatBlock, Detrend)>,
+    /// Time-weighted offset mean and std dev, for `--time-weighted` on a
+    /// log with timestamps.
+    pub time_weighted_offset: Option<TimeWeighted>,
+    /// Offset mean and max |offset| per clock hour, for `--hourly`.
+    pub hourly: Option<Vec<HourlyStats>>,
+    /// Fraction of samples in servo state s2; `None` when the log format
+    /// carries no servo state.
+    pub locked_fraction: Option<f64>,
+    pub oscillation: Oscillation,
+    /// `None` with a single sample or in `--streaming` mode.
+    pub offset_rate: Option<OffsetRate>,
+    /// Steps in the offset's mean; `None` in `--streaming` mode.
+    pub mean_shifts: Option<Vec<MeanShift>>,
+    /// Trend of |offset| judged against `--trend-ns-per-hour`; `None`
+    /// without timestamps or in `--streaming` mode.
+    pub trend: Option<Trend>,
+    /// `None` when no record carries a path delay, as with ts2phc; the
+    /// delay statistics below likewise leave out records without one.
+    pub delay: Option<StatBlock>,
+    /// The sample with the longest path delay.
+    pub worst_delay: Option<Peak>,
+    /// `None` when every delay is zero or more, and in `--streaming` mode.
+    pub negative_delays: Option<NegativeDelays>,
+    /// Correlation of the first differences of delay and offset. Offset
+    /// stepping with every delay change means the forward and reverse
+    /// paths became asymmetric. `None` in `--streaming` mode or when
+    /// either series is constant.
+    pub delay_offset_correlation: Option<f64>,
+    /// Correlation of the delay and offset samples themselves: near ±1
+    /// when offset rides on path delay, so the network rather than the
+    /// clock is the main error source. `None` as for
+    /// `delay_offset_correlation`, or with fewer than
+    /// [`MIN_CORRELATION_SAMPLES`].
+    pub offset_delay_level_correlation: Option<f64>,
+    /// `None` when the log format carries no frequency adjustment.
+    pub freq: Option<StatBlock>,
+    /// `None` without both freq values and timestamps.
+    pub freq_drift: Option<Drift>,
+}
+
+impl DeviceSummary {
+    /// Std dev of path delay. High jitter with a steady offset is a
+    /// healthy servo riding out a noisy network.
+    /// `None` without delays.
+    pub fn delay_jitter(&self) -> Option<f64> {
+        self.delay.as_ref().map(|delay| delay.std_dev)
+    }
+
+    /// Whether offset follows delay, step by step or in level, closely
+    /// enough to suspect asymmetry.
+    pub fn asymmetry_suspected(&self) -> bool {
+        [self.delay_offset_correlation, self.offset_delay_level_correlation].iter().flatten().any(|r| r.abs() >= ASYMMETRY_CORRELATION)
+    }
+
+    /// Mean path delay over mean |offset|, how many times larger the
+    /// network's delay is than the clock's error. Per-sample ratios are
+    /// avoided as offsets near zero would swamp them. `None` without
+    /// delays or when the mean |offset| is zero.
+    pub fn delay_offset_ratio(&self) -> Option<f64> {
+        let delay = self.delay.as_ref()?;
+        (self.abs_offset.mean > 0.0).then(|| delay.mean / self.abs_offset.mean)
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .string("device", &self.device)
+            .raw("grandmaster", self.grandmaster.as_deref().map_or("null".to_string(), json::string))
+            .integer("samples", self.samples)
+            .raw("sample_interval_s", self.sample_interval.map_or("null".to_string(), json::number))
+            .raw("completeness_pct", self.completeness_pct.map_or("null".to_string(), json::number))
+            .raw("percentile_sample_size", self.percentile_sample.map_or("null".to_string(), |size| size.to_string()))
+            .raw("offset", self.offset.to_json())
+            .raw("offset_mean_ci", json::array([json::number(self.offset_mean_ci.0), json::number(self.offset_mean_ci.1)]))
+            .raw("abs_offset", self.abs_offset.to_json())
+            .raw("abs_offset_integral_ns_s", self.abs_offset_integral.map_or("null".to_string(), json::number))
+            .raw("worst_offset", self.worst_offset.as_ref().map_or("null".to_string(), Peak::to_json))
+            .raw("time_in_spec", self.time_in_spec.map_or("null".to_string(), json::number))
+            .raw("settling", self.settling.as_ref().map_or("null".to_string(), Settling::to_json))
+            .raw(
+                "cleaned_offset",
+                self.cleaned_offset.as_ref().map_or("null".to_string(), |(block, removed)| {
+                    json::Object::new()
+                        .integer("outliers_removed", *removed)
+                        .raw("stats", block.to_json())
+                        .finish()
+                }),
+            )
+            .raw(
+                "detrended_offset",
+                self.detrended_offset.as_ref().map_or("null".to_string(), |(block, detrend)| {
+                    let (method, window) = match detrend {
+                        Detrend::Linear => ("linear", "null".to_string()),
+                        Detrend::MovingAverage(window) => ("moving_average", window.to_string()),
+                    };
+                    json::Object::new()
+                        .string("method", method)
+                        .raw("window", window)
+                        .raw("stats", block.to_json())
+                        .finish()
+                }),
+            )
+            .raw("time_weighted_offset", self.time_weighted_offset.as_ref().map_or("null".to_string(), TimeWeighted::to_json))
+            .raw("hourly", self.hourly.as_ref().map_or("null".to_string(), |hours| json::array(hours.iter().map(HourlyStats::to_json))))
+            .raw("locked_fraction", self.locked_fraction.map_or("null".to_string(), json::number))
+            .raw("oscillation", self.oscillation.to_json())
+            .raw("offset_rate", self.offset_rate.as_ref().map_or("null".to_string(), OffsetRate::to_json))
+            .raw("mean_shifts", self.mean_shifts.as_ref().map_or("null".to_string(), |shifts| json::array(shifts.iter().map(MeanShift::to_json))))
+            .raw("trend", self.trend.as_ref().map_or("null".to_string(), Trend::to_json))
+            .raw("delay", self.delay.as_ref().map_or("null".to_string(), StatBlock::to_json))
+            .raw("worst_delay", self.worst_delay.as_ref().map_or("null".to_string(), Peak::to_json))
+            .raw("negative_delays", self.negative_delays.as_ref().map_or("null".to_string(), NegativeDelays::to_json))
+            .raw("delay_offset_correlation", self.delay_offset_correlation.map_or("null".to_string(), json::number))
+            .raw("offset_delay_level_correlation", self.offset_delay_level_correlation.map_or("null".to_string(), json::number))
+            .raw("delay_offset_ratio", self.delay_offset_ratio().map_or("null".to_string(), json::number))
+            .raw("freq", self.freq.as_ref().map_or("null".to_string(), StatBlock::to_json))
+            .raw("freq_drift", self.freq_drift.as_ref().map_or("null".to_string(), Drift::to_json))
+            .finish()
+    }
+}
+
+/// Every device of a run taken together: the numbers to report upward,
+/// e.g. "fleet RMS offset 45 ns, worst node 210 ns".
+#[derive(Debug, Clone, PartialEq)]
+pub struct FleetSummary {
+    pub devices: usize,
+    pub samples: usize,
+    /// RMS offset over every device's samples together.
+    pub pooled_rms: f64,
+    /// Median of the devices' own RMS offsets, which one bad device
+    /// barely moves.
+    pub median_rms: f64,
+    /// The device with the largest RMS offset.
+    pub worst_device: String,
+    pub worst_rms: f64,
+    /// The largest |offset| of any device, and whose it was.
+    pub max_abs_offset: f64,
+    pub max_abs_offset_device: String,
+}
+
+impl FleetSummary {
+    /// `None` without any devices.
+    pub fn from_summaries(summaries: &[DeviceSummary]) -> Option<FleetSummary> {
+        let worst = RankBy::Rms.worst(summaries, 1).into_iter().next()?;
+        let farthest = RankBy::MaxOffset.worst(summaries, 1).into_iter().next()?;
+        let samples: usize = summaries.iter().map(|s| s.samples).sum();
+        let squares: f64 = summaries.iter().map(|s| s.offset.rms.powi(2) * s.samples as f64).sum();
+        let rms: Vec<f64> = summaries.iter().map(|s| s.offset.rms).collect();
+
+        Some(FleetSummary {
+            devices: summaries.len(),
+            samples,
+            pooled_rms: (squares / samples as f64).sqrt(),
+            median_rms: stats::median(&rms),
+            worst_device: worst.device.clone(),
+            worst_rms: worst.offset.rms,
+            max_abs_offset: farthest.abs_offset.max,
+            max_abs_offset_device: farthest.device.clone(),
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .integer("device_count", self.devices)
+            .integer("samples", self.samples)
+            .number("pooled_rms", self.pooled_rms)
+            .number("median_rms", self.median_rms)
+            .string("worst_device", &self.worst_device)
+            .number("worst_rms", self.worst_rms)
+            .number("max_abs_offset", self.max_abs_offset)
+            .string("max_abs_offset_device", &self.max_abs_offset_device)
+            .finish()
+    }
+}
+
+/// Version of the `--json` document layout. Bumped whenever a field is
+/// renamed, removed or changes meaning; adding a field doesn't bump it.
+pub const SCHEMA_VERSION: usize = 2;
+
+/// The `--json` document: every device's summary under `devices` and
+/// their [`FleetSummary`] under `fleet`, with the schema version and how
+/// and when the run was made.
+pub fn to_json_document(summaries: &[DeviceSummary], arguments: &[String], generated_at: DateTime<FixedOffset>) -> String {
+    json::Object::new()
+        .integer("schema_version", SCHEMA_VERSION)
+        .string("tool", env!("CARGO_PKG_NAME"))
+        .string("version", env!("CARGO_PKG_VERSION"))
+        .string("generated_at", &generated_at.to_rfc3339())
+        .raw("arguments", json::array(arguments.iter().map(|argument| json::string(argument))))
+        .raw("fleet", FleetSummary::from_summaries(summaries).map_or("null".to_string(), |fleet| fleet.to_json()))
+        .raw("devices", json::array(summaries.iter().map(DeviceSummary::to_json)))
+        .finish()
+}
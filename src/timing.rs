@@ -0,0 +1,217 @@
+use chrono::{DateTime, FixedOffset};
+use crate::record::Record;
+use crate::stats;
+
+/// A timestamp as fractional seconds since the Unix epoch.
+pub fn seconds(timestamp: &DateTime<FixedOffset>) -> f64 {
+    timestamp.timestamp() as f64 + f64::from(timestamp.timestamp_subsec_nanos()) * 1e-9
+}
+
+/// Seconds between consecutive timestamped samples. Empty when the log
+/// carries no timestamps.
+pub fn intervals(records: &[Record]) -> Vec<f64> {
+    records
+        .windows(2)
+        .filter_map(|pair| match (pair[0].timestamp, pair[1].timestamp) {
+            (Some(a), Some(b)) => Some((b - a).as_seconds_f64()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The typical logging interval, robust to the odd gap.
+pub fn median_interval(records: &[Record]) -> Option<f64> {
+    let intervals = intervals(records);
+    (!intervals.is_empty()).then(|| stats::median(&intervals))
+}
+
+/// Timestamped samples as a percentage of the `(span / interval) + 1`
+/// expected between the first and last timestamp at one sample per
+/// `interval` seconds. Dropped log lines pull it below 100; `None` when
+/// fewer than two samples have distinct timestamps.
+pub fn completeness_pct(records: &[Record], interval: f64) -> Option<f64> {
+    let timestamps: Vec<_> = records.iter().filter_map(|r| r.timestamp).collect();
+    let span = (*timestamps.last()? - *timestamps.first()?).as_seconds_f64();
+    if span <= 0.0 || interval <= 0.0 {
+        return None;
+    }
+    Some(100.0 * timestamps.len() as f64 / (span / interval + 1.0))
+}
+
+/// Time integral of |offset| in ns·s by the trapezoidal rule: the error
+/// accumulated over the capture, which grows with both how far and how
+/// long a clock was off. Pairs of samples lacking a timestamp add nothing.
+pub fn integrate_abs(records: &[Record]) -> f64 {
+    records
+        .windows(2)
+        .filter_map(|pair| {
+            let seconds = (pair[1].timestamp? - pair[0].timestamp?).as_seconds_f64();
+            Some(seconds * (pair[0].offset.abs() + pair[1].offset.abs()) / 2.0)
+        })
+        .sum()
+}
+
+/// Seconds each sample stands for: the time until the next one, or the
+/// median interval for the last. `None` unless every sample has a
+/// timestamp.
+pub fn sample_weights(records: &[Record]) -> Option<Vec<f64>> {
+    let median = median_interval(records)?;
+    let timestamps = records.iter().map(|r| r.timestamp).collect::<Option<Vec<_>>>()?;
+    let mut weights: Vec<f64> = timestamps.windows(2).map(|pair| (pair[1] - pair[0]).as_seconds_f64()).collect();
+    weights.push(median);
+    Some(weights)
+}
+
+/// Seconds from the first sample until |offset| came within `bound` for
+/// good, i.e. to the sample after the last one outside it; zero when none
+/// was. `None` when the last sample is still outside or the samples
+/// involved have no timestamps.
+pub fn settling_time(records: &[Record], bound: f64) -> Option<f64> {
+    let settled = match records.iter().rposition(|r| r.worst_offset() > bound) {
+        Some(last_outside) => records.get(last_outside + 1)?,
+        None => records.first()?,
+    };
+    Some((settled.timestamp? - records.first()?.timestamp?).as_seconds_f64())
+}
+
+/// The offset series linearly interpolated onto a grid every `interval`
+/// seconds from the first sample to the last. `None` unless every sample
+/// has a timestamp.
+pub fn resample(records: &[Record], interval: f64) -> Option<Vec<f64>> {
+    let first = records.first()?.timestamp?;
+    let times = records
+        .iter()
+        .map(|r| Some((r.timestamp? - first).as_seconds_f64()))
+        .collect::<Option<Vec<f64>>>()?;
+    let span = times.last().copied().unwrap_or(0.0);
+
+    let mut next = 1;
+    let steps = (span / interval).floor() as usize;
+    let resampled = (0..=steps)
+        .map(|step| {
+            let t = step as f64 * interval;
+            while next < times.len() - 1 && times[next] < t {
+                next += 1;
+            }
+            let Some(&end) = times.get(next) else {
+                return records[0].offset;
+            };
+            let (start, a, b) = (times[next - 1], records[next - 1].offset, records[next].offset);
+            if end <= start {
+                return b;
+            }
+            a + (b - a) * ((t - start) / (end - start)).clamp(0.0, 1.0)
+        })
+        .collect();
+    Some(resampled)
+}
+
+/// Whether intervals stray far from the median often enough to suggest
+/// dropped log lines: more than 5% of them exceed twice the median.
+pub fn is_irregular(records: &[Record]) -> bool {
+    let intervals = intervals(records);
+    let Some(median) = median_interval(records) else {
+        return false;
+    };
+
+    let long = intervals.iter().filter(|i| **i > 2.0 * median).count();
+    long as f64 > 0.05 * intervals.len() as f64
+}
+
+/// Consecutive timestamped samples whose timestamps don't increase, as in
+/// rotated logs concatenated in the wrong order or overlapping.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OutOfOrder {
+    /// Samples with the same timestamp as the sample before.
+    pub duplicates: usize,
+    /// Samples timestamped before the sample before.
+    pub backward: usize,
+}
+
+impl OutOfOrder {
+    pub fn count(records: &[Record]) -> OutOfOrder {
+        let mut order = OutOfOrder::default();
+        for pair in records.windows(2) {
+            match (pair[0].timestamp, pair[1].timestamp) {
+                (Some(a), Some(b)) if b == a => order.duplicates += 1,
+                (Some(a), Some(b)) if b < a => order.backward += 1,
+                _ => {}
+            }
+        }
+        order
+    }
+
+    pub fn total(&self) -> usize {
+        self.duplicates + self.backward
+    }
+}
+
+/// The start of each device's series dropped for `--skip-warmup` or
+/// `--skip-warmup-secs`, while ptp4l is still converging.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Warmup {
+    Samples(usize),
+    /// Seconds from the first sample, by wall clock, else by the daemon's
+    /// monotonic timestamps.
+    Seconds(f64),
+}
+
+impl Warmup {
+    /// How many of the leading `records` are warmup. By seconds, nothing is
+    /// skipped in a log with neither kind of timestamp.
+    pub fn count(&self, records: &[Record]) -> usize {
+        let secs = match *self {
+            Warmup::Samples(n) => return n.min(records.len()),
+            Warmup::Seconds(secs) => secs,
+        };
+        let Some(first) = records.first() else {
+            return 0;
+        };
+        if let Some(start) = first.timestamp {
+            records.iter().take_while(|r| r.timestamp.is_some_and(|t| (t - start).as_seconds_f64() < secs)).count()
+        } else if let Some(start) = first.internal_ts {
+            records.iter().take_while(|r| r.internal_ts.is_some_and(|t| t - start < secs)).count()
+        } else {
+            0
+        }
+    }
+}
+
+/// Puts records in timestamp order for `--sort-by-time`, keeping samples
+/// with equal timestamps in log order. Samples without a timestamp go
+/// first.
+pub fn sort_by_time(records: &mut [Record]) {
+    records.sort_by_key(|r| r.timestamp);
+}
+
+/// A stretch with no samples, e.g. while ptp4l was restarting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    /// Index of the first sample after the gap.
+    pub index: usize,
+    /// Timestamps of the samples either side of the gap.
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+impl Gap {
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start).as_seconds_f64()
+    }
+}
+
+/// Every interval longer than `factor` times the median interval.
+pub fn gaps(records: &[Record], factor: f64) -> Vec<Gap> {
+    let Some(median) = median_interval(records) else {
+        return Vec::new();
+    };
+
+    records
+        .windows(2)
+        .enumerate()
+        .filter_map(|(index, pair)| {
+            let (start, end) = (pair[0].timestamp?, pair[1].timestamp?);
+            ((end - start).as_seconds_f64() > factor * median).then_some(Gap { index: index + 1, start, end })
+        })
+        .collect()
+}
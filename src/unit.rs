@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+/// Time unit of offset and delay values. Values are held in nanoseconds
+/// whatever unit a log was written in (`--input-unit`); `--unit` only
+/// changes how they are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unit {
+    #[default]
+    Ns,
+    Us,
+    Ms,
+}
+
+impl Unit {
+    /// Nanoseconds per unit.
+    pub fn scale(&self) -> f64 {
+        match self {
+            Unit::Ns => 1.0,
+            Unit::Us => 1e3,
+            Unit::Ms => 1e6,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Unit::Ns => "ns",
+            Unit::Us => "µs",
+            Unit::Ms => "ms",
+        }
+    }
+
+    /// Converts nanosecond values into this unit.
+    pub fn apply(&self, values: &[f64]) -> Vec<f64> {
+        values.iter().map(|v| v / self.scale()).collect()
+    }
+}
+
+impl FromStr for Unit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ns" => Ok(Unit::Ns),
+            "us" | "µs" => Ok(Unit::Us),
+            "ms" => Ok(Unit::Ms),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use ptp_stats::bundle;
+use ptp_stats::cli::Args;
+use ptp_stats::parser::{Parser, TimeWindow};
+use ptp_stats::progress::Progress;
+use ptp_stats::summary::DeviceSummary;
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn index_lists_each_plot_under_the_device_with_the_longest_prefix() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/valid.log");
+    let log = Parser::new(None, TimeWindow::default()).parse_file(&path, &Progress::hidden()).unwrap();
+    let summary = ptp_stats::summarize(&log.records);
+    let summaries: Vec<DeviceSummary> = ["gm", "gm-2"].iter().map(|name| DeviceSummary { device: name.to_string(), ..summary.clone() }).collect();
+
+    let dir = std::env::temp_dir().join(format!("ptp-stats-bundle-{}", std::process::id()));
+    let plots = ptp_stats::plot::PlotOptions { out_dir: bundle::plot_dir(&dir), ..Args::default().plot_options };
+    fs::create_dir_all(&plots.out_dir).unwrap();
+    for file in ["gm-offset.png", "gm-2-offset.png", "comparison-offset.png", "notes.txt"] {
+        fs::write(plots.out_dir.join(file), "").unwrap();
+    }
+
+    bundle::write(&dir, "{}", &summaries, &plots, 2).unwrap();
+    let index = fs::read_to_string(dir.join("index.html")).unwrap();
+    let summary_json = fs::read_to_string(dir.join(bundle::SUMMARY_FILE)).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(summary_json, "{}\n");
+    let position = |text: &str| index.find(text).unwrap_or_else(|| panic!("{} missing from index", text));
+    assert!(position("<h2 id=\"gm\">") < position("src=\"plots/gm-offset.png\""));
+    assert!(position("src=\"plots/gm-offset.png\"") < position("<h2 id=\"gm-2\">"));
+    assert!(position("<h2 id=\"gm-2\">") < position("src=\"plots/gm-2-offset.png\""));
+    assert!(position("<h2 id=\"fleet\">") < position("src=\"plots/comparison-offset.png\""));
+    assert!(!index.contains("notes.txt"));
+}
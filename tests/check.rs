@@ -0,0 +1,39 @@
+use ptp_stats::check::{Level, RunStatus, Thresholds};
+use ptp_stats::parser::{Parser, TimeWindow};
+
+#[test]
+fn mask_margin_is_what_max_offset_leaves_under_the_mask() {
+    let records = Parser::new(None, TimeWindow::default())
+        .parse_reader("ptp4l[1.0]: master offset -340 s2 freq +1 path delay 600\nptp4l[2.0]: master offset 120 s2 freq +1 path delay 600\n".as_bytes())
+        .unwrap()
+        .records;
+    let summary = ptp_stats::summarize(&records);
+
+    let within = Thresholds { mask_ns: Some(1000.0), ..Thresholds::default() };
+    assert_eq!(within.mask_margin(&summary), Some(660.0));
+    assert_eq!(within.mask_level(&summary), Some(Level::Good));
+    assert!(within.violations(&summary).is_empty());
+
+    let broken = Thresholds { mask_ns: Some(300.0), ..Thresholds::default() };
+    assert_eq!(broken.mask_margin(&summary), Some(-40.0));
+    assert_eq!(broken.violations(&summary), ["max |offset| 340.00 ns is 40.00 ns over the 300.00 ns mask"]);
+    assert_eq!(Thresholds::default().mask_margin(&summary), None);
+}
+
+#[test]
+fn run_status_names_the_limits_each_device_exceeded() {
+    let records = Parser::new(None, TimeWindow::default())
+        .parse_reader("ptp4l[1.0]: master offset -340 s2 freq +1 path delay 600\n".as_bytes())
+        .unwrap()
+        .records;
+    let summary = ptp_stats::summarize(&records);
+    let limits = Thresholds { max_offset_ns: Some(100.0), max_delay_ns: Some(1000.0), ..Thresholds::default() };
+    let breaches = limits.breaches(&summary);
+    assert_eq!(breaches.iter().map(|b| b.threshold).collect::<Vec<_>>(), ["max_offset_ns"]);
+
+    let mut status = RunStatus { devices: vec![("alpha".to_string(), Vec::new())], unreadable_logs: Vec::new(), nonconforming_logs: 0, unfinished_logs: Vec::new() };
+    assert!(status.success());
+    status.devices.push(("beta".to_string(), breaches));
+    assert!(!status.success());
+    assert!(status.to_json().starts_with(r#"{"success":false,"devices":[{"device":"alpha","passed":true,"breaches":[]},{"device":"beta","passed":false,"breaches":[{"threshold":"max_offset_ns","#));
+}
@@ -0,0 +1,22 @@
+use ptp_stats::cli::Args;
+
+fn parse(args: &[&str]) -> Result<Args, String> {
+    Args::parse(args.iter().map(|arg| arg.to_string())).map_err(|err| err.to_string())
+}
+
+fn names(args: &Args) -> Vec<&str> {
+    args.machines.iter().map(|machine| machine.name.as_str()).collect()
+}
+
+#[test]
+fn only_and_skip_filter_devices_by_exact_name() {
+    let logs = ["gm=a.log", "gm-2=b.log", "edge=c.log"];
+    let with = |flags: &[&str]| parse(&[flags, &logs[..]].concat());
+    assert_eq!(names(&with(&["--only", "gm", "--only", "edge"]).unwrap()), ["gm", "edge"]);
+    assert_eq!(names(&with(&["--skip", "gm-2"]).unwrap()), ["gm", "edge"]);
+    assert_eq!(names(&with(&["--only", "gm-2", "--skip", "edge", "--limit", "1"]).unwrap()), ["gm-2"]);
+
+    let error = with(&["--skip", "GM"]).unwrap_err();
+    assert!(error.contains("--skip 'GM'") && error.contains("gm, gm-2, edge"), "{}", error);
+    assert!(with(&["--only", "gm", "--skip", "gm"]).is_err());
+}
@@ -0,0 +1,21 @@
+//! Fixtures shared by the integration tests.
+
+use chrono::{DateTime, Duration};
+use ptp_stats::record::{Record, Source};
+
+/// A locked ptp4l sample `millis` ms after 2024-05-01T12:00:00-04:00 with
+/// an `offset` and a 600 ns path delay. Tests change the other fields with
+/// struct update syntax.
+pub fn record(millis: i64, offset: f64) -> Record {
+    let start = DateTime::parse_from_rfc3339("2024-05-01T12:00:00-04:00").unwrap();
+    Record {
+        source: Source::Ptp4l,
+        timestamp: Some(start + Duration::milliseconds(millis)),
+        internal_ts: None,
+        offset,
+        max_offset: None,
+        servo_state: Some(2),
+        freq: None,
+        delay: Some(600.0),
+    }
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use common::record;
+use ptp_stats::correlation::aligned_series;
+use ptp_stats::record::Record;
+
+#[test]
+fn aligns_offsets_on_epoch_buckets() {
+    let series = vec![
+        ("a".to_string(), vec![record(0, 10.0), record(400, 20.0), record(1000, 30.0)]),
+        ("b".to_string(), vec![record(1200, -5.0), record(2000, -7.0), Record { timestamp: None, ..record(0, 99.0) }]),
+    ];
+    let aligned = aligned_series(&series, std::time::Duration::from_secs(1));
+
+    // 2024-05-01T16:00:00Z
+    let start = 1_714_579_200_000_000_000;
+    let rows: Vec<(i64, Vec<Option<f64>>)> = aligned.into_iter().collect();
+    assert_eq!(rows, [
+        (start, vec![Some(15.0), None]),
+        (start + 1_000_000_000, vec![Some(30.0), Some(-5.0)]),
+        (start + 2_000_000_000, vec![None, Some(-7.0)]),
+    ]);
+}
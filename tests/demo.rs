@@ -0,0 +1,36 @@
+use ptp_stats::demo::{self, SAMPLES};
+use ptp_stats::record::Record;
+use ptp_stats::stats::beyond_sigma;
+use ptp_stats::summary::{Settling, Trend, TrendDirection};
+
+fn parse(name: &str) -> Vec<Record> {
+    demo::records(name).unwrap()
+}
+
+#[test]
+fn demo_logs_parse_completely_and_show_their_drift_and_spike() {
+    for name in demo::device_names() {
+        assert_eq!(parse(name).len(), SAMPLES, "{}", name);
+    }
+    assert_eq!(demo::log("demo-stable"), demo::log("demo-stable"));
+    assert!(demo::log("nope").is_none() && demo::records("nope").is_none());
+
+    let trend = |name: &str| Trend::from_records(&parse(name), ptp_stats::cli::Args::default().trend_ns_per_hour).map(|trend| trend.direction);
+    assert_eq!(trend("demo-drifting"), Some(TrendDirection::Degrading));
+    assert_eq!(trend("demo-spike"), Some(TrendDirection::Stable));
+    let spike = ptp_stats::summarize(&parse("demo-spike"));
+    assert_eq!(spike.worst_offset.map(|peak| peak.index), Some(400));
+    assert!(spike.abs_offset.max > 2000.0);
+    let stable = ptp_stats::summarize(&parse("demo-stable"));
+    assert!(stable.abs_offset.max < 600.0 && stable.offset.std_dev < spike.offset.std_dev);
+}
+
+#[test]
+fn demo_noise_is_fixed_by_its_seed() {
+    // Exact values of the seeded noise; they change with the generator.
+    let spike: Vec<f64> = parse("demo-spike").iter().map(|record| record.offset).collect();
+    assert_eq!(beyond_sigma(&spike, 5.0), vec![400]);
+    assert_eq!(beyond_sigma(&spike, 4.0), vec![0, 400]);
+    let settling = |name: &str| Settling::from_records(&parse(name), 100.0).unwrap().seconds;
+    assert_eq!((settling("demo-stable"), settling("demo-spike")), (Some(3.0), Some(401.0)));
+}
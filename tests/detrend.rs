@@ -0,0 +1,31 @@
+use ptp_stats::detrend::Detrend;
+
+#[test]
+fn linear_fit_removes_a_ramp_and_keeps_the_jitter() {
+    // Symmetric about the middle, so uncorrelated with the sample index.
+    let jitter = [3.0, -3.0, -3.0, 3.0, 3.0, -3.0, -3.0, 3.0];
+    let data: Vec<f64> = jitter.iter().enumerate().map(|(i, j)| 100.0 + 10.0 * i as f64 + j).collect();
+    let residual = Detrend::Linear.apply(&data);
+    assert!(residual.iter().zip(jitter).all(|(r, j)| (r - j).abs() < 1e-9));
+}
+
+#[test]
+fn linear_fit_leaves_fewer_than_two_samples_as_they_are() {
+    assert_eq!(Detrend::Linear.apply(&[42.0]), [42.0]);
+    assert_eq!(Detrend::Linear.apply(&[]), Vec::<f64>::new());
+}
+
+#[test]
+fn moving_average_shortens_its_window_at_the_ends() {
+    let residual = Detrend::MovingAverage(3).apply(&[0.0, 3.0, 0.0, 3.0]);
+    assert_eq!(residual, [-1.5, 2.0, -2.0, 1.5]);
+    assert_eq!(Detrend::MovingAverage(3).apply(&[]), Vec::<f64>::new());
+}
+
+#[test]
+fn parses_linear_or_a_window() {
+    assert_eq!("linear".parse(), Ok(Detrend::Linear));
+    assert_eq!("120".parse(), Ok(Detrend::MovingAverage(120)));
+    assert!("1".parse::<Detrend>().is_err());
+    assert!("quadratic".parse::<Detrend>().is_err());
+}
@@ -0,0 +1,15 @@
+use ptp_stats::error::PtpError;
+use std::error::Error;
+use std::io;
+
+#[test]
+fn errors_read_as_one_line_messages() {
+    let err = PtpError::parse("logs/beta.log", io::Error::new(io::ErrorKind::NotFound, "no such file"));
+    assert_eq!(err.to_string(), "failed to parse logs/beta.log: no such file");
+    // `main` returning the error prints it with Debug.
+    assert_eq!(format!("{:?}", err), err.to_string());
+    assert!(err.source().is_some());
+
+    let plot = PtpError::Plot { plot: "offset plot".to_string(), source: "drawing area too small".into() };
+    assert_eq!(plot.to_string(), "failed to write offset plot: drawing area too small");
+}
@@ -0,0 +1,38 @@
+#![cfg(unix)]
+
+use ptp_stats::follow::Follower;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Reads from `follower` until `expected` has arrived or a few seconds pass.
+fn read_until(follower: &mut Follower, expected: &str) -> String {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut text = String::new();
+    while !text.contains(expected) && Instant::now() < deadline {
+        text += &follower.read_new().unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+    text
+}
+
+#[test]
+fn keeps_reading_a_fifo_after_its_writer_closes() {
+    let path = std::env::temp_dir().join(format!("ptp-stats-follow-{}.fifo", std::process::id()));
+    let _ = fs::remove_file(&path);
+    assert!(Command::new("mkfifo").arg(&path).status().unwrap().success());
+
+    let mut follower = Follower::open(&path).unwrap();
+    assert_eq!(follower.read_new().unwrap(), "");
+
+    OpenOptions::new().write(true).open(&path).unwrap().write_all(b"first\nsecond").unwrap();
+    assert_eq!(read_until(&mut follower, "first"), "first\n");
+
+    // A new writer carries on where the last one left off.
+    OpenOptions::new().write(true).open(&path).unwrap().write_all(b" line\nthird\n").unwrap();
+    assert_eq!(read_until(&mut follower, "third"), "second line\nthird\n");
+
+    fs::remove_file(&path).unwrap();
+}
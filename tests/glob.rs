@@ -0,0 +1,24 @@
+use ptp_stats::glob::expand;
+use std::fs;
+
+#[test]
+fn expands_classes_and_any_depth_of_directories() {
+    let dir = std::env::temp_dir().join(format!("ptp-stats-glob-{}", std::process::id()));
+    for file in ["ptp1.log", "ptp2.log", "ptpa.log", ".hidden.log", "site/ptp3.log", "site/rack/ptp4.log"] {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+    let names = |pattern: &str| -> Vec<String> {
+        let found = expand(&format!("{}/{}", dir.display(), pattern)).unwrap();
+        found.iter().map(|path| path.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/")).collect()
+    };
+
+    assert_eq!(names("ptp[0-9].log"), ["ptp1.log", "ptp2.log"]);
+    assert_eq!(names("*.log"), ["ptp1.log", "ptp2.log", "ptpa.log"]);
+    assert_eq!(names("**/ptp?.log"), ["ptp1.log", "ptp2.log", "ptpa.log", "site/ptp3.log", "site/rack/ptp4.log"]);
+    assert_eq!(names("site/*"), ["site/ptp3.log"]);
+    assert!(names("none-*.log").is_empty());
+    assert!(expand("logs/ptp[0-9.log").unwrap_err().contains("invalid --glob"));
+    fs::remove_dir_all(&dir).unwrap();
+}
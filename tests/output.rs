@@ -0,0 +1,54 @@
+use std::fs;
+use std::process::Command;
+
+/// Runs ptp-stats with `--no-plots` and `flags` on a log of `lines`, and
+/// returns what it printed.
+fn run(name: &str, lines: &[String], flags: &[&str]) -> String {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("ptp-stats-output-{}-{}.log", name, std::process::id()));
+    fs::write(&path, lines.join("\n") + "\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ptp-stats")).current_dir(&dir).arg("--no-plots").args(flags).arg(&path).output().unwrap();
+    fs::remove_file(&path).unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn daemons_sharing_a_syslog_second_are_in_order() {
+    let lines: Vec<String> = (0..60)
+        .flat_map(|i| {
+            let time = format!("2024-05-01T12:00:{:02}-04:00 beta", i);
+            [
+                format!("{} ptp4l[812]: ptp4l[{}.567]: master offset {} s2 freq +1345 path delay 678", time, 1234 + i, i % 5),
+                format!("{} phc2sys[900]: phc2sys[{}.600]: CLOCK_REALTIME phc offset {} s2 freq -83542 delay 1032", time, 1234 + i, i % 7),
+            ]
+        })
+        .collect();
+    let mixed = run("mixed", &lines, &[]);
+    assert!(mixed.contains("-ptp4l") && mixed.contains("-phc2sys"), "{}", mixed);
+    assert!(!mixed.contains("Out-of-order"), "{}", mixed);
+
+    // A ptp4l sample a second late is still caught.
+    let mut late = lines.clone();
+    late.swap(10, 12);
+    let output = run("late", &late, &[]);
+    assert_eq!(output.matches("Out-of-order timestamps: 1 (0 duplicated, 1 backward)").count(), 1, "{}", output);
+}
+
+#[test]
+fn no_data_says_why_the_lines_held_no_samples() {
+    let lines = ["ptp4l[1234.567]: master offset -12 s2 freq +1345 path delay 678".to_string(), "ptp4l[1235.567]: master offset 8 s2 freq +1350 path delay 681".to_string()];
+    let excluded = run("excluded", &lines, &["--exclude", "offset"]);
+    assert!(excluded.contains("No data: all 2 lines were excluded by --exclude."), "{}", excluded);
+    assert!(!excluded.contains("failed to parse"), "{}", excluded);
+
+    let events = ["ptp4l[1236.000]: selected best master clock 001122.fffe.334455".to_string(), "ptp4l[1237.000]: clock jumped".to_string()];
+    let output = run("events", &events, &[]);
+    assert!(output.contains("No data: the log holds only events (2 of 2 lines), no samples."), "{}", output);
+}
+
+#[test]
+fn a_lone_sample_is_insufficient_data_in_the_singular() {
+    let lines = ["ptp4l[1234.567]: master offset -12 s2 freq +1345 path delay 678".to_string()];
+    let output = run("single", &lines, &["--min-samples", "2"]);
+    assert!(output.contains("Insufficient data (1 sample); stats and plots need at least 2."), "{}", output);
+}
@@ -0,0 +1,335 @@
+use chrono::DateTime;
+use ptp_stats::event::last_grandmaster;
+use ptp_stats::parser::{InputFormat, LogFormat, Parser, TimeWindow};
+use ptp_stats::progress::Progress;
+use ptp_stats::record::Source;
+use ptp_stats::{ParseError, RecordIter};
+use regex::Regex;
+use std::io::{self, BufReader, Read};
+use std::path::PathBuf;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+#[test]
+fn record_iter_skips_other_lines_and_stops_at_a_read_error() {
+    let log: &[u8] = b"ptp4l[1.0]: master offset 5 s2 freq +1 path delay 600\nnoise\n\xff\nptp4l[2.0]: master offset -7 s2 freq +1 path delay 600\n";
+    let offsets: Vec<f64> = RecordIter::new(log).map(|record| record.unwrap().offset).collect();
+    assert_eq!(offsets, [5.0, -7.0]);
+
+    struct Failing;
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk gone"))
+        }
+    }
+    let reader = "ptp4l[1.0]: master offset 5 s2 freq +1 path delay 600\n".as_bytes().chain(Failing);
+    let mut records = RecordIter::new(BufReader::new(reader));
+    assert_eq!(records.next().unwrap().unwrap().offset, 5.0);
+    assert!(matches!(records.next(), Some(Err(ParseError::Io(_)))));
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn parses_ptp4l_line() {
+    let line = "2024-05-01T12:00:00-04:00 beta ptp4l[812]: ptp4l[1000.567]: master offset -27 s2 freq +1345 path delay 601";
+    let record = ptp_stats::parse_line(line).unwrap();
+
+    assert_eq!(record.source, Source::Ptp4l);
+    assert_eq!(record.timestamp, Some(DateTime::parse_from_rfc3339("2024-05-01T12:00:00-04:00").unwrap()));
+    assert_eq!(record.internal_ts, Some(1000.567));
+    assert_eq!(record.offset, -27.0);
+    assert_eq!(record.servo_state, Some(2));
+    assert_eq!(record.freq, Some(1345.0));
+    assert_eq!(record.delay, Some(601.0));
+    assert_eq!(record.max_offset, None);
+}
+
+#[test]
+fn parses_phc2sys_line() {
+    let line = "2024-05-01T12:00:00-04:00 beta phc2sys[900]: phc2sys[1234.600]: CLOCK_REALTIME phc offset -38 s2 freq -83542 delay 1032";
+    let record = ptp_stats::parse_line(line).unwrap();
+
+    assert_eq!(record.source, Source::Phc2sys);
+    assert_eq!(record.offset, -38.0);
+    assert_eq!(record.freq, Some(-83542.0));
+    assert_eq!(record.delay, Some(1032.0));
+}
+
+#[test]
+fn parses_ts2phc_lines() {
+    let line = "2024-05-01T12:00:00-04:00 beta ts2phc[700]: ts2phc[1000.100]: eth0 master offset 3 s2 freq -12";
+    let record = ptp_stats::parse_line(line).unwrap();
+    assert_eq!(record.source, Source::Ts2phc);
+    assert_eq!((record.offset, record.freq, record.delay), (3.0, Some(-12.0), None));
+
+    // Newer releases name the PHC device and drop "master".
+    let record = ptp_stats::parse_line("ts2phc[1001.100]: /dev/ptp0 offset -2 s2 freq +10").unwrap();
+    assert_eq!((record.source, record.offset, record.servo_state), (Source::Ts2phc, -2.0, Some(2)));
+}
+
+#[test]
+fn parses_summary_line() {
+    let line = "2024-05-01T12:00:10-04:00 beta ptp4l[812]: ptp4l[1240.123]: rms 30 max 60 freq -1990 +/- 19 delay 597 +/- 1";
+    let record = ptp_stats::parse_line(line).unwrap();
+
+    assert_eq!(record.source, Source::Ptp4lSummary);
+    assert_eq!(record.offset, 30.0);
+    assert_eq!(record.max_offset, Some(60.0));
+    assert_eq!(record.servo_state, None);
+    assert_eq!(record.delay, Some(597.0));
+}
+
+#[test]
+fn parses_float_offsets() {
+    let line = "2024-05-01T12:00:00-04:00 beta ptp4l[812]: ptp4l[1000.567]: master offset 1.2345e+03 s2 freq -1.5 path delay 601.25";
+    let record = ptp_stats::parse_line(line).unwrap();
+
+    assert_eq!(record.offset, 1234.5);
+    assert_eq!(record.freq, Some(-1.5));
+    assert_eq!(record.delay, Some(601.25));
+}
+
+#[test]
+fn accepts_utc_and_positive_offsets() {
+    for (timestamp, utc_offset) in [("2024-05-01T12:00:00Z", 0), ("2024-05-01T12:00:00+02:00", 7200)] {
+        let line = format!("{} beta ptp4l[812]: ptp4l[1000.567]: master offset 5 s2 freq +1345 path delay 601", timestamp);
+        let record = ptp_stats::parse_line(&line).unwrap();
+        assert_eq!(record.timestamp.unwrap().offset().local_minus_utc(), utc_offset);
+    }
+}
+
+#[test]
+fn parses_line_without_syslog_prefix() {
+    let record = ptp_stats::parse_line("ptp4l[1241.567]: master offset 4 s2 freq +1351 path delay 680").unwrap();
+
+    assert_eq!(record.timestamp, None);
+    assert_eq!(record.internal_ts, Some(1241.567));
+    assert_eq!(record.offset, 4.0);
+}
+
+#[test]
+fn rejects_non_samples() {
+    assert_eq!(ptp_stats::parse_line(""), None);
+    assert_eq!(ptp_stats::parse_line("something else"), None);
+    assert_eq!(
+        ptp_stats::parse_line("2024-05-01T12:00:01-04:00 beta ptp4l[812]: ptp4l[1001.567]: port 1: UNCALIBRATED to SLAVE"),
+        None
+    );
+    // Matches the pattern, but month 13 doesn't exist.
+    assert_eq!(
+        ptp_stats::parse_line("2024-13-01T12:00:02-04:00 beta ptp4l[812]: ptp4l[1002.567]: master offset 12 s2 freq +1340 path delay 603"),
+        None
+    );
+}
+
+#[test]
+fn parses_valid_fixture() {
+    let log = Parser::new(None, TimeWindow::default())
+        .parse_file(&fixture("valid.log"), &Progress::hidden())
+        .unwrap();
+
+    let offsets: Vec<f64> = log.records.iter().map(|r| r.offset).collect();
+    let delays: Vec<f64> = log.records.iter().filter_map(|r| r.delay).collect();
+    assert_eq!(offsets, [5.0, -27.0, 12.0]);
+    assert_eq!(delays, [601.0, 596.0, 603.0]);
+    assert_eq!(log.total_lines, 3);
+    assert_eq!(log.skipped_lines, 0);
+    assert_eq!(log.parse_failures, 0);
+    assert_eq!(log.first_unparsed, None);
+}
+
+#[test]
+fn counts_malformed_fixture_lines() {
+    let log = Parser::new(None, TimeWindow::default())
+        .parse_file(&fixture("malformed.log"), &Progress::hidden())
+        .unwrap();
+
+    let offsets: Vec<f64> = log.records.iter().map(|r| r.offset).collect();
+    assert_eq!(offsets, [5.0, -3.0]);
+    assert_eq!(log.total_lines, 6);
+    // The port state line, the truncated line and the blank line.
+    assert_eq!(log.skipped_lines, 3);
+    assert_eq!(log.parse_failures, 1);
+    assert_eq!(log.unmatched_examples.len(), 2);
+    assert_eq!(
+        log.first_unparsed,
+        Some((2, "2024-05-01T12:00:01-04:00 beta ptp4l[812]: ptp4l[1001.567]: port 1: UNCALIBRATED to SLAVE on MASTER_CLOCK_SELECTED".to_string()))
+    );
+}
+
+#[test]
+fn parses_mixed_fixture_in_order() {
+    let log = Parser::new(None, TimeWindow::default())
+        .parse_file(&fixture("mixed.log"), &Progress::hidden())
+        .unwrap();
+
+    let sources: Vec<Source> = log.records.iter().map(|r| r.source).collect();
+    assert_eq!(
+        sources,
+        [Source::Ptp4l, Source::Phc2sys, Source::Ptp4l, Source::Ptp4lSummary, Source::Ptp4l]
+    );
+    assert_eq!(log.records[2].servo_state, Some(1));
+}
+
+#[test]
+fn applies_time_window() {
+    let window = TimeWindow {
+        from: Some(DateTime::parse_from_rfc3339("2024-05-01T12:00:01-04:00").unwrap()),
+        to: None,
+    };
+    let log = Parser::new(None, window)
+        .parse_file(&fixture("valid.log"), &Progress::hidden())
+        .unwrap();
+
+    assert_eq!(log.records.len(), 2);
+    assert_eq!(log.total_samples, 3);
+}
+
+#[test]
+fn skips_lines_with_invalid_utf8() {
+    let log = Parser::new(None, TimeWindow::default())
+        .parse_file(&fixture("binary.log"), &Progress::hidden())
+        .unwrap();
+
+    // The first line ends in \r\n; the second is garbage and the last has a
+    // stray byte in the middle.
+    let offsets: Vec<f64> = log.records.iter().map(|r| r.offset).collect();
+    assert_eq!(offsets, [5.0, -27.0]);
+    assert_eq!(log.total_lines, 4);
+    assert_eq!(log.encoding_errors, 2);
+    assert_eq!(log.skipped_lines, 0);
+}
+
+#[test]
+fn ignores_carriage_returns_and_surrounding_whitespace() {
+    let log = Parser::new(None, TimeWindow::default())
+        .parse_file(&fixture("crlf.log"), &Progress::hidden())
+        .unwrap();
+
+    let delays: Vec<f64> = log.records.iter().filter_map(|r| r.delay).collect();
+    assert_eq!(delays, [601.0, 596.0]);
+    assert_eq!(log.events.len(), 1);
+    assert_eq!(last_grandmaster(&log.events), Some("001122.fffe.334455"));
+    assert_eq!(log.first_unparsed, None);
+}
+
+#[test]
+fn end_anchored_pattern_matches_despite_trailing_whitespace() {
+    let pattern = Regex::new(r"off=(?P<offset>-?\d+) dly=(?P<delay>\d+)$").unwrap();
+    let parser = Parser::new(Some(&pattern), TimeWindow::default());
+
+    let record = parser.parse_line("off=-3 dly=700 \r").unwrap();
+    assert_eq!((record.offset, record.delay), (-3.0, Some(700.0)));
+}
+
+#[test]
+fn merges_rotated_parts_oldest_first() {
+    let log = Parser::new(None, TimeWindow::default())
+        .parse_log(&[fixture("valid.log"), fixture("valid.log.1.gz")], &Progress::hidden())
+        .unwrap();
+
+    let offsets: Vec<f64> = log.records.iter().map(|r| r.offset).collect();
+    assert_eq!(offsets, [-40.0, 18.0, 5.0, -27.0, 12.0]);
+    assert_eq!(log.total_lines, 5);
+}
+
+#[test]
+fn fields_after_the_offset_may_come_in_any_order() {
+    let line = "ptp4l[1000.567]: master offset -27 s2 path delay 601 freq +1345";
+    let record = ptp_stats::parse_line(line).unwrap();
+    assert_eq!((record.offset, record.freq, record.delay), (-27.0, Some(1345.0), Some(601.0)));
+
+    let line = "phc2sys[1234.600]: CLOCK_REALTIME phc offset -38 s2 delay 1032 freq -83542";
+    let record = ptp_stats::parse_line(line).unwrap();
+    assert_eq!((record.freq, record.delay), (Some(-83542.0), Some(1032.0)));
+}
+
+#[test]
+fn sample_line_without_a_delay_is_not_a_sample() {
+    assert!(ptp_stats::parse_line("ptp4l[1000.567]: master offset -27 s2 freq +1345").is_none());
+}
+
+#[test]
+fn read_timeout_leaves_the_log_as_read() {
+    let log = Parser::new(None, TimeWindow::default())
+        .with_read_timeout(Some(std::time::Duration::from_secs(10)))
+        .parse_log(&[fixture("valid.log"), fixture("valid.log.1.gz")], &Progress::hidden())
+        .unwrap();
+    assert_eq!(log.records[..2].iter().map(|r| r.offset).collect::<Vec<_>>(), [-40.0, 18.0]);
+}
+
+#[cfg(unix)]
+#[test]
+fn read_timeout_gives_up_on_a_log_that_never_opens() {
+    // Opening a FIFO blocks until a writer shows up, like a stalled mount.
+    let path = std::env::temp_dir().join(format!("ptp-stats-timeout-{}.fifo", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert!(std::process::Command::new("mkfifo").arg(&path).status().unwrap().success());
+
+    let result = Parser::new(None, TimeWindow::default())
+        .with_read_timeout(Some(std::time::Duration::from_millis(200)))
+        .parse_file(&path, &Progress::hidden());
+    assert!(result.is_err_and(|err| err.kind() == std::io::ErrorKind::TimedOut));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn input_unit_scales_offsets_and_delays_to_nanoseconds() {
+    let log = Parser::new(None, TimeWindow::default())
+        .with_input_unit(ptp_stats::unit::Unit::Us)
+        .parse_reader("ptp4l[1.0]: master offset -1.5 s2 freq +1345 path delay 0.6\n".as_bytes())
+        .unwrap();
+    let record = &log.records[0];
+    assert_eq!((record.offset, record.delay, record.freq), (-1500.0, Some(600.0), Some(1345.0)));
+}
+
+#[test]
+fn excluded_lines_are_counted_instead_of_parsed() {
+    let log = Parser::new(None, TimeWindow::default())
+        .with_exclude(Some(Regex::new(r"\bDEBUG\b").unwrap()))
+        .parse_reader("ptp4l[1.0]: master offset 5 s2 freq +1 path delay 600\nDEBUG ptp4l[1.5]: master offset 9999 s2 freq +1 path delay 600\nDEBUG noise\n".as_bytes())
+        .unwrap();
+    assert_eq!(log.records.len(), 1);
+    assert_eq!((log.excluded_lines, log.skipped_lines, log.first_unparsed), (2, 0, None));
+}
+
+#[test]
+fn json_lines_are_read_as_samples_alongside_text_ones() {
+    let parser = Parser::new(None, TimeWindow::default());
+    let record = parser
+        .parse_line(r#"{"source":"phc2sys","timestamp":"2024-05-01T12:00:00Z","offset":-12.5,"state":"s2","freq":-3200,"delay":480}"#)
+        .unwrap();
+    assert_eq!(record.source, Source::Phc2sys);
+    assert_eq!(record.timestamp, Some(DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap()));
+    assert_eq!((record.offset, record.servo_state, record.freq, record.delay), (-12.5, Some(2), Some(-3200.0), Some(480.0)));
+    // A ts2phc sample has no path delay.
+    assert_eq!(parser.parse_line(r#"{"source":"ts2phc","offset":3}"#).unwrap().delay, None);
+    assert!(parser.parse_line(r#"{"offset":3}"#).is_none());
+    assert!(parser.parse_line(r#"{"offset":3,"delay":"600"}"#).is_none());
+    assert!(parser.parse_line(r#"{"event":"port 1: MASTER to SLAVE"}"#).is_none());
+
+    let text = "ptp4l[1000.567]: master offset          5 s2 freq   +1345 path delay       601";
+    assert!(parser.parse_line(text).is_some());
+    assert!(parser.with_input_format(InputFormat::Json).parse_line(text).is_none());
+    let text_only = Parser::new(None, TimeWindow::default()).with_input_format(InputFormat::Text);
+    assert!(text_only.parse_line(r#"{"offset":3,"delay":600}"#).is_none());
+}
+
+#[test]
+fn log_format_presets_read_only_their_own_lines() {
+    let bare = "ptp4l[1000.567]: master offset          5 s2 freq   +1345 path delay       601";
+    let syslog = format!("2024-05-01T12:00:00-04:00 gm ptp4l[812]: {}", bare);
+    let phc2sys = "phc2sys[1234.600]: CLOCK_REALTIME phc offset        -38 s2 freq  -83542 delay   1032";
+    let preset = |format| Parser::new(None, TimeWindow::default()).with_log_format(Some(format));
+
+    assert!(preset(LogFormat::Ptp4lSyslog).parse_line(&syslog).unwrap().timestamp.is_some());
+    assert!(preset(LogFormat::Ptp4lSyslog).parse_line(bare).is_none());
+    assert!(preset(LogFormat::Ptp4lBare).parse_line(bare).is_some());
+    assert!(preset(LogFormat::Ptp4lBare).parse_line(&syslog).is_none());
+    assert_eq!(preset(LogFormat::Phc2sys).parse_line(phc2sys).unwrap().source, Source::Phc2sys);
+    assert!(preset(LogFormat::Phc2sys).parse_line(bare).is_none());
+    assert!(preset(LogFormat::Summary).parse_line(bare).is_none());
+}
@@ -0,0 +1,131 @@
+use ptp_stats::cli::Args;
+use ptp_stats::parser::{Parser, TimeWindow};
+use ptp_stats::plot::{ascii_offset_plot, create_diff_plot, create_histogram, metadata_subtitle, palette_index, BoxStats, Grid, ImageFormat, TimeAxis, XAxis};
+use ptp_stats::progress::Progress;
+use std::path::PathBuf;
+
+#[test]
+fn ascii_chart_spans_the_offset_range_and_time() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/valid.log");
+    let log = Parser::new(None, TimeWindow::default()).parse_file(&path, &Progress::hidden()).unwrap();
+    let options = ptp_stats::plot::PlotOptions { format: ImageFormat::Ascii, ..Args::default().plot_options };
+    let axis = TimeAxis::from_records(&log.records, XAxis::Time);
+    let offsets: Vec<f64> = log.records.iter().map(|r| r.offset).collect();
+
+    let lines = ascii_offset_plot(&options, &axis, &offsets);
+    assert_eq!(lines[0].trim(), "Offset (ns)");
+    assert!(lines[1].trim_start().starts_with("12.00 ┤"));
+    assert!(lines[lines.len() - 3].trim_start().starts_with("-27.00 ┤"));
+    let x_labels: Vec<&str> = lines[lines.len() - 1].split_whitespace().collect();
+    assert_eq!(x_labels, ["12:00:00", "12:00:02"]);
+    // The last sample is the highest, the middle one the lowest.
+    let plotted = |line: &str| line.chars().rev().take_while(|c| *c != '┤').map(|c| c != '\u{2800}').collect::<Vec<_>>();
+    assert!(plotted(&lines[1])[0]);
+    let bottom = plotted(&lines[lines.len() - 3]);
+    assert!(!bottom[0] && bottom[bottom.len() / 2]);
+}
+
+#[test]
+fn comparison_colors_depend_only_on_the_device_name() {
+    // Pinned so a chart from an earlier run keeps its colors.
+    assert_eq!([palette_index("alpha"), palette_index("beta"), palette_index("gamma")], [12, 5, 11]);
+}
+
+#[test]
+fn file_names_follow_the_out_template() {
+    let args = Args::parse(["--out-dir", "out", "--out-template", "{date}_{device}_{type}", "a.log"].map(String::from)).unwrap();
+    let options = ptp_stats::plot::PlotOptions { file_template: args.plot_options.file_template.replace("{date}", "2024-05-01"), ..args.plot_options };
+    assert_eq!(options.path("Beta", "Rolling Std Dev"), PathBuf::from("out/2024-05-01_beta_rolling-std-dev.png"));
+    assert_eq!(options.path("beta", ""), PathBuf::from("out/2024-05-01_beta.png"));
+    assert_eq!(Args::default().plot_options.path("beta", "Offset"), PathBuf::from("plots/beta-offset.png"));
+    assert_eq!(Args::default().plot_options.path("GM 01/eth0", "Offset"), PathBuf::from("plots/gm-01_eth0-offset.png"));
+
+    for template in ["{device}", "{date}/{device}-{type}", "{device}-{type}-{host}"] {
+        assert!(Args::parse(["--out-template", template, "a.log"].map(String::from)).is_err());
+    }
+}
+
+#[test]
+fn opacity_is_a_fraction_above_zero() {
+    assert_eq!(Args::parse(["--opacity", "0.25", "a.log"].map(String::from)).unwrap().plot_options.opacity, 0.25);
+    assert_eq!(Args::default().plot_options.opacity, 1.0);
+    for opacity in ["0", "1.5", "NaN"] {
+        assert!(Args::parse(["--opacity", opacity, "a.log"].map(String::from)).is_err());
+    }
+}
+
+#[test]
+fn grid_and_label_size_are_validated() {
+    let args = Args::parse(["--grid", "coarse", "--label-size", "16", "a.log"].map(String::from)).unwrap();
+    assert_eq!((args.plot_options.grid, args.plot_options.label_size), (Grid::Coarse, Some(16)));
+    assert_eq!(Args::default().plot_options.grid, Grid::Full);
+    assert!(Args::parse(["--grid", "dense", "a.log"].map(String::from)).is_err());
+    assert!(Args::parse(["--label-size", "0", "a.log"].map(String::from)).is_err());
+}
+
+#[test]
+fn histogram_draws_the_normal_fit_on_request() {
+    let out_dir = std::env::temp_dir().join(format!("ptp-stats-hist-{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let options = ptp_stats::plot::PlotOptions { out_dir: out_dir.clone(), format: ImageFormat::Svg, ..Args::default().plot_options };
+    let offsets: Vec<f64> = (0..200).map(|i| ((i * 37) % 101) as f64 - 50.0).collect();
+
+    create_histogram(&options, &offsets, "fit", "Offset", 20, true).unwrap();
+    assert!(std::fs::read_to_string(options.path("fit", "Offset-hist")).unwrap().contains("Normal fit"));
+    create_histogram(&options, &offsets, "plain", "Offset", 20, false).unwrap();
+    assert!(!std::fs::read_to_string(options.path("plain", "Offset-hist")).unwrap().contains("Normal fit"));
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn box_whiskers_stop_at_the_last_sample_within_one_and_a_half_iqr() {
+    let mut values: Vec<f64> = (1..=9).map(f64::from).collect();
+    values.push(40.0);
+    let stats = BoxStats::from_values(&values).unwrap();
+
+    assert!(stats.q1 < stats.median && stats.median < stats.q3);
+    assert_eq!(stats.low, 1.0);
+    assert_eq!(stats.high, 9.0);
+    assert_eq!(stats.outliers, vec![40.0]);
+    assert!(BoxStats::from_values(&[]).is_none());
+}
+
+#[test]
+fn internal_axis_keeps_the_monotonic_timestamps_as_logged() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/valid.log");
+    let log = Parser::new(None, TimeWindow::default()).parse_file(&path, &Progress::hidden()).unwrap();
+    let options = ptp_stats::plot::PlotOptions { format: ImageFormat::Ascii, ..Args::default().plot_options };
+    let offsets: Vec<f64> = log.records.iter().map(|r| r.offset).collect();
+
+    let x_labels = |x_axis| {
+        let lines = ascii_offset_plot(&options, &TimeAxis::from_records(&log.records, x_axis), &offsets);
+        lines[lines.len() - 1].split_whitespace().map(String::from).collect::<Vec<_>>()
+    };
+    assert_eq!(x_labels(XAxis::Internal), ["1000.6", "1002.6"]);
+    assert_eq!(x_labels(XAxis::Elapsed), ["0", "2"]);
+}
+
+#[test]
+fn diff_plot_needs_timestamps_in_common() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/valid.log");
+    let log = Parser::new(None, TimeWindow::default()).parse_file(&path, &Progress::hidden()).unwrap();
+    let out_dir = std::env::temp_dir().join(format!("ptp-stats-diff-{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let options = ptp_stats::plot::PlotOptions { out_dir: out_dir.clone(), format: ImageFormat::Svg, ..Args::default().plot_options };
+
+    create_diff_plot(&options, &log.records, &log.records, ("a", "b")).unwrap();
+    assert!(std::fs::read_to_string(options.path("a vs b", "Offset Difference")).unwrap().contains("Difference (ns)"));
+    let untimed: Vec<_> = log.records.iter().map(|record| ptp_stats::record::Record { timestamp: None, ..record.clone() }).collect();
+    assert!(create_diff_plot(&options, &log.records, &untimed, ("a", "c")).is_err());
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn subtitle_gives_the_sample_count_time_span_and_rms() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/valid.log");
+    let log = Parser::new(None, TimeWindow::default()).parse_file(&path, &Progress::hidden()).unwrap();
+    let subtitle = metadata_subtitle(&log.records, 1500.0, ptp_stats::unit::Unit::Us, 1);
+    assert_eq!(subtitle, "3 samples, 2024-05-01 12:00:00 to 12:00:02, RMS offset 1.5 µs");
+    let untimed: Vec<_> = log.records.iter().map(|record| ptp_stats::record::Record { timestamp: None, ..record.clone() }).collect();
+    assert_eq!(metadata_subtitle(&untimed, 17.0, ptp_stats::unit::Unit::Ns, 2), "3 samples, RMS offset 17.00 ns");
+}
@@ -0,0 +1,29 @@
+use chrono::DateTime;
+use ptp_stats::record::{Record, Source};
+
+fn record() -> Record {
+    Record {
+        source: Source::Ptp4l,
+        timestamp: Some(DateTime::parse_from_rfc3339("2024-05-01T12:00:00-04:00").unwrap()),
+        internal_ts: Some(1000.5),
+        offset: -27.0,
+        max_offset: None,
+        servo_state: Some(2),
+        freq: Some(1345.5),
+        delay: Some(601.0),
+    }
+}
+
+#[test]
+fn influx_line_has_tags_fields_and_nanosecond_time() {
+    assert_eq!(
+        record().to_influx("rack 1,a=b").unwrap(),
+        "ptp,device=rack\\ 1\\,a\\=b,source=ptp4l offset=-27,delay=601,freq=1345.5,servo_state=2i 1714579200000000000"
+    );
+}
+
+#[test]
+fn influx_skips_untimed_samples() {
+    let untimed = Record { timestamp: None, ..record() };
+    assert_eq!(untimed.to_influx("beta"), None);
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::record;
+use ptp_stats::record::Record;
+use ptp_stats::servo::{self, StateRun};
+
+/// One sample a second in each of `states`.
+fn records(states: &[Option<u8>]) -> Vec<Record> {
+    states.iter().enumerate().map(|(i, &servo_state)| Record { servo_state, ..record(i as i64 * 1000, 0.0) }).collect()
+}
+
+#[test]
+fn runs_split_where_the_state_changes() {
+    let runs = servo::state_runs(&records(&[Some(0), Some(2), Some(2), None, Some(1), Some(2)]));
+    assert_eq!(
+        runs,
+        [
+            StateRun { state: 0, index: 0, samples: 1, duration: Some(1.0) },
+            StateRun { state: 2, index: 1, samples: 2, duration: Some(3.0) },
+            StateRun { state: 1, index: 4, samples: 1, duration: Some(1.0) },
+            StateRun { state: 2, index: 5, samples: 1, duration: Some(0.0) },
+        ]
+    );
+}
+
+#[test]
+fn no_states_no_runs() {
+    assert!(servo::state_runs(&records(&[None, None])).is_empty());
+}
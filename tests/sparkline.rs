@@ -0,0 +1,18 @@
+use ptp_stats::sparkline::sparkline;
+
+#[test]
+fn scales_from_lowest_to_highest() {
+    assert_eq!(sparkline(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0], 8), "▁▂▃▄▅▆▇█");
+}
+
+#[test]
+fn averages_down_to_width() {
+    assert_eq!(sparkline(&[0.0, 0.0, 10.0, 10.0, 5.0, 5.0], 3), "▁█▅");
+    assert_eq!(sparkline(&[1.0, 2.0], 10).chars().count(), 2);
+}
+
+#[test]
+fn flat_and_empty_series() {
+    assert_eq!(sparkline(&[3.0, 3.0, 3.0], 3), "▁▁▁");
+    assert_eq!(sparkline(&[], 5), "");
+}
@@ -0,0 +1,19 @@
+use ptp_stats::spectrum::{amplitude_spectrum, peak};
+use std::f64::consts::PI;
+
+#[test]
+fn finds_period_of_a_sine() {
+    let data: Vec<f64> = (0..1024).map(|i| 100.0 + 40.0 * (2.0 * PI * i as f64 / 64.0).sin()).collect();
+    let spectrum = amplitude_spectrum(&data, 0.5);
+
+    let (freq, amplitude) = peak(&spectrum).unwrap();
+    assert!((1.0 / freq - 32.0).abs() < 1e-9);
+    assert!((amplitude - 40.0).abs() < 1e-6);
+    assert_eq!(spectrum.len(), 512);
+}
+
+#[test]
+fn empty_for_too_few_samples_or_no_interval() {
+    assert!(amplitude_spectrum(&[1.0], 1.0).is_empty());
+    assert!(amplitude_spectrum(&[1.0, 2.0, 3.0], 0.0).is_empty());
+}
@@ -0,0 +1,95 @@
+use ptp_stats::demo;
+use ptp_stats::stats::{autocorrelation, beyond_sigma, decorrelation_lag, detect_changepoints, first_difference, weighted_mean_std_dev, Reservoir};
+
+fn demo_offsets(name: &str) -> Vec<f64> {
+    demo::records(name).unwrap().iter().map(|record| record.offset).collect()
+}
+
+#[test]
+fn weights_values_by_duration() {
+    let (mean, std_dev) = weighted_mean_std_dev(&[100.0, 0.0], &[1.0, 3.0]);
+    assert_eq!(mean, 25.0);
+    assert!((std_dev - 1875.0_f64.sqrt()).abs() < 1e-9);
+}
+
+#[test]
+fn equal_weights_give_the_population_stats() {
+    let (mean, std_dev) = weighted_mean_std_dev(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], &[2.0; 8]);
+    assert_eq!(mean, 5.0);
+    assert_eq!(std_dev, 2.0);
+}
+
+#[test]
+fn zero_weights_are_nan() {
+    let (mean, std_dev) = weighted_mean_std_dev(&[1.0, 2.0], &[0.0, 0.0]);
+    assert!(mean.is_nan() && std_dev.is_nan());
+}
+
+#[test]
+fn sinusoid_decorrelates_where_its_cosine_drops_below_one_over_e() {
+    // A sinusoid's autocorrelation is cos(2π lag / period), below 1/e
+    // from 0.19 of a period on.
+    let data: Vec<f64> = (0..10_000).map(|i| (i as f64 * std::f64::consts::TAU / 100.0).sin()).collect();
+    let acf = autocorrelation(&data, 50);
+    assert_eq!(acf.len(), 51);
+    assert!((acf[0] - 1.0).abs() < 1e-12);
+    assert!(acf[50] < -0.99);
+    assert_eq!(decorrelation_lag(&acf), Some(20));
+}
+
+#[test]
+fn alternating_series_is_anticorrelated_at_lag_one() {
+    let data: Vec<f64> = (0..100).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+    let acf = autocorrelation(&data, 3);
+    assert!(acf[1] < -0.9 && acf[2] > 0.9);
+    assert_eq!(decorrelation_lag(&acf), Some(1));
+    assert!(autocorrelation(&[5.0; 10], 3).is_empty());
+}
+
+#[test]
+fn first_difference_is_one_shorter() {
+    assert_eq!(first_difference(&[1.0, 4.0, 2.0]), vec![3.0, -2.0]);
+    assert!(first_difference(&[1.0]).is_empty());
+}
+
+#[test]
+fn changepoints_are_steps_in_the_mean() {
+    // Alternating noise of ±5 with two steps, up by 40 and back down.
+    let data: Vec<f64> = (0..150).map(|i| if i % 2 == 0 { 5.0 } else { -5.0 } + if (50..100).contains(&i) { 40.0 } else { 0.0 }).collect();
+    assert_eq!(detect_changepoints(&data), vec![50, 100]);
+
+    let wander: Vec<f64> = (0..600).map(|i| 40.0 * (i as f64 * std::f64::consts::TAU / 50.0).sin()).collect();
+    assert!(detect_changepoints(&wander).is_empty());
+    // Neither settling nor a one-sample spike is a step.
+    assert!(detect_changepoints(&demo_offsets("demo-stable")).is_empty());
+    assert!(detect_changepoints(&demo_offsets("demo-spike")).is_empty());
+    assert!(detect_changepoints(&[3.0; 100]).is_empty());
+}
+
+#[test]
+fn beyond_sigma_picks_samples_far_from_the_mean() {
+    let mut data = vec![1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0];
+    data[4] = 12.0;
+    assert_eq!(beyond_sigma(&data, 2.0), vec![4]);
+    assert_eq!(beyond_sigma(&data, 10.0), Vec::<usize>::new());
+    assert!(beyond_sigma(&demo_offsets("demo-spike"), 4.0).contains(&400));
+    assert!(beyond_sigma(&[5.0], 1.0).is_empty());
+}
+
+#[test]
+fn reservoir_keeps_a_bounded_uniform_sample() {
+    let mut small = Reservoir::new(10);
+    (0..5).for_each(|i| small.push(i));
+    assert_eq!(small.items(), [0, 1, 2, 3, 4]);
+    assert!(!small.is_sampled());
+
+    let mut reservoir = Reservoir::new(1000);
+    (0..100_000).for_each(|i| reservoir.push(i));
+    assert_eq!((reservoir.items().len(), reservoir.seen()), (1000, 100_000));
+    assert!(reservoir.is_sampled());
+    // Each tenth of the input holds about a tenth of the sample.
+    for decile in 0..10 {
+        let count = reservoir.items().iter().filter(|&&i| i / 10_000 == decile).count();
+        assert!((60..=140).contains(&count), "decile {} has {} items", decile, count);
+    }
+}
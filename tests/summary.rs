@@ -0,0 +1,212 @@
+mod common;
+
+use chrono::DateTime;
+use common::record;
+use ptp_stats::baseline;
+use ptp_stats::check::Thresholds;
+use ptp_stats::demo;
+use ptp_stats::record::Record;
+use ptp_stats::summary::{to_json_document, DeviceSummary, FleetSummary, HourlyStats, MeanShift, Metric, OffsetRate, RankBy, Settling, StatBlock, Trend, TrendDirection, METRICS, SCHEMA_VERSION};
+
+#[test]
+fn metrics_read_their_stat() {
+    let block = StatBlock::from_values(&[1.0, 2.0, 3.0, 10.0]);
+    let value = |name: &str| (Metric::find(name).unwrap().value)(&block);
+
+    assert_eq!(value("mean"), 4.0);
+    assert_eq!(value("min"), 1.0);
+    assert_eq!(value("max"), 10.0);
+    assert_eq!(value("peak_to_peak"), 9.0);
+    assert!(Metric::find("p50").is_none());
+}
+
+#[test]
+fn metric_names_match_the_json_keys() {
+    let json = StatBlock::from_values(&[1.0, 2.0]).to_json();
+    for metric in METRICS.iter().filter(|metric| metric.name != "peak_to_peak") {
+        assert!(json.contains(&format!("\"{}\":", metric.name)), "{}", metric.name);
+    }
+}
+
+/// One sample a second with each of `offsets`.
+fn records(offsets: &[f64]) -> Vec<Record> {
+    offsets.iter().enumerate().map(|(i, &offset)| record(i as i64 * 1000, offset)).collect()
+}
+
+#[test]
+fn settles_after_the_last_excursion() {
+    // Dips inside the bound at 2 s but is out again at 3 s.
+    let settling = Settling::from_records(&records(&[9000.0, 800.0, 40.0, -150.0, 20.0, -30.0]), 100.0).unwrap();
+    assert_eq!(settling.seconds, Some(4.0));
+
+    let settled = Settling::from_records(&records(&[5.0, -3.0]), 100.0).unwrap();
+    assert_eq!(settled.seconds, Some(0.0));
+
+    // The demo spike at 400 s unsettles a log that had settled early on.
+    let demo_settling = |name: &str| Settling::from_records(&demo::records(name).unwrap(), 100.0).unwrap().seconds;
+    assert!(demo_settling("demo-stable").is_some_and(|seconds| seconds < 60.0));
+    assert!(demo_settling("demo-spike").is_some_and(|seconds| seconds > 400.0));
+}
+
+#[test]
+fn unsettled_at_the_end_has_no_settling_time() {
+    let settling = Settling::from_records(&records(&[9000.0, 20.0, 500.0]), 100.0).unwrap();
+    assert_eq!(settling.seconds, None);
+
+    let untimed: Vec<Record> = records(&[5.0]).into_iter().map(|r| Record { timestamp: None, ..r }).collect();
+    assert_eq!(Settling::from_records(&untimed, 100.0), None);
+}
+
+#[test]
+fn ranks_by_delay_jitter_with_single_samples_last() {
+    let with_delays = |device: &str, delays: &[f64]| {
+        let records: Vec<Record> = records(&vec![0.0; delays.len()])
+            .into_iter()
+            .zip(delays)
+            .map(|(r, &delay)| Record { delay: Some(delay), ..r })
+            .collect();
+        DeviceSummary { device: device.to_string(), ..ptp_stats::summarize(&records) }
+    };
+    let summaries = [with_delays("single", &[600.0]), with_delays("steady", &[600.0, 601.0]), with_delays("noisy", &[560.0, 640.0])];
+
+    let ranked: Vec<&str> = RankBy::DelayJitter.worst(&summaries, 3).iter().map(|s| s.device.as_str()).collect();
+    assert_eq!(ranked, ["noisy", "steady", "single"]);
+}
+
+#[test]
+fn json_document_round_trips_through_the_baseline_reader() {
+    let summary = DeviceSummary { device: "gm".to_string(), ..ptp_stats::summarize(&records(&[-30.0, 40.0])) };
+    let generated_at = DateTime::parse_from_rfc3339("2024-03-01T12:00:00+01:00").unwrap();
+    let document = to_json_document(&[summary], &["--json".to_string(), "gm.log".to_string()], generated_at);
+    assert!(document.starts_with(&format!("{{\"schema_version\":{},", SCHEMA_VERSION)));
+    assert!(document.contains("\"generated_at\":\"2024-03-01T12:00:00+01:00\""));
+    assert!(document.contains("\"arguments\":[\"--json\",\"gm.log\"]"));
+
+    let baselines = baseline::from_json(&document).unwrap();
+    assert_eq!(baselines.len(), 1);
+    assert_eq!((baselines[0].device.as_str(), baselines[0].max_offset), ("gm", 40.0));
+
+    // Summaries from before the schema version are a bare device array.
+    let bare = &document[document.find("\"devices\":").unwrap() + 10..document.len() - 1];
+    assert_eq!(baseline::from_json(bare).unwrap(), baselines);
+
+    let newer = document.replacen(&format!(":{},", SCHEMA_VERSION), &format!(":{},", SCHEMA_VERSION + 1), 1);
+    assert!(baseline::from_json(&newer).is_err());
+}
+
+#[test]
+fn trend_needs_a_steep_and_significant_slope() {
+    // One sample a second: 0.01 ns/s is 36 ns/h.
+    let growing: Vec<f64> = (0..600).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 } * (20.0 + 0.01 * i as f64)).collect();
+    let trend = Trend::from_records(&records(&growing), 10.0).unwrap();
+    assert_eq!(trend.direction, TrendDirection::Degrading);
+    assert!((trend.ns_per_hour - 36.0).abs() < 1e-6);
+    assert_eq!(Trend::from_records(&records(&growing), 50.0).unwrap().direction, TrendDirection::Stable);
+
+    let shrinking: Vec<f64> = growing.iter().rev().copied().collect();
+    assert_eq!(Trend::from_records(&records(&shrinking), 10.0).unwrap().direction, TrendDirection::Improving);
+
+    // Steep, but three noisy samples can't tell it from chance.
+    assert_eq!(Trend::from_records(&records(&[10.0, 50.0, 20.0]), 10.0).unwrap().direction, TrendDirection::Stable);
+}
+
+#[test]
+fn offset_rate_is_per_second_when_the_interval_is_known() {
+    let records = records(&[0.0, 10.0, 30.0, 20.0]);
+    let per_sample = OffsetRate::from_records(&records, None).unwrap();
+    assert_eq!(per_sample.per(), "sample");
+    assert_eq!((per_sample.stats.min, per_sample.stats.max), (-10.0, 20.0));
+    let largest = per_sample.largest.unwrap();
+    assert_eq!((largest.value, largest.index), (20.0, 2));
+
+    let per_second = OffsetRate::from_records(&records, Some(0.5)).unwrap();
+    assert_eq!(per_second.per(), "s");
+    assert_eq!(per_second.stats.max, 40.0);
+    assert!(OffsetRate::from_records(&records[..1], Some(1.0)).is_none());
+}
+
+#[test]
+fn hourly_buckets_follow_the_clock_hour() {
+    // 12:00:00 + 3599 s is still the 12:00 hour; the last two are 13:00.
+    let mut records = records(&[1.0; 3602]);
+    records[3600].offset = -9.0;
+    records[3601].offset = 4.0;
+    records.push(Record { timestamp: None, ..records[0].clone() });
+
+    let hours = HourlyStats::buckets(&records);
+    assert_eq!(hours.len(), 2);
+    assert_eq!(hours[0].start.to_rfc3339(), "2024-05-01T12:00:00-04:00");
+    assert_eq!((hours[0].samples, hours[0].max_abs), (3600, 1.0));
+    assert_eq!((hours[1].samples, hours[1].mean, hours[1].max_abs), (2, -2.5, 9.0));
+}
+
+#[test]
+fn offset_riding_on_delay_is_flagged() {
+    let offsets: Vec<f64> = (0..20).map(|i| [10.0, 30.0, 20.0, 40.0][i % 4]).collect();
+    let mut records = records(&offsets);
+    for record in &mut records {
+        record.delay = Some(500.0 + 2.0 * record.offset);
+    }
+    let summary = ptp_stats::summarize(&records);
+    assert!((summary.offset_delay_level_correlation.unwrap() - 1.0).abs() < 1e-9);
+    assert!(summary.asymmetry_suspected());
+    assert!((summary.delay_offset_ratio().unwrap() - 22.0).abs() < 1e-9);
+
+    // Two samples always correlate perfectly, so say nothing.
+    assert_eq!(ptp_stats::summarize(&records[..2]).offset_delay_level_correlation, None);
+}
+
+#[test]
+fn negative_delays_are_counted_from_the_first() {
+    let mut records = records(&[1.0; 12]);
+    assert_eq!(ptp_stats::summarize(&records).negative_delays, None);
+    records[4].delay = Some(-15.0);
+    records[9].delay = Some(-1.0);
+    let negative = ptp_stats::summarize(&records).negative_delays.unwrap();
+    assert_eq!((negative.count, negative.first.index, negative.first.value), (2, 4, -15.0));
+}
+
+#[test]
+fn mean_shifts_carry_the_levels_either_side() {
+    let offsets: Vec<f64> = (0..60).map(|i| if i < 30 { [1.0, -1.0][i % 2] } else { 20.0 + [1.0, -1.0][i % 2] }).collect();
+    let shifts = ptp_stats::summarize(&records(&offsets)).mean_shifts.unwrap();
+    assert_eq!(shifts, [MeanShift { index: 30, timestamp: records(&offsets)[30].timestamp, before: 0.0, after: 20.0 }]);
+    // A one-sample spike leaves the level either side of it alone.
+    assert_eq!(ptp_stats::summarize(&demo::records("demo-spike").unwrap()).mean_shifts, Some(vec![]));
+}
+
+#[test]
+fn fleet_pools_every_device_sample() {
+    let mut quiet = ptp_stats::summarize(&records(&[1.0, -1.0, 1.0]));
+    quiet.device = "quiet".to_string();
+    let mut noisy = ptp_stats::summarize(&records(&[10.0, -10.0, 10.0]));
+    noisy.device = "noisy".to_string();
+
+    let fleet = FleetSummary::from_summaries(&[quiet, noisy]).unwrap();
+    assert_eq!((fleet.devices, fleet.samples), (2, 6));
+    assert!((fleet.pooled_rms - 50.5_f64.sqrt()).abs() < 1e-9);
+    assert!((fleet.median_rms - 5.5).abs() < 1e-9);
+    assert_eq!((fleet.worst_device.as_str(), fleet.max_abs_offset_device.as_str()), ("noisy", "noisy"));
+    assert_eq!(FleetSummary::from_summaries(&[]), None);
+}
+
+#[test]
+fn ts2phc_samples_carry_no_delay_statistics() {
+    let ts2phc: Vec<Record> = (0..12)
+        .map(|i| ptp_stats::parse_line(&format!("ts2phc[{}.1]: eth0 master offset {} s2 freq -12", 1000 + i, i % 3)).unwrap())
+        .collect();
+    let summary = ptp_stats::summarize(&ts2phc);
+    assert_eq!(summary.delay, None);
+    assert_eq!((&summary.worst_delay, &summary.negative_delays, summary.delay_jitter(), summary.delay_offset_ratio()), (&None, &None, None, None));
+    assert_eq!((summary.delay_offset_correlation, summary.offset_delay_level_correlation), (None, None));
+    assert!(summary.to_json().contains("\"delay\":null"));
+    let limits = Thresholds { max_delay_ns: Some(1.0), max_delay_jitter_ns: Some(1.0), ..Thresholds::default() };
+    assert!(limits.breaches(&summary).is_empty());
+    assert_eq!(limits.delay_level(&summary), None);
+
+    // Mixed in with ptp4l samples, they leave the delay mean alone.
+    let mut mixed = records(&[1.0; 4]);
+    mixed.extend(ts2phc);
+    let delay = ptp_stats::summarize(&mixed).delay.unwrap();
+    assert_eq!((delay.mean, delay.min), (600.0, 600.0));
+}
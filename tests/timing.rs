@@ -0,0 +1,62 @@
+mod common;
+
+use common::record;
+use ptp_stats::record::Record;
+use ptp_stats::timing;
+
+#[test]
+fn resamples_irregular_samples_onto_a_uniform_grid() {
+    // A sample went missing at 2 s and another came late at 3.5 s.
+    let records = [record(0, 0.0), record(1000, 10.0), record(3500, 60.0), record(4000, 0.0)];
+    assert_eq!(timing::resample(&records, 1.0).unwrap(), [0.0, 10.0, 30.0, 50.0, 0.0]);
+}
+
+#[test]
+fn resampling_needs_timestamps() {
+    let untimed = Record { timestamp: None, ..record(0, 5.0) };
+    assert_eq!(timing::resample(&[record(0, 1.0), untimed], 1.0), None);
+    assert_eq!(timing::resample(&[record(0, 1.0)], 1.0).unwrap(), [1.0]);
+}
+
+#[test]
+fn integrates_abs_offset_by_the_trapezoidal_rule() {
+    // 10 ns for a second, then a ramp from 10 to -30 over two seconds.
+    let records = [record(0, 10.0), record(1000, 10.0), record(3000, -30.0)];
+    assert_eq!(timing::integrate_abs(&records), 10.0 + 40.0);
+
+    let untimed = [Record { timestamp: None, ..record(0, 10.0) }, record(1000, 10.0)];
+    assert_eq!(timing::integrate_abs(&untimed), 0.0);
+}
+
+#[test]
+fn completeness_counts_samples_against_the_span() {
+    // Five samples expected over 4 s at 1 s, one missing.
+    let records = [record(0, 0.0), record(1000, 0.0), record(3000, 0.0), record(4000, 0.0)];
+    assert_eq!(timing::completeness_pct(&records, 1.0), Some(80.0));
+    assert_eq!(timing::completeness_pct(&records, 0.5), Some(100.0 * 4.0 / 9.0));
+    assert_eq!(timing::completeness_pct(&records[..1], 1.0), None);
+}
+
+#[test]
+fn counts_and_sorts_out_of_order_timestamps() {
+    let mut records = vec![record(0, 0.0), record(2000, 2.0), record(1000, 1.0), record(1000, 1.5), record(3000, 3.0)];
+    assert_eq!(timing::OutOfOrder::count(&records), timing::OutOfOrder { duplicates: 1, backward: 1 });
+
+    timing::sort_by_time(&mut records);
+    let offsets: Vec<f64> = records.iter().map(|r| r.offset).collect();
+    assert_eq!(offsets, [0.0, 1.0, 1.5, 2.0, 3.0]);
+    assert_eq!(timing::OutOfOrder::count(&records).backward, 0);
+}
+
+#[test]
+fn warmup_counts_leading_samples_or_seconds() {
+    let records: Vec<Record> = (0..10).map(|i| record(i * 500, 0.0)).collect();
+    assert_eq!(timing::Warmup::Samples(3).count(&records), 3);
+    assert_eq!(timing::Warmup::Samples(30).count(&records), 10);
+    assert_eq!(timing::Warmup::Seconds(2.0).count(&records), 4);
+
+    let monotonic: Vec<Record> = (0..10).map(|i| Record { timestamp: None, internal_ts: Some(100.0 + i as f64), ..record(0, 0.0) }).collect();
+    assert_eq!(timing::Warmup::Seconds(2.5).count(&monotonic), 3);
+    let untimed: Vec<Record> = (0..10).map(|_| Record { timestamp: None, ..record(0, 0.0) }).collect();
+    assert_eq!(timing::Warmup::Seconds(2.5).count(&untimed), 0);
+}